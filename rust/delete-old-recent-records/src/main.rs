@@ -1,29 +1,82 @@
-use tokio_postgres::{NoTls};
-use std::env;
+use anyhow::{Context, Result};
+use clap::Parser;
+use processor::{
+    retention::{Retention, RetentionConfig, RetentionPolicy},
+    utils::database::new_db_pool,
+};
+use serde::{Deserialize, Serialize};
+use std::{env, path::PathBuf};
 
-#[tokio::main]
-async fn main() -> Result<(), tokio_postgres::Error> {
-    let database_url = env::var("DATABASE_URL")
-        .expect("DATABASE_URL environment variable not set");
-    let period = env::var("PERIOD").unwrap_or_else(|_| "30".to_string());
+// Tables cleaned up when no config file is given
+const LEGACY_TABLES: [&str; 12] = [
+    "events", "transactions", "user_transactions", "signatures",
+    "current_collection_datas", "current_collections_v2", "current_token_datas", "current_token_datas_v2",
+    "current_token_ownerships", "current_token_ownerships_v2", "current_token_v2_metadata",
+    "move_resources",
+];
+
+/// Applies retention policies once and exits, meant to run as a scheduled job.
+/// Without `--config-path` the old behavior is kept: DATABASE_URL and PERIOD (days) from the
+/// environment, applied to a fixed list of tables.
+#[derive(Parser)]
+struct Args {
+    #[clap(short, long, value_parser)]
+    config_path: Option<PathBuf>,
+}
 
-    let (client, connection) = tokio_postgres::connect(&database_url, NoTls).await.expect("Could not connect to database");
+#[derive(Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+struct RetentionJobConfig {
+    postgres_connection_string: String,
+    retention: RetentionConfig,
+}
 
-    println!("Removing outdated recent records (older than {} days)", period);
+fn legacy_config() -> Result<RetentionJobConfig> {
+    let database_url = env::var("DATABASE_URL")
+        .context("DATABASE_URL environment variable not set")?;
+    let period = env::var("PERIOD").unwrap_or_else(|_| "30".to_string());
+    let max_age_days = period.parse::<u32>().context("PERIOD must be a number of days")?;
+    Ok(RetentionJobConfig {
+        postgres_connection_string: database_url,
+        retention: RetentionConfig {
+            interval_secs: RetentionConfig::default_interval_secs(),
+            batch_size: RetentionConfig::default_batch_size(),
+            batch_delay_ms: RetentionConfig::default_batch_delay_ms(),
+            dry_run: false,
+            policies: LEGACY_TABLES
+                .iter()
+                .map(|table| RetentionPolicy {
+                    table: table.to_string(),
+                    max_age_days,
+                    age_column: RetentionPolicy::default_age_column(),
+                    keep_latest_per_key: None,
+                    keep_if_referenced: vec![],
+                })
+                .collect(),
+        },
+    })
+}
 
-    tokio::spawn(async move {
-        if let Err(e) = connection.await {
-            eprintln!("DB connection error: {}", e);
-        }
-    });
+#[tokio::main]
+async fn main() -> Result<()> {
+    server_framework::setup_logging();
+    let args = Args::parse();
+    let config = match &args.config_path {
+        Some(path) => server_framework::load::<RetentionJobConfig>(path)?,
+        None => legacy_config()?,
+    };
 
-    for table in ["events", "transactions", "user_transactions", "signatures",
-        "current_collection_datas", "current_collections_v2", "current_token_datas", "current_token_datas_v2",
-        "current_token_ownerships", "current_token_ownerships_v2", "current_token_v2_metadata",
-        "move_resources"
-    ] {
-        println!("table: {}", table);
-        client.simple_query(&format!("DELETE FROM {} WHERE inserted_at < now() - interval '{} days'", table, period)).await?;
+    let pool = new_db_pool(&config.postgres_connection_string, Some(1))
+        .await
+        .context("Could not connect to database")?;
+    let retention = Retention::new(pool, config.retention)?;
+    for report in retention.run_once().await? {
+        println!(
+            "table: {} rows: {}{}",
+            report.table,
+            report.rows,
+            if report.dry_run { " (dry run)" } else { "" }
+        );
     }
 
     println!("Done");
@@ -0,0 +1,48 @@
+use anyhow::{Context, Result};
+use clap::Parser;
+use processor::{
+    partition_manager::{PartitionConfig, PartitionManager},
+    utils::database::new_db_pool,
+};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+/// Converts the tables in `partitioning` that aren't partitioned yet and exits. Each conversion
+/// holds an ACCESS EXCLUSIVE lock on its table, so run it during a maintenance window, before
+/// starting processors with the same `partitioning` config.
+#[derive(Parser)]
+struct Args {
+    #[clap(short, long, value_parser)]
+    config_path: PathBuf,
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+struct PartitionJobConfig {
+    postgres_connection_string: String,
+    partitioning: PartitionConfig,
+    // Lowest version partitions are created for, as `starting_version` in the processor config
+    starting_version: Option<u64>,
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    server_framework::setup_logging();
+    let args = Args::parse();
+    let config = server_framework::load::<PartitionJobConfig>(&args.config_path)?;
+
+    let pool = new_db_pool(&config.postgres_connection_string, Some(1))
+        .await
+        .context("Could not connect to database")?;
+    let partition_manager = PartitionManager::new(
+        pool,
+        config.partitioning,
+        config.starting_version.unwrap_or(0),
+    )?;
+    for table in partition_manager.convert_unpartitioned().await? {
+        println!("converted: {}", table);
+    }
+
+    println!("Done");
+    Ok(())
+}
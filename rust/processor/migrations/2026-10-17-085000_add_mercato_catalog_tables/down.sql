@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE IF EXISTS recent_action;
+DROP TABLE IF EXISTS action;
+DROP TABLE IF EXISTS nft_meta;
@@ -0,0 +1,53 @@
+-- Catalog tables written by mercato_indexer_processor. They usually already exist in the catalog
+-- database; this is the shape the processor expects, matching src/db/postgres/schema.rs
+CREATE TABLE IF NOT EXISTS nft_meta (
+  id UUID NOT NULL,
+  name TEXT NOT NULL,
+  token_id TEXT NOT NULL,
+  collection_id UUID NOT NULL,
+  chain_id UUID NOT NULL,
+  smart_contract_id UUID NOT NULL,
+  properties JSONB NOT NULL,
+  mint_tx TEXT NOT NULL,
+  owner TEXT NOT NULL,
+  owner_block_height BIGINT NOT NULL,
+  owner_tx_id TEXT NOT NULL,
+  PRIMARY KEY (id)
+);
+-- Mints are inserted with ON CONFLICT (collection_id, token_id) DO NOTHING
+CREATE UNIQUE INDEX IF NOT EXISTS nm_collection_id_token_id_index ON nft_meta (collection_id, token_id);
+CREATE INDEX IF NOT EXISTS nm_token_id_index ON nft_meta (token_id);
+
+CREATE TABLE IF NOT EXISTS action (
+  tx_id TEXT NOT NULL,
+  tx_index BIGINT NOT NULL,
+  action TEXT NOT NULL,
+  seller TEXT,
+  buyer TEXT,
+  block_height BIGINT NOT NULL,
+  block_time TIMESTAMP NOT NULL,
+  nonce BIGINT NOT NULL,
+  collection_id UUID NOT NULL,
+  nft_meta_id UUID NOT NULL,
+  smart_contract_id UUID NOT NULL,
+  PRIMARY KEY (tx_id, tx_index)
+);
+CREATE INDEX IF NOT EXISTS a_nft_meta_id_index ON action (nft_meta_id);
+CREATE INDEX IF NOT EXISTS a_collection_id_block_time_index ON action (collection_id, block_time);
+
+-- Same rows as action, kept for recent activity feeds
+CREATE TABLE IF NOT EXISTS recent_action (
+  tx_id TEXT NOT NULL,
+  tx_index BIGINT NOT NULL,
+  action TEXT NOT NULL,
+  seller TEXT,
+  buyer TEXT,
+  block_height BIGINT NOT NULL,
+  block_time TIMESTAMP NOT NULL,
+  nonce BIGINT NOT NULL,
+  collection_id UUID NOT NULL,
+  nft_meta_id UUID NOT NULL,
+  smart_contract_id UUID NOT NULL,
+  PRIMARY KEY (tx_id, tx_index)
+);
+CREATE INDEX IF NOT EXISTS ra_collection_id_block_time_index ON recent_action (collection_id, block_time);
@@ -0,0 +1,2 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE IF EXISTS mercato_indexer_dead_letters;
\ No newline at end of file
@@ -0,0 +1,13 @@
+-- Batches MercatoIndexerProcessor failed to write after all retries
+CREATE TABLE IF NOT EXISTS mercato_indexer_dead_letters (
+  id BIGSERIAL NOT NULL,
+  processor VARCHAR(50) NOT NULL,
+  start_version BIGINT NOT NULL,
+  end_version BIGINT NOT NULL,
+  nft_meta JSONB NOT NULL,
+  actions JSONB NOT NULL,
+  error TEXT NOT NULL,
+  inserted_at TIMESTAMP NOT NULL DEFAULT NOW(),
+  PRIMARY KEY (id)
+);
+CREATE INDEX IF NOT EXISTS mida_start_version_index ON mercato_indexer_dead_letters (start_version);
@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE IF EXISTS marketplace_activities;
+DROP TABLE IF EXISTS current_listings;
+DROP TABLE IF EXISTS current_bids;
@@ -0,0 +1,61 @@
+-- Decoded marketplace events, one row per event
+CREATE TABLE IF NOT EXISTS marketplace_activities (
+  transaction_version BIGINT NOT NULL,
+  event_index BIGINT NOT NULL,
+  marketplace VARCHAR(50) NOT NULL,
+  contract_address VARCHAR(66) NOT NULL,
+  event_type VARCHAR(50) NOT NULL,
+  listing_id VARCHAR(66),
+  offer_id VARCHAR(66),
+  token_data_id VARCHAR(66),
+  collection_id VARCHAR(66),
+  price NUMERIC NOT NULL,
+  token_amount NUMERIC,
+  seller VARCHAR(66),
+  buyer VARCHAR(66),
+  transaction_timestamp TIMESTAMP NOT NULL,
+  inserted_at TIMESTAMP NOT NULL DEFAULT NOW(),
+  PRIMARY KEY (transaction_version, event_index)
+);
+CREATE INDEX IF NOT EXISTS ma_token_data_id_index ON marketplace_activities (token_data_id);
+CREATE INDEX IF NOT EXISTS ma_collection_id_index ON marketplace_activities (collection_id);
+CREATE INDEX IF NOT EXISTS ma_insat_index ON marketplace_activities (inserted_at);
+
+-- Latest state of every listing
+CREATE TABLE IF NOT EXISTS current_listings (
+  listing_id VARCHAR(66) NOT NULL,
+  marketplace VARCHAR(50) NOT NULL,
+  contract_address VARCHAR(66) NOT NULL,
+  token_data_id VARCHAR(66) NOT NULL,
+  collection_id VARCHAR(66) NOT NULL,
+  seller VARCHAR(66) NOT NULL,
+  price NUMERIC NOT NULL,
+  is_active BOOLEAN NOT NULL,
+  last_transaction_version BIGINT NOT NULL,
+  last_transaction_timestamp TIMESTAMP NOT NULL,
+  inserted_at TIMESTAMP NOT NULL DEFAULT NOW(),
+  PRIMARY KEY (listing_id)
+);
+CREATE INDEX IF NOT EXISTS cl_token_data_id_active_index ON current_listings (token_data_id) WHERE is_active;
+CREATE INDEX IF NOT EXISTS cl_collection_id_index ON current_listings (collection_id);
+CREATE INDEX IF NOT EXISTS cl_seller_index ON current_listings (seller);
+
+-- Latest state of every token and collection offer
+CREATE TABLE IF NOT EXISTS current_bids (
+  offer_id VARCHAR(66) NOT NULL,
+  marketplace VARCHAR(50) NOT NULL,
+  contract_address VARCHAR(66) NOT NULL,
+  token_data_id VARCHAR(66),
+  collection_id VARCHAR(66),
+  buyer VARCHAR(66) NOT NULL,
+  price NUMERIC NOT NULL,
+  token_amount NUMERIC,
+  is_active BOOLEAN NOT NULL,
+  last_transaction_version BIGINT NOT NULL,
+  last_transaction_timestamp TIMESTAMP NOT NULL,
+  inserted_at TIMESTAMP NOT NULL DEFAULT NOW(),
+  PRIMARY KEY (offer_id)
+);
+CREATE INDEX IF NOT EXISTS cb_token_data_id_index ON current_bids (token_data_id);
+CREATE INDEX IF NOT EXISTS cb_collection_id_index ON current_bids (collection_id);
+CREATE INDEX IF NOT EXISTS cb_buyer_index ON current_bids (buyer);
@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE IF EXISTS current_collection_stats;
+DROP TABLE IF EXISTS collection_stats_hourly;
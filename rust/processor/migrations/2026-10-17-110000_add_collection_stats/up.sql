@@ -0,0 +1,32 @@
+-- Latest stats of every collection, refreshed whenever a batch touches the collection
+CREATE TABLE IF NOT EXISTS current_collection_stats (
+  collection_id VARCHAR(66) NOT NULL,
+  current_supply NUMERIC,
+  max_supply NUMERIC,
+  total_minted NUMERIC,
+  unique_holders BIGINT NOT NULL DEFAULT 0,
+  floor_price NUMERIC,
+  volume_24h NUMERIC NOT NULL DEFAULT 0,
+  volume_7d NUMERIC NOT NULL DEFAULT 0,
+  total_volume NUMERIC NOT NULL DEFAULT 0,
+  sales_count BIGINT NOT NULL DEFAULT 0,
+  last_transaction_version BIGINT NOT NULL,
+  last_transaction_timestamp TIMESTAMP NOT NULL,
+  inserted_at TIMESTAMP NOT NULL DEFAULT NOW(),
+  PRIMARY KEY (collection_id)
+);
+CREATE INDEX IF NOT EXISTS ccs_volume_24h_index ON current_collection_stats (volume_24h);
+
+-- Activity per collection per hour
+CREATE TABLE IF NOT EXISTS collection_stats_hourly (
+  collection_id VARCHAR(66) NOT NULL,
+  bucket_start TIMESTAMP NOT NULL,
+  mint_count BIGINT NOT NULL,
+  burn_count BIGINT NOT NULL,
+  transfer_count BIGINT NOT NULL,
+  sales_count BIGINT NOT NULL,
+  volume NUMERIC NOT NULL,
+  inserted_at TIMESTAMP NOT NULL DEFAULT NOW(),
+  PRIMARY KEY (collection_id, bucket_start)
+);
+CREATE INDEX IF NOT EXISTS csh_bucket_start_index ON collection_stats_hourly (bucket_start);
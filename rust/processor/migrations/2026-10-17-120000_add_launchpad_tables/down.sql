@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE IF EXISTS launchpad_mints;
+DROP TABLE IF EXISTS launchpad_mint_stages;
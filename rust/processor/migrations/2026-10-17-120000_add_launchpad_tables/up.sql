@@ -0,0 +1,34 @@
+-- Latest configuration of each mint stage of a launchpad collection
+CREATE TABLE IF NOT EXISTS launchpad_mint_stages (
+  collection_id VARCHAR(66) NOT NULL,
+  stage VARCHAR(100) NOT NULL,
+  contract_address VARCHAR(66) NOT NULL,
+  is_allowlist BOOLEAN NOT NULL,
+  price NUMERIC NOT NULL,
+  per_wallet_limit NUMERIC,
+  start_time TIMESTAMP NOT NULL,
+  end_time TIMESTAMP NOT NULL,
+  last_transaction_version BIGINT NOT NULL,
+  last_transaction_timestamp TIMESTAMP NOT NULL,
+  inserted_at TIMESTAMP NOT NULL DEFAULT NOW(),
+  PRIMARY KEY (collection_id, stage)
+);
+
+-- Every mint made through the launchpad
+CREATE TABLE IF NOT EXISTS launchpad_mints (
+  transaction_version BIGINT NOT NULL,
+  event_index BIGINT NOT NULL,
+  contract_address VARCHAR(66) NOT NULL,
+  collection_id VARCHAR(66) NOT NULL,
+  token_data_id VARCHAR(66),
+  stage VARCHAR(100),
+  mint_type VARCHAR(20) NOT NULL,
+  minter VARCHAR(66) NOT NULL,
+  price NUMERIC NOT NULL,
+  amount NUMERIC NOT NULL,
+  transaction_timestamp TIMESTAMP NOT NULL,
+  inserted_at TIMESTAMP NOT NULL DEFAULT NOW(),
+  PRIMARY KEY (transaction_version, event_index)
+);
+CREATE INDEX IF NOT EXISTS lm_collection_id_index ON launchpad_mints (collection_id);
+CREATE INDEX IF NOT EXISTS lm_minter_collection_id_index ON launchpad_mints (minter, collection_id);
@@ -0,0 +1,6 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX IF EXISTS lt_status_next_attempt_at_index;
+ALTER TABLE launchpad_transactions
+  DROP COLUMN IF EXISTS status,
+  DROP COLUMN IF EXISTS next_attempt_at,
+  DROP COLUMN IF EXISTS processed_at;
@@ -0,0 +1,9 @@
+-- Work queue state of launchpad_transactions, see launchpad_queue.rs
+ALTER TABLE launchpad_transactions
+  ADD COLUMN IF NOT EXISTS status VARCHAR(20) NOT NULL DEFAULT 'pending',
+  ADD COLUMN IF NOT EXISTS next_attempt_at TIMESTAMP NOT NULL DEFAULT NOW(),
+  ADD COLUMN IF NOT EXISTS processed_at TIMESTAMP;
+CREATE INDEX IF NOT EXISTS lt_status_next_attempt_at_index ON launchpad_transactions (status, next_attempt_at);
+-- Rows written before the queue existed are held in the legacy status, which the queue never
+-- claims, as the previous consumer may have handled them. See the README for replaying them
+UPDATE launchpad_transactions SET status = 'legacy' WHERE status = 'pending';
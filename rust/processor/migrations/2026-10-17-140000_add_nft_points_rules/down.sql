@@ -0,0 +1,9 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX IF EXISTS np_rv_pt_idx;
+DELETE FROM nft_points WHERE rules_version != 'legacy' OR event_index != -1;
+ALTER TABLE nft_points DROP CONSTRAINT IF EXISTS nft_points_pkey;
+ALTER TABLE nft_points
+  DROP COLUMN IF EXISTS event_index,
+  DROP COLUMN IF EXISTS rule_name,
+  DROP COLUMN IF EXISTS rules_version;
+ALTER TABLE nft_points ADD PRIMARY KEY (transaction_version);
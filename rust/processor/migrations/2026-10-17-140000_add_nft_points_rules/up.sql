@@ -0,0 +1,10 @@
+-- A transaction can now award points through several rules and events, and replays with a new
+-- rules version are kept next to the old rows
+ALTER TABLE nft_points
+  ADD COLUMN IF NOT EXISTS event_index BIGINT NOT NULL DEFAULT -1,
+  ADD COLUMN IF NOT EXISTS rule_name TEXT NOT NULL DEFAULT 'nft_points_contract',
+  ADD COLUMN IF NOT EXISTS rules_version TEXT NOT NULL DEFAULT 'legacy';
+ALTER TABLE nft_points DROP CONSTRAINT IF EXISTS nft_points_transaction_version_key;
+ALTER TABLE nft_points DROP CONSTRAINT IF EXISTS nft_points_pkey;
+ALTER TABLE nft_points ADD PRIMARY KEY (transaction_version, event_index, rule_name, rules_version);
+CREATE INDEX IF NOT EXISTS np_rv_pt_idx ON nft_points (rules_version, point_type);
@@ -0,0 +1,9 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX IF EXISTS acc_first_ts_index;
+DROP INDEX IF EXISTS acc_last_ts_index;
+ALTER TABLE accounts DROP COLUMN IF EXISTS first_transaction_version,
+  DROP COLUMN IF EXISTS first_transaction_timestamp,
+  DROP COLUMN IF EXISTS last_transaction_version,
+  DROP COLUMN IF EXISTS last_transaction_timestamp,
+  DROP COLUMN IF EXISTS transaction_count,
+  DROP COLUMN IF EXISTS has_signed;
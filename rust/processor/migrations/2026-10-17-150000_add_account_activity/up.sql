@@ -0,0 +1,10 @@
+-- Activity columns are nullable because rows written before this migration have no history
+ALTER TABLE accounts
+  ADD COLUMN IF NOT EXISTS first_transaction_version BIGINT,
+  ADD COLUMN IF NOT EXISTS first_transaction_timestamp TIMESTAMP,
+  ADD COLUMN IF NOT EXISTS last_transaction_version BIGINT,
+  ADD COLUMN IF NOT EXISTS last_transaction_timestamp TIMESTAMP,
+  ADD COLUMN IF NOT EXISTS transaction_count BIGINT NOT NULL DEFAULT 0,
+  ADD COLUMN IF NOT EXISTS has_signed BOOLEAN NOT NULL DEFAULT FALSE;
+CREATE INDEX IF NOT EXISTS acc_first_ts_index ON accounts (first_transaction_timestamp);
+CREATE INDEX IF NOT EXISTS acc_last_ts_index ON accounts (last_transaction_timestamp);
@@ -0,0 +1,18 @@
+-- Transfers and burns mercato_indexer_processor couldn't match to an nft_meta row while earlier
+-- batches were still in flight. They're attached once the mint is written, and dropped once
+-- processor_status passes them.
+CREATE TABLE IF NOT EXISTS mercato_indexer_pending_actions (
+  tx_id TEXT NOT NULL,
+  tx_index BIGINT NOT NULL,
+  token_id TEXT NOT NULL,
+  action TEXT NOT NULL,
+  seller TEXT,
+  buyer TEXT,
+  new_owner TEXT,
+  block_height BIGINT NOT NULL,
+  block_time TIMESTAMP NOT NULL,
+  nonce BIGINT NOT NULL,
+  PRIMARY KEY (tx_id, tx_index)
+);
+CREATE INDEX IF NOT EXISTS mipa_token_id_index ON mercato_indexer_pending_actions (token_id);
+CREATE INDEX IF NOT EXISTS mipa_nonce_index ON mercato_indexer_pending_actions (nonce);
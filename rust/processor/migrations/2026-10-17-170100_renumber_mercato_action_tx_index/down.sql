@@ -0,0 +1,2 @@
+-- This file should undo anything in `up.sql`
+-- Sequence numbers aren't stored, so the previous tx_index values can't be restored
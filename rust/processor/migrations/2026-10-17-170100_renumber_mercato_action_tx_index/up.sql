@@ -0,0 +1,6 @@
+-- tx_index used to be the sender sequence number * 10 for the mint and * 10 + 1 for the transfer
+-- of a mint transaction. It is now the index of the token event within the transaction, and a mint
+-- transaction emits the mint before the transfer, so the old rows become 0 and 1. Reprocessing
+-- those versions then hits the existing rows instead of duplicating them.
+UPDATE action SET tx_index = tx_index % 10 WHERE tx_index >= 10;
+UPDATE recent_action SET tx_index = tx_index % 10 WHERE tx_index >= 10;
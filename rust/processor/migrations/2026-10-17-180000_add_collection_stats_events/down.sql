@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE IF EXISTS collection_holders;
+DROP TABLE IF EXISTS collection_stats_events;
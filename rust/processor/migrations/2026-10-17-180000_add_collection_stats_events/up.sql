@@ -0,0 +1,33 @@
+-- One row per counted token event or sale. collection_stats_hourly is recomputed from these rows
+-- for every hour a batch touches, so processing a version range twice doesn't count it twice
+CREATE TABLE IF NOT EXISTS collection_stats_events (
+  transaction_version BIGINT NOT NULL,
+  event_index BIGINT NOT NULL,
+  collection_id VARCHAR(66) NOT NULL,
+  bucket_start TIMESTAMP NOT NULL,
+  -- mint, burn, transfer or sale
+  event_kind VARCHAR(20) NOT NULL,
+  -- Price of a sale, 0 otherwise
+  amount NUMERIC NOT NULL,
+  inserted_at TIMESTAMP NOT NULL DEFAULT NOW(),
+  PRIMARY KEY (transaction_version, event_index)
+);
+CREATE INDEX IF NOT EXISTS cse_collection_id_bucket_start_index ON collection_stats_events (collection_id, bucket_start);
+
+-- Owners holding at least one token of a collection. Only owners whose tokens moved in a batch are
+-- rechecked, and unique_holders is adjusted by the rows added and removed
+CREATE TABLE IF NOT EXISTS collection_holders (
+  collection_id VARCHAR(66) NOT NULL,
+  owner_address VARCHAR(66) NOT NULL,
+  inserted_at TIMESTAMP NOT NULL DEFAULT NOW(),
+  PRIMARY KEY (collection_id, owner_address)
+);
+INSERT INTO collection_holders (collection_id, owner_address)
+SELECT DISTINCT d.collection_id, o.owner_address
+FROM current_token_ownerships_v2 o
+JOIN current_token_datas_v2 d ON d.token_data_id = o.token_data_id
+WHERE o.amount > 0 AND d.collection_id IN (SELECT collection_id FROM current_collection_stats)
+ON CONFLICT DO NOTHING;
+UPDATE current_collection_stats s SET unique_holders = (
+  SELECT COUNT(*) FROM collection_holders h WHERE h.collection_id = s.collection_id
+);
@@ -0,0 +1,9 @@
+-- Latest transfer of a token outside any marketplace, kept until the batches before it are
+-- committed so a listing written out of order is still invalidated
+CREATE TABLE IF NOT EXISTS listing_invalidations (
+  token_data_id VARCHAR(66) NOT NULL,
+  transaction_version BIGINT NOT NULL,
+  transaction_timestamp TIMESTAMP NOT NULL,
+  PRIMARY KEY (token_data_id)
+);
+CREATE INDEX IF NOT EXISTS li_transaction_version_index ON listing_invalidations (transaction_version);
@@ -0,0 +1,7 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX IF EXISTS cse_unresolved_token_data_id_index;
+DELETE FROM collection_stats_events WHERE collection_id IS NULL;
+ALTER TABLE collection_stats_events ALTER COLUMN collection_id SET NOT NULL;
+ALTER TABLE collection_stats_events DROP COLUMN IF EXISTS token_data_id;
+DROP TABLE IF EXISTS collection_stats_ownerships;
+DROP TABLE IF EXISTS collection_stats_tokens;
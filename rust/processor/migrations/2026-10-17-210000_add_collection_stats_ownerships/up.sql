@@ -0,0 +1,40 @@
+-- Collection of every token collection_stats_processor has seen, so later activity of the token is
+-- attributed to its collection without reading another processor's tables
+CREATE TABLE IF NOT EXISTS collection_stats_tokens (
+  token_data_id VARCHAR(66) NOT NULL,
+  collection_id VARCHAR(66) NOT NULL,
+  inserted_at TIMESTAMP NOT NULL DEFAULT NOW(),
+  PRIMARY KEY (token_data_id)
+);
+
+-- Latest amount of every token ownership collection_stats_processor has seen, used to recheck
+-- collection_holders
+CREATE TABLE IF NOT EXISTS collection_stats_ownerships (
+  token_data_id VARCHAR(66) NOT NULL,
+  property_version_v1 NUMERIC NOT NULL,
+  owner_address VARCHAR(66) NOT NULL,
+  storage_id VARCHAR(66) NOT NULL,
+  amount NUMERIC NOT NULL,
+  last_transaction_version BIGINT NOT NULL,
+  inserted_at TIMESTAMP NOT NULL DEFAULT NOW(),
+  PRIMARY KEY (token_data_id, property_version_v1, owner_address, storage_id)
+);
+CREATE INDEX IF NOT EXISTS cso_owner_address_index ON collection_stats_ownerships (owner_address) WHERE amount > 0;
+
+-- Events of a token whose collection isn't known yet are stored without a collection and
+-- attributed once a batch writes the token
+ALTER TABLE collection_stats_events ADD COLUMN IF NOT EXISTS token_data_id VARCHAR(66);
+ALTER TABLE collection_stats_events ALTER COLUMN collection_id DROP NOT NULL;
+CREATE INDEX IF NOT EXISTS cse_unresolved_token_data_id_index ON collection_stats_events (token_data_id) WHERE collection_id IS NULL;
+
+-- A running processor only sees tokens and ownerships from now on, so start from what
+-- token_v2_processor has written so far
+INSERT INTO collection_stats_tokens (token_data_id, collection_id)
+SELECT token_data_id, collection_id FROM current_token_datas_v2
+ON CONFLICT DO NOTHING;
+INSERT INTO collection_stats_ownerships (
+  token_data_id, property_version_v1, owner_address, storage_id, amount, last_transaction_version
+)
+SELECT token_data_id, property_version_v1, owner_address, storage_id, amount, last_transaction_version
+FROM current_token_ownerships_v2
+ON CONFLICT DO NOTHING;
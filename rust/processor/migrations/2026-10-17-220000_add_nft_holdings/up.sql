@@ -0,0 +1,27 @@
+-- Tokens held under a `holding` nft_points rule: when the owner received them and up to when their
+-- points were awarded. Rows with a zero amount were released and are kept so a batch written out
+-- of order can't start the holding again
+CREATE TABLE IF NOT EXISTS nft_holdings (
+  rule_name TEXT NOT NULL,
+  rules_version TEXT NOT NULL,
+  token_data_id_hash VARCHAR(64) NOT NULL,
+  property_version NUMERIC NOT NULL,
+  owner_address VARCHAR(66) NOT NULL,
+  creator_address VARCHAR(66) NOT NULL,
+  collection_name VARCHAR(128) NOT NULL,
+  token_name VARCHAR(128) NOT NULL,
+  amount NUMERIC NOT NULL,
+  held_since TIMESTAMP NOT NULL,
+  accrued_until TIMESTAMP NOT NULL,
+  last_transaction_version BIGINT NOT NULL,
+  inserted_at TIMESTAMP NOT NULL DEFAULT NOW(),
+  PRIMARY KEY (
+    rule_name,
+    rules_version,
+    token_data_id_hash,
+    property_version,
+    owner_address
+  )
+);
+CREATE INDEX IF NOT EXISTS nh_rule_accrued_index ON nft_holdings (rule_name, rules_version, accrued_until)
+WHERE amount > 0;
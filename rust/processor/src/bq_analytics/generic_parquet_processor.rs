@@ -175,7 +175,7 @@ where
         Ok(())
     }
 
-    async fn upload_buffer(&mut self, gcs_client: &GCSClient) -> Result<()> {
+    pub async fn upload_buffer(&mut self, gcs_client: &GCSClient) -> Result<()> {
         // This is to cover the case when interval duration has passed but buffer is empty
         if self.buffer.is_empty() {
             debug!("Buffer is empty, skipping upload.");
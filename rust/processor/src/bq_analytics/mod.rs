@@ -7,6 +7,7 @@ use crate::{
         ParquetHandler as GenericParquetHandler,
     },
     gap_detectors::ProcessingResult,
+    utils::shutdown,
     worker::PROCESSOR_SERVICE_TYPE,
 };
 use ahash::AHashMap;
@@ -136,37 +137,55 @@ where
             .expect("Failed to create GCS client config");
         let gcs_client = Arc::new(GCSClient::new(gcs_config));
 
+        let mut shutdown = shutdown::subscribe();
         loop {
-            match parquet_receiver.recv().await {
-                Ok(txn_pb_res) => {
-                    let result = parquet_handler.handle(&gcs_client, txn_pb_res).await;
-
-                    match result {
-                        Ok(_) => {
-                            info!(
-                                processor_name = processor_name.clone(),
-                                service_type = PROCESSOR_SERVICE_TYPE,
-                                "[Parquet Handler] Successfully processed structs to buffer",
-                            );
-                        },
-                        Err(e) => {
-                            error!(
-                                processor_name = processor_name.clone(),
-                                service_type = PROCESSOR_SERVICE_TYPE,
-                                "[Parquet Handler] Error processing parquet files: {:?}",
-                                e
-                            );
-                            panic!("Error processing parquet files: {:?}", e);
-                        },
+            // On shutdown the processor tasks are done, so whatever is queued is all there is
+            let received = if shutdown::is_triggered() {
+                parquet_receiver.try_recv().ok().flatten()
+            } else {
+                tokio::select! {
+                    received = parquet_receiver.recv() => received.ok(),
+                    _ = shutdown.changed() => continue,
+                }
+            };
+            let txn_pb_res = match received {
+                Some(txn_pb_res) => txn_pb_res,
+                // Closed, or drained on shutdown: upload the buffer so nothing is lost
+                None => {
+                    info!(
+                        processor_name = processor_name.clone(),
+                        service_type = PROCESSOR_SERVICE_TYPE,
+                        "[Parquet Handler] Channel closed, uploading the remaining buffer",
+                    );
+                    if let Err(e) = parquet_handler.upload_buffer(&gcs_client).await {
+                        error!(
+                            processor_name = processor_name.clone(),
+                            service_type = PROCESSOR_SERVICE_TYPE,
+                            "[Parquet Handler] Error uploading the remaining buffer: {:?}",
+                            e
+                        );
                     }
+                    break;
+                },
+            };
+            let result = parquet_handler.handle(&gcs_client, txn_pb_res).await;
+
+            match result {
+                Ok(_) => {
+                    info!(
+                        processor_name = processor_name.clone(),
+                        service_type = PROCESSOR_SERVICE_TYPE,
+                        "[Parquet Handler] Successfully processed structs to buffer",
+                    );
                 },
                 Err(e) => {
                     error!(
                         processor_name = processor_name.clone(),
                         service_type = PROCESSOR_SERVICE_TYPE,
-                        "[Parquet Handler] Error receiving parquet files: {:?}",
+                        "[Parquet Handler] Error processing parquet files: {:?}",
                         e
                     );
+                    panic!("Error processing parquet files: {:?}", e);
                 },
             }
         }
@@ -1,31 +1,87 @@
 #![allow(clippy::extra_unused_lifetimes)]
 #![allow(clippy::unused_unit)]
 
-use crate::db::common::models::account_transaction_models::account_transactions::AccountTransaction;
-use crate::schema::accounts;
-use aptos_protos::transaction::v1::Transaction;
+use crate::{
+    db::common::models::{
+        account_transaction_models::account_transactions::AccountTransaction,
+        user_transactions_models::{signatures::Signature, user_transactions::UserTransaction},
+    },
+    schema::accounts,
+    utils::util::parse_timestamp,
+};
 use ahash::AHashMap;
+use aptos_protos::transaction::v1::{transaction::TxnData, Transaction};
 use field_count::FieldCount;
 use serde::{Deserialize, Serialize};
 
+/// Activity of an account over a batch of transactions. Rows are merged into `accounts` only if
+/// they move `last_transaction_version` forward.
 #[derive(Clone, Debug, Deserialize, FieldCount, Identifiable, Insertable, Serialize)]
 #[diesel(primary_key(account_address))]
 #[diesel(table_name = accounts)]
 pub struct Account {
     pub account_address: String,
+    pub first_transaction_version: i64,
+    pub first_transaction_timestamp: chrono::NaiveDateTime,
+    pub last_transaction_version: i64,
+    pub last_transaction_timestamp: chrono::NaiveDateTime,
+    pub transaction_count: i64,
+    // True if the account signed any of the transactions, as sender, secondary signer or fee payer
+    pub has_signed: bool,
 }
 
 impl Account {
+    /// Every account touched by the transaction, see `AccountTransaction::from_transaction`
     pub fn from_transaction(transaction: &Transaction) -> AHashMap<String, Self> {
-        let account_transactions = AccountTransaction::from_transaction(transaction)
-            .into_values()
-            .collect::<Vec<AccountTransaction>>();
+        let txn_version = transaction.version as i64;
+        let mut signers = vec![];
+        if let Some(TxnData::User(inner)) = transaction.txn_data.as_ref() {
+            if let Some(request) = inner.request.as_ref() {
+                signers.extend(
+                    UserTransaction::get_signatures(
+                        request,
+                        txn_version,
+                        transaction.block_height as i64,
+                    )
+                    .into_iter()
+                    .map(|sig| sig.signer),
+                );
+                if let Some(signature) = request.signature.as_ref() {
+                    signers.extend(Signature::get_fee_payer_address(signature, txn_version));
+                }
+            }
+        }
+        let account_transactions = AccountTransaction::from_transaction(transaction);
+        if account_transactions.is_empty() {
+            return AHashMap::new();
+        }
+        let txn_timestamp = parse_timestamp(transaction.timestamp.as_ref().unwrap(), txn_version);
         let mut map = AHashMap::new();
-        for tr in account_transactions {
-            map.insert(tr.account_address.clone(), Self{
-                account_address: tr.account_address.clone(),
+        for tr in account_transactions.into_values() {
+            map.insert(tr.account_address.clone(), Self {
+                has_signed: signers.contains(&tr.account_address),
+                account_address: tr.account_address,
+                first_transaction_version: txn_version,
+                first_transaction_timestamp: txn_timestamp,
+                last_transaction_version: txn_version,
+                last_transaction_timestamp: txn_timestamp,
+                transaction_count: 1,
             });
         }
         map
     }
-}
\ No newline at end of file
+
+    /// Folds the activity of `other` for the same account into this record
+    pub fn merge(&mut self, other: &Self) {
+        if other.first_transaction_version < self.first_transaction_version {
+            self.first_transaction_version = other.first_transaction_version;
+            self.first_transaction_timestamp = other.first_transaction_timestamp;
+        }
+        if other.last_transaction_version > self.last_transaction_version {
+            self.last_transaction_version = other.last_transaction_version;
+            self.last_transaction_timestamp = other.last_transaction_timestamp;
+        }
+        self.transaction_count += other.transaction_count;
+        self.has_signed |= other.has_signed;
+    }
+}
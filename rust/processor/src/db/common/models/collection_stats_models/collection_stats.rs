@@ -0,0 +1,493 @@
+// Copyright © Aptos Foundation
+// SPDX-License-Identifier: Apache-2.0
+
+// This is required because a diesel macro makes clippy sad
+#![allow(clippy::extra_unused_lifetimes)]
+
+use crate::{
+    db::common::models::token_v2_models::{
+        v2_collections::CurrentCollectionV2, v2_token_activities::TokenActivityV2,
+        v2_token_datas::CurrentTokenDataV2, v2_token_ownerships::CurrentTokenOwnershipV2,
+    },
+    schema::{
+        collection_stats_events, collection_stats_ownerships, collection_stats_tokens,
+        current_collection_stats,
+    },
+    utils::database::{count_rows_written, MyDbConnection},
+};
+use bigdecimal::{BigDecimal, Zero};
+use chrono::{NaiveDateTime, Timelike};
+use diesel::{
+    pg::Pg,
+    prelude::*,
+    query_builder::{QueryFragment, QueryId},
+    sql_query,
+    sql_types::{Array, BigInt, Text, Timestamp},
+};
+use diesel_async::RunQueryDsl;
+use field_count::FieldCount;
+use serde::{Deserialize, Serialize};
+
+pub const MINT_EVENT_KIND: &str = "mint";
+pub const BURN_EVENT_KIND: &str = "burn";
+pub const TRANSFER_EVENT_KIND: &str = "transfer";
+pub const SALE_EVENT_KIND: &str = "sale";
+
+/// Token event or sale counted in `collection_stats_hourly`. Keyed by the event, so writing the
+/// same version twice keeps a single row. Events of tokens whose collection isn't known yet are
+/// stored without a collection and attributed by `resolve_collection_stats_events`.
+#[derive(Clone, Debug, Deserialize, FieldCount, Identifiable, Insertable, Serialize)]
+#[diesel(primary_key(transaction_version, event_index))]
+#[diesel(table_name = collection_stats_events)]
+pub struct CollectionStatsEvent {
+    pub transaction_version: i64,
+    pub event_index: i64,
+    pub collection_id: Option<String>,
+    pub bucket_start: NaiveDateTime,
+    pub event_kind: String,
+    pub amount: BigDecimal,
+    pub token_data_id: Option<String>,
+}
+
+impl CollectionStatsEvent {
+    /// Start of the hour the timestamp falls in
+    pub fn get_bucket_start(timestamp: NaiveDateTime) -> NaiveDateTime {
+        timestamp
+            .date()
+            .and_hms_opt(timestamp.hour(), 0, 0)
+            .unwrap()
+    }
+
+    /// Activity types other than mint, burn and transfer aren't counted
+    pub fn from_token_activity(
+        activity: &TokenActivityV2,
+        collection_id: Option<&String>,
+    ) -> Option<Self> {
+        let event_kind = match activity.type_.as_str() {
+            "0x4::collection::Mint"
+            | "0x4::collection::MintEvent"
+            | "0x3::token::MintTokenEvent" => MINT_EVENT_KIND,
+            "0x4::collection::Burn"
+            | "0x4::collection::BurnEvent"
+            | "0x3::token::BurnTokenEvent" => BURN_EVENT_KIND,
+            "0x1::object::TransferEvent" | "0x3::token::DepositEvent" => TRANSFER_EVENT_KIND,
+            _ => return None,
+        };
+        Some(Self {
+            transaction_version: activity.transaction_version,
+            event_index: activity.event_index,
+            collection_id: collection_id.cloned(),
+            bucket_start: Self::get_bucket_start(activity.transaction_timestamp),
+            event_kind: event_kind.to_string(),
+            amount: BigDecimal::zero(),
+            token_data_id: Some(activity.token_data_id.clone()),
+        })
+    }
+
+    pub fn from_sale(
+        transaction_version: i64,
+        event_index: i64,
+        collection_id: String,
+        token_data_id: Option<String>,
+        timestamp: NaiveDateTime,
+        price: BigDecimal,
+    ) -> Self {
+        Self {
+            transaction_version,
+            event_index,
+            collection_id: Some(collection_id),
+            bucket_start: Self::get_bucket_start(timestamp),
+            event_kind: SALE_EVENT_KIND.to_string(),
+            amount: price,
+            token_data_id,
+        }
+    }
+}
+
+/// Collection of a token, kept so later activity of the token can be attributed to it
+#[derive(Clone, Debug, Deserialize, FieldCount, Identifiable, Insertable, Serialize)]
+#[diesel(primary_key(token_data_id))]
+#[diesel(table_name = collection_stats_tokens)]
+pub struct CollectionStatsToken {
+    pub token_data_id: String,
+    pub collection_id: String,
+}
+
+impl From<&CurrentTokenDataV2> for CollectionStatsToken {
+    fn from(token_data: &CurrentTokenDataV2) -> Self {
+        Self {
+            token_data_id: token_data.token_data_id.clone(),
+            collection_id: token_data.collection_id.clone(),
+        }
+    }
+}
+
+/// Latest amount of a token ownership, read to recheck `collection_holders`
+#[derive(Clone, Debug, Deserialize, FieldCount, Identifiable, Insertable, Serialize)]
+#[diesel(primary_key(token_data_id, property_version_v1, owner_address, storage_id))]
+#[diesel(table_name = collection_stats_ownerships)]
+pub struct CollectionStatsOwnership {
+    pub token_data_id: String,
+    pub property_version_v1: BigDecimal,
+    pub owner_address: String,
+    pub storage_id: String,
+    pub amount: BigDecimal,
+    pub last_transaction_version: i64,
+}
+
+impl From<&CurrentTokenOwnershipV2> for CollectionStatsOwnership {
+    fn from(ownership: &CurrentTokenOwnershipV2) -> Self {
+        Self {
+            token_data_id: ownership.token_data_id.clone(),
+            property_version_v1: ownership.property_version_v1.clone(),
+            owner_address: ownership.owner_address.clone(),
+            storage_id: ownership.storage_id.clone(),
+            amount: ownership.amount.clone(),
+            last_transaction_version: ownership.last_transaction_version,
+        }
+    }
+}
+
+/// Supply columns of `current_collection_stats`, taken from `CurrentCollectionV2`. The other
+/// columns are computed in the database by the stats rollup.
+#[derive(Clone, Debug, Deserialize, FieldCount, Identifiable, Insertable, Serialize)]
+#[diesel(primary_key(collection_id))]
+#[diesel(table_name = current_collection_stats)]
+pub struct CurrentCollectionSupply {
+    pub collection_id: String,
+    pub current_supply: Option<BigDecimal>,
+    pub max_supply: Option<BigDecimal>,
+    pub total_minted: Option<BigDecimal>,
+    pub last_transaction_version: i64,
+    pub last_transaction_timestamp: NaiveDateTime,
+}
+
+impl From<&CurrentCollectionV2> for CurrentCollectionSupply {
+    fn from(collection: &CurrentCollectionV2) -> Self {
+        Self {
+            collection_id: collection.collection_id.clone(),
+            current_supply: Some(collection.current_supply.clone()),
+            max_supply: collection.max_supply.clone(),
+            total_minted: collection.total_minted_v2.clone(),
+            last_transaction_version: collection.last_transaction_version,
+            last_transaction_timestamp: collection.last_transaction_timestamp,
+        }
+    }
+}
+
+/// Hour of a collection whose `collection_stats_hourly` row has to be recomputed
+#[derive(Debug, QueryableByName)]
+pub struct CollectionBucket {
+    #[diesel(sql_type = Text)]
+    pub collection_id: String,
+    #[diesel(sql_type = Timestamp)]
+    pub bucket_start: NaiveDateTime,
+}
+
+/// Attributes stored events without a collection to the collection of their token, for the given
+/// tokens. Covers events written by a batch that committed before the batch that wrote the token.
+/// Returns the hours of the attributed events.
+pub async fn resolve_collection_stats_events(
+    conn: &mut MyDbConnection,
+    token_data_ids: Vec<String>,
+) -> QueryResult<Vec<CollectionBucket>> {
+    let buckets = sql_query(
+        r#"
+        UPDATE collection_stats_events e
+        SET collection_id = t.collection_id
+        FROM collection_stats_tokens t
+        WHERE e.collection_id IS NULL
+        AND e.token_data_id = t.token_data_id
+        AND t.token_data_id = ANY($1)
+        RETURNING e.collection_id, e.bucket_start
+        "#,
+    )
+    .bind::<Array<Text>, _>(token_data_ids)
+    .load::<CollectionBucket>(conn)
+    .await?;
+    count_rows_written("collection_stats_events", buckets.len());
+    Ok(buckets)
+}
+
+/// Recomputes the hourly rows of the given (collection_id, bucket_start) pairs from
+/// `collection_stats_events`
+pub fn refresh_collection_stats_hourly_query(
+    collection_ids: Vec<String>,
+    bucket_starts: Vec<NaiveDateTime>,
+) -> impl QueryFragment<Pg> + QueryId + Send {
+    sql_query(
+        r#"
+        INSERT INTO collection_stats_hourly (
+            collection_id, bucket_start, mint_count, burn_count, transfer_count, sales_count, volume
+        )
+        SELECT
+            e.collection_id,
+            e.bucket_start,
+            COUNT(*) FILTER (WHERE e.event_kind = 'mint'),
+            COUNT(*) FILTER (WHERE e.event_kind = 'burn'),
+            COUNT(*) FILTER (WHERE e.event_kind = 'transfer'),
+            COUNT(*) FILTER (WHERE e.event_kind = 'sale'),
+            COALESCE(SUM(e.amount) FILTER (WHERE e.event_kind = 'sale'), 0)
+        FROM unnest($1, $2) AS b(collection_id, bucket_start)
+        JOIN collection_stats_events e
+            ON e.collection_id = b.collection_id AND e.bucket_start = b.bucket_start
+        GROUP BY e.collection_id, e.bucket_start
+        ON CONFLICT (collection_id, bucket_start) DO UPDATE SET
+            mint_count = EXCLUDED.mint_count,
+            burn_count = EXCLUDED.burn_count,
+            transfer_count = EXCLUDED.transfer_count,
+            sales_count = EXCLUDED.sales_count,
+            volume = EXCLUDED.volume,
+            inserted_at = NOW()
+        "#,
+    )
+    .bind::<Array<Text>, _>(collection_ids)
+    .bind::<Array<Timestamp>, _>(bucket_starts)
+}
+
+/// Recomputes floor, volume and sales of the given collections from `current_listings` and
+/// `collection_stats_hourly`. Rolling volumes are relative to `transaction_timestamp` rather than
+/// the wall clock so that backfills produce the same numbers as live processing.
+pub fn refresh_collection_stats_query(
+    collection_ids: Vec<String>,
+    transaction_version: i64,
+    transaction_timestamp: NaiveDateTime,
+) -> impl QueryFragment<Pg> + QueryId + Send {
+    sql_query(
+        r#"
+        INSERT INTO current_collection_stats (
+            collection_id, floor_price, volume_24h, volume_7d, total_volume, sales_count,
+            last_transaction_version, last_transaction_timestamp
+        )
+        SELECT
+            c.collection_id,
+            (
+                SELECT MIN(l.price)
+                FROM current_listings l
+                WHERE l.collection_id = c.collection_id AND l.is_active
+            ),
+            COALESCE((
+                SELECT SUM(h.volume) FROM collection_stats_hourly h
+                WHERE h.collection_id = c.collection_id
+                AND h.bucket_start > $3 - INTERVAL '24 hours'
+            ), 0),
+            COALESCE((
+                SELECT SUM(h.volume) FROM collection_stats_hourly h
+                WHERE h.collection_id = c.collection_id
+                AND h.bucket_start > $3 - INTERVAL '7 days'
+            ), 0),
+            COALESCE((
+                SELECT SUM(h.volume) FROM collection_stats_hourly h
+                WHERE h.collection_id = c.collection_id
+            ), 0),
+            COALESCE((
+                SELECT SUM(h.sales_count) FROM collection_stats_hourly h
+                WHERE h.collection_id = c.collection_id
+            ), 0),
+            $2,
+            $3
+        FROM unnest($1) AS c(collection_id)
+        ON CONFLICT (collection_id) DO UPDATE SET
+            floor_price = EXCLUDED.floor_price,
+            volume_24h = EXCLUDED.volume_24h,
+            volume_7d = EXCLUDED.volume_7d,
+            total_volume = EXCLUDED.total_volume,
+            sales_count = EXCLUDED.sales_count,
+            last_transaction_version = GREATEST(current_collection_stats.last_transaction_version, EXCLUDED.last_transaction_version),
+            last_transaction_timestamp = GREATEST(current_collection_stats.last_transaction_timestamp, EXCLUDED.last_transaction_timestamp),
+            inserted_at = NOW()
+        "#,
+    )
+    .bind::<Array<Text>, _>(collection_ids)
+    .bind::<BigInt, _>(transaction_version)
+    .bind::<Timestamp, _>(transaction_timestamp)
+}
+
+/// Slides the 24h and 7d volumes of collections the batch didn't touch. Only collections with
+/// volume in the last 7 days can change.
+pub fn refresh_volume_windows_query(
+    transaction_timestamp: NaiveDateTime,
+) -> impl QueryFragment<Pg> + QueryId + Send {
+    sql_query(
+        r#"
+        UPDATE current_collection_stats s SET
+            volume_24h = COALESCE((
+                SELECT SUM(h.volume) FROM collection_stats_hourly h
+                WHERE h.collection_id = s.collection_id
+                AND h.bucket_start > $1 - INTERVAL '24 hours'
+            ), 0),
+            volume_7d = COALESCE((
+                SELECT SUM(h.volume) FROM collection_stats_hourly h
+                WHERE h.collection_id = s.collection_id
+                AND h.bucket_start > $1 - INTERVAL '7 days'
+            ), 0)
+        WHERE s.volume_7d > 0
+        "#,
+    )
+    .bind::<Timestamp, _>(transaction_timestamp)
+}
+
+/// Rechecks whether the owners of the given tokens, current and previous, still hold a token of the
+/// token's collection, and adjusts `unique_holders` by the `collection_holders` rows added and
+/// removed. Reads only `collection_stats_ownerships` and `collection_stats_tokens`.
+pub fn refresh_collection_holders_query(
+    token_data_ids: Vec<String>,
+    transaction_version: i64,
+    transaction_timestamp: NaiveDateTime,
+) -> impl QueryFragment<Pg> + QueryId + Send {
+    sql_query(
+        r#"
+        WITH touched AS (
+            SELECT DISTINCT t.collection_id, o.owner_address
+            FROM collection_stats_ownerships o
+            JOIN collection_stats_tokens t ON t.token_data_id = o.token_data_id
+            WHERE o.token_data_id = ANY($1)
+        ),
+        holding AS (
+            SELECT
+                touched.collection_id,
+                touched.owner_address,
+                EXISTS (
+                    SELECT 1
+                    FROM collection_stats_ownerships o
+                    JOIN collection_stats_tokens t ON t.token_data_id = o.token_data_id
+                    WHERE o.owner_address = touched.owner_address
+                    AND t.collection_id = touched.collection_id
+                    AND o.amount > 0
+                ) AS holds
+            FROM touched
+        ),
+        added AS (
+            INSERT INTO collection_holders (collection_id, owner_address)
+            SELECT collection_id, owner_address FROM holding WHERE holds
+            ON CONFLICT DO NOTHING
+            RETURNING collection_id
+        ),
+        removed AS (
+            DELETE FROM collection_holders h
+            USING holding
+            WHERE NOT holding.holds
+            AND h.collection_id = holding.collection_id
+            AND h.owner_address = holding.owner_address
+            RETURNING h.collection_id
+        ),
+        delta AS (
+            SELECT collection_id, SUM(holder_change) AS holder_change
+            FROM (
+                SELECT collection_id, 1 AS holder_change FROM added
+                UNION ALL
+                SELECT collection_id, -1 AS holder_change FROM removed
+            ) changes
+            GROUP BY collection_id
+        )
+        INSERT INTO current_collection_stats (
+            collection_id, unique_holders, last_transaction_version, last_transaction_timestamp
+        )
+        SELECT collection_id, holder_change, $2, $3 FROM delta
+        ON CONFLICT (collection_id) DO UPDATE SET
+            unique_holders = current_collection_stats.unique_holders + EXCLUDED.unique_holders
+        "#,
+    )
+    .bind::<Array<Text>, _>(token_data_ids)
+    .bind::<BigInt, _>(transaction_version)
+    .bind::<Timestamp, _>(transaction_timestamp)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn token_activity(type_: &str) -> TokenActivityV2 {
+        TokenActivityV2 {
+            transaction_version: 10,
+            event_index: 2,
+            event_account_address: "0x1".to_string(),
+            token_data_id: "0x70".to_string(),
+            property_version_v1: BigDecimal::zero(),
+            type_: type_.to_string(),
+            from_address: None,
+            to_address: None,
+            token_amount: BigDecimal::from(1),
+            before_value: None,
+            after_value: None,
+            entry_function_id_str: None,
+            token_standard: "v2".to_string(),
+            is_fungible_v2: None,
+            transaction_timestamp: NaiveDateTime::parse_from_str(
+                "2024-05-01 13:45:10",
+                "%Y-%m-%d %H:%M:%S",
+            )
+            .unwrap(),
+        }
+    }
+
+    #[test]
+    fn test_from_token_activity() {
+        let mint = CollectionStatsEvent::from_token_activity(
+            &token_activity("0x4::collection::Mint"),
+            Some(&"0xc0".to_string()),
+        )
+        .unwrap();
+        assert_eq!(mint.event_kind, MINT_EVENT_KIND);
+        assert_eq!((mint.transaction_version, mint.event_index), (10, 2));
+        assert_eq!(mint.collection_id.as_deref(), Some("0xc0"));
+        assert_eq!(mint.token_data_id.as_deref(), Some("0x70"));
+        assert_eq!(
+            mint.bucket_start,
+            NaiveDateTime::parse_from_str("2024-05-01 13:00:00", "%Y-%m-%d %H:%M:%S").unwrap()
+        );
+        assert_eq!(
+            CollectionStatsEvent::from_token_activity(
+                &token_activity("0x3::token::BurnTokenEvent"),
+                None
+            )
+            .unwrap()
+            .event_kind,
+            BURN_EVENT_KIND
+        );
+        assert_eq!(
+            CollectionStatsEvent::from_token_activity(
+                &token_activity("0x1::object::TransferEvent"),
+                None
+            )
+            .unwrap()
+            .event_kind,
+            TRANSFER_EVENT_KIND
+        );
+        // The collection of a token that isn't in the batch is attributed once its token is written
+        assert!(CollectionStatsEvent::from_token_activity(
+            &token_activity("0x1::object::TransferEvent"),
+            None
+        )
+        .unwrap()
+        .collection_id
+        .is_none());
+        // Mutations aren't counted
+        assert!(CollectionStatsEvent::from_token_activity(
+            &token_activity("0x4::token::MutationEvent"),
+            None
+        )
+        .is_none());
+    }
+
+    #[test]
+    fn test_from_sale() {
+        let timestamp =
+            NaiveDateTime::parse_from_str("2024-05-01 00:59:59", "%Y-%m-%d %H:%M:%S").unwrap();
+        let sale = CollectionStatsEvent::from_sale(
+            10,
+            3,
+            "0xc0".to_string(),
+            Some("0x70".to_string()),
+            timestamp,
+            BigDecimal::from(250),
+        );
+        assert_eq!(sale.event_kind, SALE_EVENT_KIND);
+        assert_eq!(sale.collection_id.as_deref(), Some("0xc0"));
+        assert_eq!(sale.amount, BigDecimal::from(250));
+        assert_eq!(
+            sale.bucket_start,
+            NaiveDateTime::parse_from_str("2024-05-01 00:00:00", "%Y-%m-%d %H:%M:%S").unwrap()
+        );
+    }
+}
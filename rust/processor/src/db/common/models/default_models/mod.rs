@@ -3,6 +3,7 @@
 
 pub mod block_metadata_transactions;
 pub mod move_modules;
+pub mod move_resource_projections;
 pub mod move_resources;
 pub mod move_tables;
 pub mod transactions;
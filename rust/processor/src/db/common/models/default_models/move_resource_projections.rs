@@ -0,0 +1,383 @@
+// Copyright © Aptos Foundation
+// SPDX-License-Identifier: Apache-2.0
+
+//! Projects fields of decoded resource `data` into typed columns of a narrow per-type table.
+//! Tables are created on first use with `CREATE TABLE IF NOT EXISTS`. An existing table is never
+//! altered: its columns are checked against the config before the first write and the processor
+//! fails if any is missing or has a different type.
+
+use super::move_resources::MoveResource;
+use crate::utils::{
+    database::{execute_with_better_error, ArcDbPool, DbPoolConnection},
+    json_path::{lookup, parse_path},
+};
+use anyhow::{bail, Result};
+use bigdecimal::BigDecimal;
+use diesel::{
+    pg::Pg,
+    sql_types::{BigInt, Bool, Jsonb, Nullable, Numeric, Text},
+    QueryResult, QueryableByName,
+};
+use diesel_async::RunQueryDsl;
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::{collections::HashSet, str::FromStr};
+use tokio::sync::OnceCell;
+
+// Postgres accepts at most 65535 bind parameters per statement
+const MAX_BIND_PARAMS: usize = 65535;
+
+// Columns every projection table starts with, in insert order
+const FIXED_COLUMNS: &[&str] = &[
+    "transaction_version",
+    "write_set_change_index",
+    "transaction_block_height",
+    "address",
+    "type",
+    "is_deleted",
+];
+
+lazy_static! {
+    static ref IDENTIFIER_REGEX: Regex = Regex::new("^[a-z_][a-z0-9_]{0,62}$").unwrap();
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProjectionColumnType {
+    Text,
+    Numeric,
+    Bigint,
+    Boolean,
+    Jsonb,
+}
+
+impl ProjectionColumnType {
+    fn sql_type(&self) -> &'static str {
+        match self {
+            Self::Text => "TEXT",
+            Self::Numeric => "NUMERIC",
+            Self::Bigint => "BIGINT",
+            Self::Boolean => "BOOLEAN",
+            Self::Jsonb => "JSONB",
+        }
+    }
+
+    /// Name of the type in `information_schema.columns.data_type`
+    fn information_schema_type(&self) -> &'static str {
+        match self {
+            Self::Text => "text",
+            Self::Numeric => "numeric",
+            Self::Bigint => "bigint",
+            Self::Boolean => "boolean",
+            Self::Jsonb => "jsonb",
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct ProjectionColumn {
+    pub name: String,
+    // Path into the resource data, e.g. $.supply.vec.0.current
+    pub path: String,
+    #[serde(rename = "type")]
+    pub column_type: ProjectionColumnType,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct ResourceProjection {
+    pub table: String,
+    pub columns: Vec<ProjectionColumn>,
+}
+
+#[derive(Debug, QueryableByName)]
+struct ExistingColumn {
+    #[diesel(sql_type = Text)]
+    column_name: String,
+    #[diesel(sql_type = Text)]
+    data_type: String,
+}
+
+enum ProjectedValue {
+    Text(Option<String>),
+    Numeric(Option<BigDecimal>),
+    Bigint(Option<i64>),
+    Boolean(Option<bool>),
+    Jsonb(Option<Value>),
+}
+
+struct CompiledColumn {
+    name: String,
+    path: Vec<String>,
+    column_type: ProjectionColumnType,
+}
+
+impl CompiledColumn {
+    /// Missing fields and values that don't convert to the column type are stored as NULL
+    fn project(&self, data: Option<&Value>) -> ProjectedValue {
+        let value = data
+            .and_then(|data| lookup(data, &self.path).ok())
+            .filter(|value| !value.is_null());
+        match self.column_type {
+            ProjectionColumnType::Text => ProjectedValue::Text(value.map(|value| match value {
+                Value::String(s) => s.clone(),
+                other => other.to_string(),
+            })),
+            ProjectionColumnType::Numeric => {
+                ProjectedValue::Numeric(value.and_then(|value| match value {
+                    Value::String(s) => BigDecimal::from_str(s).ok(),
+                    Value::Number(n) => BigDecimal::from_str(&n.to_string()).ok(),
+                    _ => None,
+                }))
+            },
+            ProjectionColumnType::Bigint => {
+                ProjectedValue::Bigint(value.and_then(|value| match value {
+                    Value::String(s) => s.parse::<i64>().ok(),
+                    Value::Number(n) => n.as_i64(),
+                    _ => None,
+                }))
+            },
+            ProjectionColumnType::Boolean => {
+                ProjectedValue::Boolean(value.and_then(|value| match value {
+                    Value::Bool(b) => Some(*b),
+                    Value::String(s) => s.parse::<bool>().ok(),
+                    _ => None,
+                }))
+            },
+            ProjectionColumnType::Jsonb => ProjectedValue::Jsonb(value.cloned()),
+        }
+    }
+}
+
+/// Validated form of `ResourceProjection`
+pub struct CompiledResourceProjection {
+    table: String,
+    columns: Vec<CompiledColumn>,
+    table_created: OnceCell<()>,
+}
+
+impl CompiledResourceProjection {
+    pub fn new(projection: &ResourceProjection) -> Result<Self> {
+        if !IDENTIFIER_REGEX.is_match(&projection.table) {
+            bail!("Invalid projection table name {:?}", projection.table);
+        }
+        let mut names: HashSet<&str> = FIXED_COLUMNS.iter().copied().collect();
+        names.insert("inserted_at");
+        let mut columns = vec![];
+        for column in &projection.columns {
+            if !IDENTIFIER_REGEX.is_match(&column.name) {
+                bail!(
+                    "Invalid column name {:?} in projection {}",
+                    column.name,
+                    projection.table
+                );
+            }
+            if !names.insert(column.name.as_str()) {
+                bail!(
+                    "Column {} is reserved or duplicated in projection {}",
+                    column.name,
+                    projection.table
+                );
+            }
+            columns.push(CompiledColumn {
+                name: column.name.clone(),
+                path: parse_path(&column.path)?,
+                column_type: column.column_type,
+            });
+        }
+        Ok(Self {
+            table: projection.table.clone(),
+            columns,
+            table_created: OnceCell::new(),
+        })
+    }
+
+    pub fn table(&self) -> &str {
+        &self.table
+    }
+
+    fn create_table_sql(&self) -> String {
+        let columns = self
+            .columns
+            .iter()
+            .map(|column| format!("{} {},", column.name, column.column_type.sql_type()))
+            .collect::<Vec<_>>()
+            .join("\n  ");
+        format!(
+            "CREATE TABLE IF NOT EXISTS {} (
+  transaction_version BIGINT NOT NULL,
+  write_set_change_index BIGINT NOT NULL,
+  transaction_block_height BIGINT NOT NULL,
+  address VARCHAR(66) NOT NULL,
+  type TEXT NOT NULL,
+  is_deleted BOOLEAN NOT NULL,
+  {}
+  inserted_at TIMESTAMP NOT NULL DEFAULT NOW(),
+  PRIMARY KEY (transaction_version, write_set_change_index)
+)",
+            self.table, columns
+        )
+    }
+
+    /// Fails unless every configured column exists in the table with the configured type
+    fn check_columns(&self, existing: &[ExistingColumn]) -> Result<()> {
+        let mut problems = vec![];
+        for column in &self.columns {
+            let expected = column.column_type.information_schema_type();
+            match existing.iter().find(|e| e.column_name == column.name) {
+                None => problems.push(format!(
+                    "column {} is missing (ALTER TABLE {} ADD COLUMN {} {})",
+                    column.name,
+                    self.table,
+                    column.name,
+                    column.column_type.sql_type()
+                )),
+                Some(e) if e.data_type != expected => problems.push(format!(
+                    "column {} is {} but the config says {}",
+                    column.name, e.data_type, expected
+                )),
+                Some(_) => {},
+            }
+        }
+        if !problems.is_empty() {
+            bail!(
+                "Projection table {} doesn't match its config, the processor does not alter existing tables: {}",
+                self.table,
+                problems.join("; ")
+            );
+        }
+        Ok(())
+    }
+
+    /// Creates the table if it doesn't exist and checks its columns against the config
+    async fn ensure_table(&self, pool: ArcDbPool) -> Result<()> {
+        execute_with_better_error(
+            pool.clone(),
+            diesel::sql_query(self.create_table_sql()),
+            None,
+        )
+        .await?;
+        let mut conn: DbPoolConnection = pool.get().await?;
+        let existing = diesel::sql_query(
+            "SELECT column_name::TEXT AS column_name, data_type::TEXT AS data_type \
+             FROM information_schema.columns \
+             WHERE table_schema = current_schema() AND table_name = $1",
+        )
+        .bind::<Text, _>(&self.table)
+        .load::<ExistingColumn>(&mut conn)
+        .await?;
+        self.check_columns(&existing)
+    }
+
+    fn insert_sql(&self, row_count: usize) -> String {
+        let column_names = FIXED_COLUMNS
+            .iter()
+            .copied()
+            .chain(self.columns.iter().map(|column| column.name.as_str()))
+            .collect::<Vec<_>>();
+        let width = column_names.len();
+        let rows = (0..row_count)
+            .map(|row| {
+                let placeholders = (1..=width)
+                    .map(|i| format!("${}", row * width + i))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                format!("({})", placeholders)
+            })
+            .collect::<Vec<_>>()
+            .join(", ");
+        format!(
+            "INSERT INTO {} ({}) VALUES {} ON CONFLICT (transaction_version, write_set_change_index) DO NOTHING",
+            self.table,
+            column_names.join(", "),
+            rows
+        )
+    }
+
+    /// Writes one row per resource, creating or checking the table first if this is the first write
+    pub async fn insert(&self, pool: ArcDbPool, resources: &[&MoveResource]) -> QueryResult<()> {
+        if resources.is_empty() {
+            return Ok(());
+        }
+        self.table_created
+            .get_or_try_init(|| self.ensure_table(pool.clone()))
+            .await
+            .map_err(|e| diesel::result::Error::QueryBuilderError(e.into()))?;
+
+        let rows_per_chunk = MAX_BIND_PARAMS / (FIXED_COLUMNS.len() + self.columns.len());
+        for chunk in resources.chunks(rows_per_chunk) {
+            let mut query = diesel::sql_query(self.insert_sql(chunk.len())).into_boxed::<Pg>();
+            for resource in chunk {
+                query = query
+                    .bind::<BigInt, _>(resource.transaction_version)
+                    .bind::<BigInt, _>(resource.write_set_change_index)
+                    .bind::<BigInt, _>(resource.transaction_block_height)
+                    .bind::<Text, _>(resource.address.clone())
+                    .bind::<Text, _>(resource.type_.clone())
+                    .bind::<Bool, _>(resource.is_deleted);
+                for column in &self.columns {
+                    query = match column.project(resource.data.as_ref()) {
+                        ProjectedValue::Text(v) => query.bind::<Nullable<Text>, _>(v),
+                        ProjectedValue::Numeric(v) => query.bind::<Nullable<Numeric>, _>(v),
+                        ProjectedValue::Bigint(v) => query.bind::<Nullable<BigInt>, _>(v),
+                        ProjectedValue::Boolean(v) => query.bind::<Nullable<Bool>, _>(v),
+                        ProjectedValue::Jsonb(v) => query.bind::<Nullable<Jsonb>, _>(v),
+                    };
+                }
+            }
+            execute_with_better_error(pool.clone(), query, None).await?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn existing(columns: &[(&str, &str)]) -> Vec<ExistingColumn> {
+        columns
+            .iter()
+            .map(|(name, data_type)| ExistingColumn {
+                column_name: name.to_string(),
+                data_type: data_type.to_string(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_check_columns() {
+        let column = |name: &str, path: &str| ProjectionColumn {
+            name: name.to_string(),
+            path: path.to_string(),
+            column_type: ProjectionColumnType::Numeric,
+        };
+        let projection = CompiledResourceProjection::new(&ResourceProjection {
+            table: "collection_concurrent_supplies".to_string(),
+            columns: vec![
+                column("current_supply", "$.current_supply.value"),
+                column("max_supply", "$.current_supply.max_value"),
+            ],
+        })
+        .unwrap();
+
+        let matching = existing(&[
+            ("transaction_version", "bigint"),
+            ("current_supply", "numeric"),
+            ("max_supply", "numeric"),
+        ]);
+        assert!(projection.check_columns(&matching).is_ok());
+
+        let missing = existing(&[("current_supply", "numeric")]);
+        let err = projection.check_columns(&missing).unwrap_err().to_string();
+        assert!(err.contains("max_supply is missing"), "{}", err);
+        assert!(err.contains("ADD COLUMN max_supply NUMERIC"), "{}", err);
+
+        let retyped = existing(&[("current_supply", "numeric"), ("max_supply", "bigint")]);
+        let err = projection.check_columns(&retyped).unwrap_err().to_string();
+        assert!(err.contains("max_supply is bigint"), "{}", err);
+    }
+}
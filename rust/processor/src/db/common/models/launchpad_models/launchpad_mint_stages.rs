@@ -0,0 +1,49 @@
+// Copyright © Aptos Foundation
+// SPDX-License-Identifier: Apache-2.0
+
+// This is required because a diesel macro makes clippy sad
+#![allow(clippy::extra_unused_lifetimes)]
+
+use super::launchpad_utils::MintStageUpdatedEventData;
+use crate::{schema::launchpad_mint_stages, utils::util::parse_timestamp_secs};
+use bigdecimal::BigDecimal;
+use field_count::FieldCount;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, Deserialize, FieldCount, Identifiable, Insertable, Serialize)]
+#[diesel(primary_key(collection_id, stage))]
+#[diesel(table_name = launchpad_mint_stages)]
+pub struct LaunchpadMintStage {
+    pub collection_id: String,
+    pub stage: String,
+    pub contract_address: String,
+    pub is_allowlist: bool,
+    pub price: BigDecimal,
+    pub per_wallet_limit: Option<BigDecimal>,
+    pub start_time: chrono::NaiveDateTime,
+    pub end_time: chrono::NaiveDateTime,
+    pub last_transaction_version: i64,
+    pub last_transaction_timestamp: chrono::NaiveDateTime,
+}
+
+impl LaunchpadMintStage {
+    pub fn from_event(
+        contract_address: &str,
+        event: &MintStageUpdatedEventData,
+        txn_version: i64,
+        txn_timestamp: chrono::NaiveDateTime,
+    ) -> Self {
+        Self {
+            collection_id: event.get_collection_address(),
+            stage: event.get_stage().to_string(),
+            contract_address: contract_address.to_string(),
+            is_allowlist: event.is_allowlist(),
+            price: event.get_price().clone(),
+            per_wallet_limit: event.get_per_wallet_limit(),
+            start_time: parse_timestamp_secs(event.get_start_time(), txn_version),
+            end_time: parse_timestamp_secs(event.get_end_time(), txn_version),
+            last_transaction_version: txn_version,
+            last_transaction_timestamp: txn_timestamp,
+        }
+    }
+}
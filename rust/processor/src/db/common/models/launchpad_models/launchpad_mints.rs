@@ -0,0 +1,56 @@
+// Copyright © Aptos Foundation
+// SPDX-License-Identifier: Apache-2.0
+
+// This is required because a diesel macro makes clippy sad
+#![allow(clippy::extra_unused_lifetimes)]
+
+use super::launchpad_utils::MintEventData;
+use crate::schema::launchpad_mints;
+use bigdecimal::BigDecimal;
+use field_count::FieldCount;
+use serde::{Deserialize, Serialize};
+
+pub const ALLOWLIST_MINT_TYPE: &str = "allowlist";
+pub const PUBLIC_MINT_TYPE: &str = "public";
+
+#[derive(Clone, Debug, Deserialize, FieldCount, Identifiable, Insertable, Serialize)]
+#[diesel(primary_key(transaction_version, event_index))]
+#[diesel(table_name = launchpad_mints)]
+pub struct LaunchpadMint {
+    pub transaction_version: i64,
+    pub event_index: i64,
+    pub contract_address: String,
+    pub collection_id: String,
+    pub token_data_id: Option<String>,
+    pub stage: Option<String>,
+    pub mint_type: String,
+    pub minter: String,
+    pub price: BigDecimal,
+    pub amount: BigDecimal,
+    pub transaction_timestamp: chrono::NaiveDateTime,
+}
+
+impl LaunchpadMint {
+    pub fn from_event(
+        contract_address: &str,
+        event: &MintEventData,
+        mint_type: &str,
+        txn_version: i64,
+        event_index: i64,
+        txn_timestamp: chrono::NaiveDateTime,
+    ) -> Self {
+        Self {
+            transaction_version: txn_version,
+            event_index,
+            contract_address: contract_address.to_string(),
+            collection_id: event.get_collection_address(),
+            token_data_id: event.get_token_address(),
+            stage: event.get_stage().map(|s| s.to_string()),
+            mint_type: mint_type.to_string(),
+            minter: event.get_minter(),
+            price: event.get_price().clone(),
+            amount: event.get_amount().clone(),
+            transaction_timestamp: txn_timestamp,
+        }
+    }
+}
@@ -0,0 +1,261 @@
+// Copyright © Aptos Foundation
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::utils::util::{deserialize_from_string, standardize_address};
+use ahash::AHashMap;
+use anyhow::{Context, Result};
+use bigdecimal::{BigDecimal, One};
+use serde::{Deserialize, Serialize};
+
+/// Kind of a decoded launchpad event
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LaunchpadEventKind {
+    MintStageUpdated,
+    AllowlistMint,
+    PublicMint,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct OptionalString {
+    vec: Vec<String>,
+}
+
+impl OptionalString {
+    fn get_string(&self) -> Option<String> {
+        self.vec.first().cloned()
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct OptionalBigDecimal {
+    vec: Vec<BigDecimalWrapper>,
+}
+
+impl OptionalBigDecimal {
+    fn get_big_decimal(&self) -> Option<BigDecimal> {
+        self.vec.first().map(|v| v.0.clone())
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct BigDecimalWrapper(#[serde(deserialize_with = "deserialize_from_string")] pub BigDecimal);
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct MintStageUpdatedEventData {
+    collection: String,
+    stage: String,
+    #[serde(default)]
+    is_allowlist: bool,
+    #[serde(deserialize_with = "deserialize_from_string")]
+    price: BigDecimal,
+    per_wallet_limit: OptionalBigDecimal,
+    // Unix seconds
+    #[serde(deserialize_with = "deserialize_from_string")]
+    start_time: u64,
+    #[serde(deserialize_with = "deserialize_from_string")]
+    end_time: u64,
+}
+
+impl MintStageUpdatedEventData {
+    pub fn get_collection_address(&self) -> String {
+        standardize_address(&self.collection)
+    }
+
+    pub fn get_stage(&self) -> &str {
+        &self.stage
+    }
+
+    pub fn is_allowlist(&self) -> bool {
+        self.is_allowlist
+    }
+
+    pub fn get_price(&self) -> &BigDecimal {
+        &self.price
+    }
+
+    pub fn get_per_wallet_limit(&self) -> Option<BigDecimal> {
+        self.per_wallet_limit.get_big_decimal()
+    }
+
+    pub fn get_start_time(&self) -> u64 {
+        self.start_time
+    }
+
+    pub fn get_end_time(&self) -> u64 {
+        self.end_time
+    }
+}
+
+fn default_mint_amount() -> BigDecimal {
+    BigDecimal::one()
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct MintEventData {
+    collection: String,
+    // Token object, missing for v1 tokens or batched mints
+    token: OptionalString,
+    #[serde(default)]
+    stage: Option<String>,
+    minter: String,
+    // Total paid for the mint
+    #[serde(deserialize_with = "deserialize_from_string")]
+    price: BigDecimal,
+    #[serde(
+        default = "default_mint_amount",
+        deserialize_with = "deserialize_from_string"
+    )]
+    amount: BigDecimal,
+}
+
+impl MintEventData {
+    pub fn get_collection_address(&self) -> String {
+        standardize_address(&self.collection)
+    }
+
+    pub fn get_token_address(&self) -> Option<String> {
+        self.token.get_string().map(|t| standardize_address(&t))
+    }
+
+    pub fn get_stage(&self) -> Option<&str> {
+        self.stage.as_deref()
+    }
+
+    pub fn get_minter(&self) -> String {
+        standardize_address(&self.minter)
+    }
+
+    pub fn get_price(&self) -> &BigDecimal {
+        &self.price
+    }
+
+    pub fn get_amount(&self) -> &BigDecimal {
+        &self.amount
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum LaunchpadEvent {
+    MintStageUpdated(MintStageUpdatedEventData),
+    AllowlistMint(MintEventData),
+    PublicMint(MintEventData),
+}
+
+/// Decodes the events of one launchpad contract
+#[derive(Debug)]
+pub struct LaunchpadEventDecoder {
+    // Full event type -> kind
+    event_types: AHashMap<String, LaunchpadEventKind>,
+}
+
+impl LaunchpadEventDecoder {
+    const DEFAULT_EVENT_TYPES: [(&'static str, LaunchpadEventKind); 3] = [
+        (
+            "launchpad::MintStageUpdatedEvent",
+            LaunchpadEventKind::MintStageUpdated,
+        ),
+        (
+            "launchpad::AllowlistMintEvent",
+            LaunchpadEventKind::AllowlistMint,
+        ),
+        ("launchpad::PublicMintEvent", LaunchpadEventKind::PublicMint),
+    ];
+
+    /// `event_types` maps `module::Struct` of the event to its kind and replaces the defaults
+    /// when not empty
+    pub fn new(contract_address: &str, event_types: &AHashMap<String, LaunchpadEventKind>) -> Self {
+        let contract_address = standardize_address(contract_address);
+        let event_types = if event_types.is_empty() {
+            Self::DEFAULT_EVENT_TYPES
+                .iter()
+                .map(|(name, kind)| (format!("{}::{}", contract_address, name), *kind))
+                .collect()
+        } else {
+            event_types
+                .iter()
+                .map(|(name, kind)| (format!("{}::{}", contract_address, name), *kind))
+                .collect()
+        };
+        Self { event_types }
+    }
+
+    /// Returns None for events that don't belong to the launchpad
+    pub fn decode(
+        &self,
+        event_type: &str,
+        data: &str,
+        txn_version: i64,
+    ) -> Result<Option<LaunchpadEvent>> {
+        let kind = match self.event_types.get(event_type) {
+            Some(kind) => *kind,
+            None => return Ok(None),
+        };
+        let context = || {
+            format!(
+                "version {} failed! failed to parse type {}, data {:?}",
+                txn_version, event_type, data
+            )
+        };
+        let event = match kind {
+            LaunchpadEventKind::MintStageUpdated => {
+                LaunchpadEvent::MintStageUpdated(serde_json::from_str(data).with_context(context)?)
+            },
+            LaunchpadEventKind::AllowlistMint => {
+                LaunchpadEvent::AllowlistMint(serde_json::from_str(data).with_context(context)?)
+            },
+            LaunchpadEventKind::PublicMint => {
+                LaunchpadEvent::PublicMint(serde_json::from_str(data).with_context(context)?)
+            },
+        };
+        Ok(Some(event))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_decode_launchpad_events() {
+        let decoder = LaunchpadEventDecoder::new("0x123", &AHashMap::new());
+        let stage = decoder
+            .decode(
+                &format!("{}::launchpad::MintStageUpdatedEvent", standardize_address("0x123")),
+                r#"{"collection":"0xc","stage":"allowlist","is_allowlist":true,"price":"100000000","per_wallet_limit":{"vec":["2"]},"start_time":"1700000000","end_time":"1700086400"}"#,
+                1,
+            )
+            .unwrap();
+        match stage {
+            Some(LaunchpadEvent::MintStageUpdated(inner)) => {
+                assert_eq!(inner.get_collection_address(), standardize_address("0xc"));
+                assert!(inner.is_allowlist());
+                assert_eq!(inner.get_per_wallet_limit(), Some(BigDecimal::from(2)));
+            },
+            other => panic!("unexpected event {:?}", other),
+        }
+
+        let mint = decoder
+            .decode(
+                &format!(
+                    "{}::launchpad::PublicMintEvent",
+                    standardize_address("0x123")
+                ),
+                r#"{"collection":"0xc","token":{"vec":[]},"minter":"0xa","price":"100"}"#,
+                2,
+            )
+            .unwrap();
+        match mint {
+            Some(LaunchpadEvent::PublicMint(inner)) => {
+                assert_eq!(inner.get_token_address(), None);
+                assert_eq!(inner.get_amount(), &BigDecimal::one());
+            },
+            other => panic!("unexpected event {:?}", other),
+        }
+
+        assert!(decoder
+            .decode("0x1::object::TransferEvent", "{}", 3)
+            .unwrap()
+            .is_none());
+    }
+}
@@ -1,6 +1,9 @@
 // Copyright © Aptos Foundation
 // SPDX-License-Identifier: Apache-2.0
 
+pub mod launchpad_mint_stages;
+pub mod launchpad_mints;
 pub mod launchpad_transactions;
+pub mod launchpad_utils;
 
-pub use launchpad_transactions::*;
\ No newline at end of file
+pub use launchpad_transactions::*;
@@ -0,0 +1,66 @@
+// Copyright © Aptos Foundation
+// SPDX-License-Identifier: Apache-2.0
+
+// This is required because a diesel macro makes clippy sad
+#![allow(clippy::extra_unused_lifetimes)]
+
+use super::marketplace_utils::{MarketplaceEvent, MarketplaceEventKind};
+use crate::schema::marketplace_activities;
+use bigdecimal::BigDecimal;
+use field_count::FieldCount;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, Deserialize, FieldCount, Identifiable, Insertable, Serialize)]
+#[diesel(primary_key(transaction_version, event_index))]
+#[diesel(table_name = marketplace_activities)]
+pub struct MarketplaceActivity {
+    pub transaction_version: i64,
+    pub event_index: i64,
+    pub marketplace: String,
+    pub contract_address: String,
+    pub event_type: String,
+    pub listing_id: Option<String>,
+    pub offer_id: Option<String>,
+    pub token_data_id: Option<String>,
+    pub collection_id: Option<String>,
+    pub price: BigDecimal,
+    pub token_amount: Option<BigDecimal>,
+    pub seller: Option<String>,
+    pub buyer: Option<String>,
+    pub transaction_timestamp: chrono::NaiveDateTime,
+}
+
+impl MarketplaceActivity {
+    pub fn from_event(
+        marketplace: &str,
+        contract_address: &str,
+        event: &MarketplaceEvent,
+        txn_version: i64,
+        event_index: i64,
+        txn_timestamp: chrono::NaiveDateTime,
+    ) -> Self {
+        let (listing_id, offer_id) = match event.kind {
+            MarketplaceEventKind::ListingPlaced
+            | MarketplaceEventKind::ListingCanceled
+            | MarketplaceEventKind::ListingFilled => (Some(event.object_id.clone()), None),
+            _ => (None, Some(event.object_id.clone())),
+        };
+        let event_type: &'static str = event.kind.into();
+        Self {
+            transaction_version: txn_version,
+            event_index,
+            marketplace: marketplace.to_string(),
+            contract_address: contract_address.to_string(),
+            event_type: event_type.to_string(),
+            listing_id,
+            offer_id,
+            token_data_id: event.token.token_data_id.clone(),
+            collection_id: Some(event.token.collection_id.clone()),
+            price: event.price.clone(),
+            token_amount: event.token_amount.clone(),
+            seller: event.seller.clone(),
+            buyer: event.buyer.clone(),
+            transaction_timestamp: txn_timestamp,
+        }
+    }
+}
@@ -0,0 +1,62 @@
+// Copyright © Aptos Foundation
+// SPDX-License-Identifier: Apache-2.0
+
+// This is required because a diesel macro makes clippy sad
+#![allow(clippy::extra_unused_lifetimes)]
+
+use super::marketplace_utils::{MarketplaceEvent, MarketplaceEventKind};
+use crate::schema::current_bids;
+use bigdecimal::BigDecimal;
+use field_count::FieldCount;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, Deserialize, FieldCount, Identifiable, Insertable, Serialize)]
+#[diesel(primary_key(offer_id))]
+#[diesel(table_name = current_bids)]
+pub struct CurrentBid {
+    pub offer_id: String,
+    pub marketplace: String,
+    pub contract_address: String,
+    pub token_data_id: Option<String>,
+    pub collection_id: Option<String>,
+    pub buyer: String,
+    pub price: BigDecimal,
+    pub token_amount: Option<BigDecimal>,
+    pub is_active: bool,
+    pub last_transaction_version: i64,
+    pub last_transaction_timestamp: chrono::NaiveDateTime,
+}
+
+impl CurrentBid {
+    /// Latest state of the offer after an offer event. None for listing events and for
+    /// collection offer fills, which don't say how many tokens the offer still wants.
+    pub fn from_event(
+        marketplace: &str,
+        contract_address: &str,
+        event: &MarketplaceEvent,
+        txn_version: i64,
+        txn_timestamp: chrono::NaiveDateTime,
+    ) -> Option<Self> {
+        let is_active = match event.kind {
+            MarketplaceEventKind::TokenOfferPlaced
+            | MarketplaceEventKind::CollectionOfferPlaced => true,
+            MarketplaceEventKind::TokenOfferCanceled
+            | MarketplaceEventKind::TokenOfferFilled
+            | MarketplaceEventKind::CollectionOfferCanceled => false,
+            _ => return None,
+        };
+        Some(Self {
+            offer_id: event.object_id.clone(),
+            marketplace: marketplace.to_string(),
+            contract_address: contract_address.to_string(),
+            token_data_id: event.token.token_data_id.clone(),
+            collection_id: Some(event.token.collection_id.clone()),
+            buyer: event.buyer.clone()?,
+            price: event.price.clone(),
+            token_amount: event.token_amount.clone(),
+            is_active,
+            last_transaction_version: txn_version,
+            last_transaction_timestamp: txn_timestamp,
+        })
+    }
+}
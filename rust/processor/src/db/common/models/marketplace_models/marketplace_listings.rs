@@ -0,0 +1,66 @@
+// Copyright © Aptos Foundation
+// SPDX-License-Identifier: Apache-2.0
+
+// This is required because a diesel macro makes clippy sad
+#![allow(clippy::extra_unused_lifetimes)]
+
+use super::marketplace_utils::{MarketplaceEvent, MarketplaceEventKind};
+use crate::schema::{current_listings, listing_invalidations};
+use bigdecimal::BigDecimal;
+use field_count::FieldCount;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, Deserialize, FieldCount, Identifiable, Insertable, Serialize)]
+#[diesel(primary_key(listing_id))]
+#[diesel(table_name = current_listings)]
+pub struct CurrentListing {
+    pub listing_id: String,
+    pub marketplace: String,
+    pub contract_address: String,
+    pub token_data_id: String,
+    pub collection_id: String,
+    pub seller: String,
+    pub price: BigDecimal,
+    pub is_active: bool,
+    pub last_transaction_version: i64,
+    pub last_transaction_timestamp: chrono::NaiveDateTime,
+}
+
+impl CurrentListing {
+    /// Latest state of the listing after a listing event. None for offer events.
+    pub fn from_event(
+        marketplace: &str,
+        contract_address: &str,
+        event: &MarketplaceEvent,
+        txn_version: i64,
+        txn_timestamp: chrono::NaiveDateTime,
+    ) -> Option<Self> {
+        let is_active = match event.kind {
+            MarketplaceEventKind::ListingPlaced => true,
+            MarketplaceEventKind::ListingCanceled | MarketplaceEventKind::ListingFilled => false,
+            _ => return None,
+        };
+        Some(Self {
+            listing_id: event.object_id.clone(),
+            marketplace: marketplace.to_string(),
+            contract_address: contract_address.to_string(),
+            token_data_id: event.token.token_data_id.clone()?,
+            collection_id: event.token.collection_id.clone(),
+            seller: event.seller.clone()?,
+            price: event.price.clone(),
+            is_active,
+            last_transaction_version: txn_version,
+            last_transaction_timestamp: txn_timestamp,
+        })
+    }
+}
+
+/// A token changed owner outside of any marketplace, so its open listings can no longer be filled
+#[derive(Clone, Debug, Deserialize, FieldCount, Identifiable, Insertable, Serialize)]
+#[diesel(primary_key(token_data_id))]
+#[diesel(table_name = listing_invalidations)]
+pub struct ListingInvalidation {
+    pub token_data_id: String,
+    pub transaction_version: i64,
+    pub transaction_timestamp: chrono::NaiveDateTime,
+}
@@ -0,0 +1,571 @@
+// Copyright © Aptos Foundation
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::{
+    db::common::models::{
+        token_models::token_utils::{CollectionDataIdType, TokenDataIdType},
+        token_v2_models::v2_token_utils::ResourceReference,
+    },
+    utils::util::{deserialize_from_string, standardize_address},
+};
+use ahash::AHashMap;
+use anyhow::{Context, Result};
+use bigdecimal::BigDecimal;
+use serde::{Deserialize, Serialize};
+use std::fmt::Debug;
+
+/// Kind of a decoded marketplace event. Also the `event_type` written to `marketplace_activities`.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, PartialEq, Serialize, strum::IntoStaticStr)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum MarketplaceEventKind {
+    ListingPlaced,
+    ListingCanceled,
+    ListingFilled,
+    TokenOfferPlaced,
+    TokenOfferCanceled,
+    TokenOfferFilled,
+    CollectionOfferPlaced,
+    CollectionOfferCanceled,
+    CollectionOfferFilled,
+}
+
+/// Token and collection ids in the form used by `token_activities_v2` and
+/// `current_token_ownerships_v2`: the object address for v2 tokens and the hashed
+/// creator/collection/name for v1 tokens.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MarketplaceTokenIds {
+    pub token_data_id: Option<String>,
+    pub collection_id: String,
+}
+
+/// A marketplace event, independent of the contract that emitted it
+#[derive(Clone, Debug)]
+pub struct MarketplaceEvent {
+    pub kind: MarketplaceEventKind,
+    // Listing object for listing events, offer object for offer events
+    pub object_id: String,
+    pub token: MarketplaceTokenIds,
+    pub price: BigDecimal,
+    pub token_amount: Option<BigDecimal>,
+    pub seller: Option<String>,
+    pub buyer: Option<String>,
+}
+
+/// Decodes the events of one marketplace contract. Each supported contract family gets an
+/// implementation and a `MarketplaceDecoderKind` variant to select it from config.
+pub trait MarketplaceDecoder: Debug + Send + Sync {
+    /// Returns None for events that don't belong to this marketplace
+    fn decode(
+        &self,
+        event_type: &str,
+        data: &str,
+        txn_version: i64,
+    ) -> Result<Option<MarketplaceEvent>>;
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MarketplaceDecoderKind {
+    /// Events of the marketplace example contract published with aptos-core
+    /// (`aptos-move/move-examples/marketplace`), which several marketplaces deploy
+    AptosMarketplace,
+}
+
+impl MarketplaceDecoderKind {
+    /// `event_types` maps `module::Struct` of the event to its kind and replaces the
+    /// decoder's defaults when not empty
+    pub fn build(
+        &self,
+        contract_address: &str,
+        event_types: &AHashMap<String, MarketplaceEventKind>,
+    ) -> Box<dyn MarketplaceDecoder> {
+        match self {
+            Self::AptosMarketplace => {
+                Box::new(AptosMarketplaceDecoder::new(contract_address, event_types))
+            },
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct OptionalResourceReference {
+    vec: Vec<ResourceReference>,
+}
+
+impl OptionalResourceReference {
+    pub fn get_reference_address(&self) -> Option<String> {
+        self.vec.first().map(|r| r.get_reference_address())
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TokenMetadata {
+    creator_address: String,
+    collection_name: String,
+    collection: OptionalResourceReference,
+    token_name: String,
+    token: OptionalResourceReference,
+}
+
+impl TokenMetadata {
+    pub fn get_token_ids(&self) -> MarketplaceTokenIds {
+        let token_data_id = self.token.get_reference_address().unwrap_or_else(|| {
+            TokenDataIdType::new(
+                self.creator_address.clone(),
+                self.collection_name.clone(),
+                self.token_name.clone(),
+            )
+            .to_id()
+        });
+        MarketplaceTokenIds {
+            token_data_id: Some(token_data_id),
+            collection_id: get_collection_id(
+                &self.collection,
+                &self.creator_address,
+                &self.collection_name,
+            ),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CollectionMetadata {
+    creator_address: String,
+    collection_name: String,
+    collection: OptionalResourceReference,
+}
+
+impl CollectionMetadata {
+    pub fn get_token_ids(&self) -> MarketplaceTokenIds {
+        MarketplaceTokenIds {
+            token_data_id: None,
+            collection_id: get_collection_id(
+                &self.collection,
+                &self.creator_address,
+                &self.collection_name,
+            ),
+        }
+    }
+}
+
+fn get_collection_id(
+    collection: &OptionalResourceReference,
+    creator_address: &str,
+    collection_name: &str,
+) -> String {
+    collection.get_reference_address().unwrap_or_else(|| {
+        CollectionDataIdType::new(creator_address.to_string(), collection_name.to_string()).to_id()
+    })
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ListingEventData {
+    listing: String,
+    seller: String,
+    #[serde(default)]
+    purchaser: Option<String>,
+    #[serde(deserialize_with = "deserialize_from_string")]
+    price: BigDecimal,
+    token_metadata: TokenMetadata,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TokenOfferEventData {
+    token_offer: String,
+    purchaser: String,
+    #[serde(default)]
+    seller: Option<String>,
+    #[serde(deserialize_with = "deserialize_from_string")]
+    price: BigDecimal,
+    token_metadata: TokenMetadata,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CollectionOfferPlacedEventData {
+    collection_offer: String,
+    purchaser: String,
+    #[serde(deserialize_with = "deserialize_from_string")]
+    price: BigDecimal,
+    #[serde(deserialize_with = "deserialize_from_string")]
+    token_amount: BigDecimal,
+    collection_metadata: CollectionMetadata,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CollectionOfferCanceledEventData {
+    collection_offer: String,
+    purchaser: String,
+    #[serde(deserialize_with = "deserialize_from_string")]
+    price: BigDecimal,
+    #[serde(deserialize_with = "deserialize_from_string")]
+    remaining_token_amount: BigDecimal,
+    collection_metadata: CollectionMetadata,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CollectionOfferFilledEventData {
+    collection_offer: String,
+    purchaser: String,
+    seller: String,
+    #[serde(deserialize_with = "deserialize_from_string")]
+    price: BigDecimal,
+    token_metadata: TokenMetadata,
+}
+
+#[derive(Debug)]
+pub struct AptosMarketplaceDecoder {
+    // Full event type -> kind
+    event_types: AHashMap<String, MarketplaceEventKind>,
+}
+
+impl AptosMarketplaceDecoder {
+    const DEFAULT_EVENT_TYPES: [(&'static str, MarketplaceEventKind); 9] = [
+        (
+            "events::ListingPlacedEvent",
+            MarketplaceEventKind::ListingPlaced,
+        ),
+        (
+            "events::ListingCanceledEvent",
+            MarketplaceEventKind::ListingCanceled,
+        ),
+        (
+            "events::ListingFilledEvent",
+            MarketplaceEventKind::ListingFilled,
+        ),
+        (
+            "events::TokenOfferPlacedEvent",
+            MarketplaceEventKind::TokenOfferPlaced,
+        ),
+        (
+            "events::TokenOfferCanceledEvent",
+            MarketplaceEventKind::TokenOfferCanceled,
+        ),
+        (
+            "events::TokenOfferFilledEvent",
+            MarketplaceEventKind::TokenOfferFilled,
+        ),
+        (
+            "events::CollectionOfferPlacedEvent",
+            MarketplaceEventKind::CollectionOfferPlaced,
+        ),
+        (
+            "events::CollectionOfferCanceledEvent",
+            MarketplaceEventKind::CollectionOfferCanceled,
+        ),
+        (
+            "events::CollectionOfferFilledEvent",
+            MarketplaceEventKind::CollectionOfferFilled,
+        ),
+    ];
+
+    pub fn new(
+        contract_address: &str,
+        event_types: &AHashMap<String, MarketplaceEventKind>,
+    ) -> Self {
+        let contract_address = standardize_address(contract_address);
+        let event_types = if event_types.is_empty() {
+            Self::DEFAULT_EVENT_TYPES
+                .iter()
+                .map(|(name, kind)| (format!("{}::{}", contract_address, name), *kind))
+                .collect()
+        } else {
+            event_types
+                .iter()
+                .map(|(name, kind)| (format!("{}::{}", contract_address, name), *kind))
+                .collect()
+        };
+        Self { event_types }
+    }
+}
+
+impl MarketplaceDecoder for AptosMarketplaceDecoder {
+    fn decode(
+        &self,
+        event_type: &str,
+        data: &str,
+        txn_version: i64,
+    ) -> Result<Option<MarketplaceEvent>> {
+        let kind = match self.event_types.get(event_type) {
+            Some(kind) => *kind,
+            None => return Ok(None),
+        };
+        let context = || {
+            format!(
+                "version {} failed! failed to parse type {}, data {:?}",
+                txn_version, event_type, data
+            )
+        };
+        let event = match kind {
+            MarketplaceEventKind::ListingPlaced
+            | MarketplaceEventKind::ListingCanceled
+            | MarketplaceEventKind::ListingFilled => {
+                let inner: ListingEventData = serde_json::from_str(data).with_context(context)?;
+                MarketplaceEvent {
+                    kind,
+                    object_id: standardize_address(&inner.listing),
+                    token: inner.token_metadata.get_token_ids(),
+                    price: inner.price,
+                    token_amount: None,
+                    seller: Some(standardize_address(&inner.seller)),
+                    buyer: inner.purchaser.map(|p| standardize_address(&p)),
+                }
+            },
+            MarketplaceEventKind::TokenOfferPlaced
+            | MarketplaceEventKind::TokenOfferCanceled
+            | MarketplaceEventKind::TokenOfferFilled => {
+                let inner: TokenOfferEventData =
+                    serde_json::from_str(data).with_context(context)?;
+                MarketplaceEvent {
+                    kind,
+                    object_id: standardize_address(&inner.token_offer),
+                    token: inner.token_metadata.get_token_ids(),
+                    price: inner.price,
+                    token_amount: None,
+                    seller: inner.seller.map(|s| standardize_address(&s)),
+                    buyer: Some(standardize_address(&inner.purchaser)),
+                }
+            },
+            MarketplaceEventKind::CollectionOfferPlaced => {
+                let inner: CollectionOfferPlacedEventData =
+                    serde_json::from_str(data).with_context(context)?;
+                MarketplaceEvent {
+                    kind,
+                    object_id: standardize_address(&inner.collection_offer),
+                    token: inner.collection_metadata.get_token_ids(),
+                    price: inner.price,
+                    token_amount: Some(inner.token_amount),
+                    seller: None,
+                    buyer: Some(standardize_address(&inner.purchaser)),
+                }
+            },
+            MarketplaceEventKind::CollectionOfferCanceled => {
+                let inner: CollectionOfferCanceledEventData =
+                    serde_json::from_str(data).with_context(context)?;
+                MarketplaceEvent {
+                    kind,
+                    object_id: standardize_address(&inner.collection_offer),
+                    token: inner.collection_metadata.get_token_ids(),
+                    price: inner.price,
+                    token_amount: Some(inner.remaining_token_amount),
+                    seller: None,
+                    buyer: Some(standardize_address(&inner.purchaser)),
+                }
+            },
+            MarketplaceEventKind::CollectionOfferFilled => {
+                let inner: CollectionOfferFilledEventData =
+                    serde_json::from_str(data).with_context(context)?;
+                MarketplaceEvent {
+                    kind,
+                    object_id: standardize_address(&inner.collection_offer),
+                    token: inner.token_metadata.get_token_ids(),
+                    price: inner.price,
+                    token_amount: None,
+                    seller: Some(standardize_address(&inner.seller)),
+                    buyer: Some(standardize_address(&inner.purchaser)),
+                }
+            },
+        };
+        Ok(Some(event))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::str::FromStr;
+
+    const CONTRACT: &str = "0xfe";
+
+    fn decode(
+        decoder: &AptosMarketplaceDecoder,
+        name: &str,
+        data: serde_json::Value,
+    ) -> Result<Option<MarketplaceEvent>> {
+        let event_type = format!("{}::events::{}", standardize_address(CONTRACT), name);
+        decoder.decode(&event_type, &data.to_string(), 1)
+    }
+
+    fn token_metadata() -> serde_json::Value {
+        serde_json::json!({
+            "creator_address": "0xc",
+            "collection_name": "Collection",
+            "collection": { "vec": [{ "inner": "0xc0" }] },
+            "token_name": "Token #1",
+            "token": { "vec": [{ "inner": "0x70" }] },
+        })
+    }
+
+    fn v2_token() -> MarketplaceTokenIds {
+        MarketplaceTokenIds {
+            token_data_id: Some(standardize_address("0x70")),
+            collection_id: standardize_address("0xc0"),
+        }
+    }
+
+    #[test]
+    fn test_decode_listing_events() {
+        let decoder = AptosMarketplaceDecoder::new(CONTRACT, &AHashMap::new());
+
+        let placed = decode(
+            &decoder,
+            "ListingPlacedEvent",
+            serde_json::json!({
+                "listing": "0x1",
+                "seller": "0x5",
+                "price": "100",
+                "token_metadata": token_metadata(),
+            }),
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(placed.kind, MarketplaceEventKind::ListingPlaced);
+        assert_eq!(placed.object_id, standardize_address("0x1"));
+        assert_eq!(placed.token, v2_token());
+        assert_eq!(placed.price, BigDecimal::from(100));
+        assert_eq!(placed.seller, Some(standardize_address("0x5")));
+        assert_eq!(placed.buyer, None);
+
+        let canceled = decode(
+            &decoder,
+            "ListingCanceledEvent",
+            serde_json::json!({
+                "listing": "0x1",
+                "seller": "0x5",
+                "price": "100",
+                "token_metadata": token_metadata(),
+            }),
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(canceled.kind, MarketplaceEventKind::ListingCanceled);
+        assert_eq!(canceled.buyer, None);
+
+        let filled = decode(
+            &decoder,
+            "ListingFilledEvent",
+            serde_json::json!({
+                "listing": "0x1",
+                "seller": "0x5",
+                "purchaser": "0x6",
+                "price": "100",
+                "token_metadata": token_metadata(),
+            }),
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(filled.kind, MarketplaceEventKind::ListingFilled);
+        assert_eq!(filled.seller, Some(standardize_address("0x5")));
+        assert_eq!(filled.buyer, Some(standardize_address("0x6")));
+    }
+
+    #[test]
+    fn test_decode_offer_events() {
+        let decoder = AptosMarketplaceDecoder::new(CONTRACT, &AHashMap::new());
+
+        // v1 tokens have no object references, their ids are derived from the names
+        let token_offer = decode(
+            &decoder,
+            "TokenOfferFilledEvent",
+            serde_json::json!({
+                "token_offer": "0x2",
+                "purchaser": "0x6",
+                "seller": "0x5",
+                "price": "7",
+                "token_metadata": {
+                    "creator_address": "0xc",
+                    "collection_name": "Collection",
+                    "collection": { "vec": [] },
+                    "token_name": "Token #1",
+                    "token": { "vec": [] },
+                },
+            }),
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(token_offer.kind, MarketplaceEventKind::TokenOfferFilled);
+        assert_eq!(token_offer.object_id, standardize_address("0x2"));
+        assert_eq!(token_offer.token, MarketplaceTokenIds {
+            token_data_id: Some(
+                TokenDataIdType::new(
+                    "0xc".to_string(),
+                    "Collection".to_string(),
+                    "Token #1".to_string()
+                )
+                .to_id()
+            ),
+            collection_id: CollectionDataIdType::new("0xc".to_string(), "Collection".to_string())
+                .to_id(),
+        });
+        assert_eq!(token_offer.seller, Some(standardize_address("0x5")));
+        assert_eq!(token_offer.buyer, Some(standardize_address("0x6")));
+
+        let collection_offer = decode(
+            &decoder,
+            "CollectionOfferPlacedEvent",
+            serde_json::json!({
+                "collection_offer": "0x3",
+                "purchaser": "0x6",
+                "price": "5",
+                "token_amount": "10",
+                "collection_metadata": {
+                    "creator_address": "0xc",
+                    "collection_name": "Collection",
+                    "collection": { "vec": [{ "inner": "0xc0" }] },
+                },
+            }),
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(
+            collection_offer.kind,
+            MarketplaceEventKind::CollectionOfferPlaced
+        );
+        assert_eq!(collection_offer.token, MarketplaceTokenIds {
+            token_data_id: None,
+            collection_id: standardize_address("0xc0"),
+        });
+        assert_eq!(
+            collection_offer.token_amount,
+            Some(BigDecimal::from_str("10").unwrap())
+        );
+        assert_eq!(collection_offer.seller, None);
+
+        let collection_offer_filled = decode(
+            &decoder,
+            "CollectionOfferFilledEvent",
+            serde_json::json!({
+                "collection_offer": "0x3",
+                "purchaser": "0x6",
+                "seller": "0x5",
+                "price": "5",
+                "token_metadata": token_metadata(),
+            }),
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(
+            collection_offer_filled.kind,
+            MarketplaceEventKind::CollectionOfferFilled
+        );
+        assert_eq!(collection_offer_filled.token, v2_token());
+    }
+
+    #[test]
+    fn test_decode_unknown_and_malformed_events() {
+        let decoder = AptosMarketplaceDecoder::new(CONTRACT, &AHashMap::new());
+
+        // Events of other contracts are not marketplace events
+        assert!(decoder
+            .decode("0x1::coin::DepositEvent", "{}", 1)
+            .unwrap()
+            .is_none());
+        // Matching events that don't decode are errors, so the caller can count them
+        assert!(decode(
+            &decoder,
+            "ListingPlacedEvent",
+            serde_json::json!({ "listing": "0x1" })
+        )
+        .is_err());
+    }
+}
@@ -0,0 +1,7 @@
+// Copyright © Aptos Foundation
+// SPDX-License-Identifier: Apache-2.0
+
+pub mod marketplace_activities;
+pub mod marketplace_bids;
+pub mod marketplace_listings;
+pub mod marketplace_utils;
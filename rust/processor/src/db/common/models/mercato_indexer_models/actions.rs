@@ -0,0 +1,36 @@
+// Copyright © Aptos Foundation
+// SPDX-License-Identifier: Apache-2.0
+
+// This is required because a diesel macro makes clippy sad
+#![allow(clippy::extra_unused_lifetimes)]
+
+use crate::schema::action;
+use field_count::FieldCount;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+pub const MINT_ACTION: &str = "mint";
+pub const TRANSFER_ACTION: &str = "transfer";
+pub const BURN_ACTION: &str = "burn";
+pub const LIST_ACTION: &str = "list";
+pub const DELIST_ACTION: &str = "delist";
+pub const BUY_ACTION: &str = "buy";
+
+/// Activity row of the marketplace feed. Every row is written to both `action` and
+/// `recent_action`.
+#[derive(Clone, Debug, Deserialize, FieldCount, Identifiable, Insertable, Serialize)]
+#[diesel(primary_key(tx_id, tx_index))]
+#[diesel(table_name = action)]
+pub struct Action {
+    pub tx_id: String,
+    pub tx_index: i64,
+    pub action_type: String,
+    pub seller: Option<String>,
+    pub buyer: Option<String>,
+    pub block_height: i64,
+    pub block_time: chrono::NaiveDateTime,
+    pub nonce: i64,
+    pub collection_id: Uuid,
+    pub nft_meta_id: Uuid,
+    pub smart_contract_id: Uuid,
+}
@@ -0,0 +1,83 @@
+// Copyright © Aptos Foundation
+// SPDX-License-Identifier: Apache-2.0
+
+// This is required because a diesel macro makes clippy sad
+#![allow(clippy::extra_unused_lifetimes)]
+
+use super::{nft_meta::NftMeta, token_activities::TokenActivity};
+use crate::{schema::mercato_indexer_dead_letters, utils::database::MyDbConnection};
+use diesel::prelude::*;
+use diesel_async::RunQueryDsl;
+use field_count::FieldCount;
+use serde::{Deserialize, Serialize};
+
+/// A batch that could not be written after all retries, kept so it can be replayed on startup
+#[derive(Clone, Debug, Deserialize, FieldCount, Insertable, Serialize)]
+#[diesel(table_name = mercato_indexer_dead_letters)]
+pub struct MercatoIndexerDeadLetter {
+    pub processor: String,
+    pub start_version: i64,
+    pub end_version: i64,
+    pub nft_meta: serde_json::Value,
+    pub actions: serde_json::Value,
+    pub error: String,
+}
+
+impl MercatoIndexerDeadLetter {
+    pub fn new(
+        processor: &str,
+        start_version: u64,
+        end_version: u64,
+        nft_metas: &[NftMeta],
+        activities: &[TokenActivity],
+        error: &diesel::result::Error,
+    ) -> Self {
+        Self {
+            processor: processor.to_string(),
+            start_version: start_version as i64,
+            end_version: end_version as i64,
+            nft_meta: serde_json::to_value(nft_metas).unwrap(),
+            actions: serde_json::to_value(activities).unwrap(),
+            error: error.to_string(),
+        }
+    }
+}
+
+/// A stored dead letter, read back to replay it
+#[derive(Clone, Debug, Identifiable, Queryable)]
+#[diesel(table_name = mercato_indexer_dead_letters)]
+pub struct MercatoIndexerDeadLetterQuery {
+    pub id: i64,
+    pub processor: String,
+    pub start_version: i64,
+    pub end_version: i64,
+    pub nft_meta: serde_json::Value,
+    pub actions: serde_json::Value,
+    pub error: String,
+    pub inserted_at: chrono::NaiveDateTime,
+}
+
+impl MercatoIndexerDeadLetterQuery {
+    /// Dead letters of a processor, oldest batch first so mints are replayed before later transfers
+    pub async fn get_by_processor(
+        conn: &mut MyDbConnection,
+        processor: &str,
+    ) -> diesel::QueryResult<Vec<Self>> {
+        mercato_indexer_dead_letters::table
+            .filter(mercato_indexer_dead_letters::processor.eq(processor))
+            .order((
+                mercato_indexer_dead_letters::start_version.asc(),
+                mercato_indexer_dead_letters::id.asc(),
+            ))
+            .load::<Self>(conn)
+            .await
+    }
+
+    pub fn nft_metas(&self) -> serde_json::Result<Vec<NftMeta>> {
+        serde_json::from_value(self.nft_meta.clone())
+    }
+
+    pub fn activities(&self) -> serde_json::Result<Vec<TokenActivity>> {
+        serde_json::from_value(self.actions.clone())
+    }
+}
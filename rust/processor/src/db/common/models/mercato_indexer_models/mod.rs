@@ -0,0 +1,7 @@
+// Copyright © Aptos Foundation
+// SPDX-License-Identifier: Apache-2.0
+
+pub mod actions;
+pub mod dead_letters;
+pub mod nft_meta;
+pub mod token_activities;
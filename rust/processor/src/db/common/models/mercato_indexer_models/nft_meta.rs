@@ -0,0 +1,71 @@
+// Copyright © Aptos Foundation
+// SPDX-License-Identifier: Apache-2.0
+
+// This is required because a diesel macro makes clippy sad
+#![allow(clippy::extra_unused_lifetimes)]
+
+use crate::{schema::nft_meta, utils::database::MyDbConnection};
+use diesel::prelude::*;
+use diesel_async::RunQueryDsl;
+use field_count::FieldCount;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// Catalog entry of a minted NFT. Values come from on-chain data, so they must only ever be
+/// written through bind parameters.
+#[derive(Clone, Debug, Deserialize, FieldCount, Identifiable, Insertable, Serialize)]
+#[diesel(primary_key(id))]
+#[diesel(table_name = nft_meta)]
+pub struct NftMeta {
+    pub id: Uuid,
+    pub name: String,
+    pub token_id: String,
+    pub collection_id: Uuid,
+    pub chain_id: Uuid,
+    pub smart_contract_id: Uuid,
+    pub properties: serde_json::Value,
+    pub mint_tx: String,
+    pub owner: String,
+    pub owner_block_height: i64,
+    pub owner_tx_id: String,
+}
+
+/// Catalog ids of an already indexed NFT, used to attach later activity to it
+#[derive(Clone, Debug, Identifiable, Queryable)]
+#[diesel(primary_key(id))]
+#[diesel(table_name = nft_meta)]
+pub struct NftMetaQuery {
+    pub id: Uuid,
+    pub token_id: String,
+    pub collection_id: Uuid,
+    pub smart_contract_id: Uuid,
+}
+
+impl NftMetaQuery {
+    pub async fn get_by_token_ids(
+        conn: &mut MyDbConnection,
+        token_ids: &[String],
+        collection_ids: &[Uuid],
+    ) -> diesel::QueryResult<Vec<Self>> {
+        nft_meta::table
+            .select((
+                nft_meta::id,
+                nft_meta::token_id,
+                nft_meta::collection_id,
+                nft_meta::smart_contract_id,
+            ))
+            .filter(nft_meta::token_id.eq_any(token_ids))
+            .filter(nft_meta::collection_id.eq_any(collection_ids))
+            .load::<Self>(conn)
+            .await
+    }
+}
+
+/// Latest owner of an NFT. Only applied if it is not older than the owner already stored.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct NftMetaOwner {
+    pub id: Uuid,
+    pub owner: String,
+    pub owner_block_height: i64,
+    pub owner_tx_id: String,
+}
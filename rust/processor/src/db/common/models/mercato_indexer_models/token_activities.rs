@@ -0,0 +1,44 @@
+// Copyright © Aptos Foundation
+// SPDX-License-Identifier: Apache-2.0
+
+// This is required because a diesel macro makes clippy sad
+#![allow(clippy::extra_unused_lifetimes)]
+
+use crate::{schema::mercato_indexer_pending_actions, utils::database::MyDbConnection};
+use diesel::prelude::*;
+use diesel_async::RunQueryDsl;
+use field_count::FieldCount;
+use serde::{Deserialize, Serialize};
+
+/// Ownership-changing event of a token, before it is matched to its `nft_meta` row. Activities
+/// that can't be matched yet are parked in `mercato_indexer_pending_actions`.
+#[derive(Clone, Debug, Deserialize, FieldCount, Identifiable, Insertable, Queryable, Serialize)]
+#[diesel(primary_key(tx_id, tx_index))]
+#[diesel(table_name = mercato_indexer_pending_actions)]
+pub struct TokenActivity {
+    pub tx_id: String,
+    // Index of the token event (mint, transfer or burn) within the transaction
+    pub tx_index: i64,
+    pub token_id: String,
+    pub action_type: String,
+    pub seller: Option<String>,
+    pub buyer: Option<String>,
+    // Owner of the token after this event, if the event moved it
+    pub new_owner: Option<String>,
+    pub block_height: i64,
+    pub block_time: chrono::NaiveDateTime,
+    pub nonce: i64,
+}
+
+impl TokenActivity {
+    /// Pending activities of the given tokens
+    pub async fn get_by_token_ids(
+        conn: &mut MyDbConnection,
+        token_ids: &[String],
+    ) -> diesel::QueryResult<Vec<Self>> {
+        mercato_indexer_pending_actions::table
+            .filter(mercato_indexer_pending_actions::token_id.eq_any(token_ids))
+            .load::<Self>(conn)
+            .await
+    }
+}
@@ -5,11 +5,15 @@ pub mod account_models;
 pub mod account_transaction_models;
 pub mod ans_models;
 pub mod coin_models;
+pub mod collection_stats_models;
 pub mod default_models;
 pub mod events_models;
 pub mod fungible_asset_models;
 pub mod ledger_info;
+pub mod marketplace_models;
+pub mod mercato_indexer_models;
 pub mod object_models;
+pub mod processor_ranges;
 pub mod processor_status;
 pub mod property_map;
 pub mod stake_models;
@@ -0,0 +1,42 @@
+// Copyright © Aptos Foundation
+// SPDX-License-Identifier: Apache-2.0
+
+#![allow(clippy::extra_unused_lifetimes)]
+
+use crate::{schema::processor_ranges, utils::database::DbPoolConnection};
+use diesel::{ExpressionMethods, QueryDsl};
+use diesel_async::RunQueryDsl;
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = processor_ranges)]
+/// A contiguous range processed ahead of processor_status.last_success_version
+pub struct ProcessorRange {
+    pub processor: String,
+    pub start_version: i64,
+    pub end_version: i64,
+    pub last_transaction_timestamp: Option<chrono::NaiveDateTime>,
+}
+
+#[derive(Debug, Queryable)]
+#[diesel(table_name = processor_ranges)]
+/// A contiguous range processed ahead of processor_status.last_success_version
+pub struct ProcessorRangeQuery {
+    pub processor: String,
+    pub start_version: i64,
+    pub end_version: i64,
+    pub last_transaction_timestamp: Option<chrono::NaiveDateTime>,
+    pub last_updated: chrono::NaiveDateTime,
+}
+
+impl ProcessorRangeQuery {
+    pub async fn get_by_processor(
+        processor_name: &str,
+        conn: &mut DbPoolConnection<'_>,
+    ) -> diesel::QueryResult<Vec<Self>> {
+        processor_ranges::table
+            .filter(processor_ranges::processor.eq(processor_name))
+            .order(processor_ranges::start_version.asc())
+            .load::<Self>(conn)
+            .await
+    }
+}
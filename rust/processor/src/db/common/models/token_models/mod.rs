@@ -2,7 +2,9 @@
 // SPDX-License-Identifier: Apache-2.0
 
 pub mod collection_datas;
+pub mod nft_holdings;
 pub mod nft_points;
+pub mod nft_points_rules;
 pub mod token_activities;
 pub mod token_claims;
 pub mod token_datas;
@@ -0,0 +1,392 @@
+// Copyright © Aptos Foundation
+// SPDX-License-Identifier: Apache-2.0
+
+//! State of the `holding` nft_points rules. A holding starts when an owner's balance of a token
+//! of the rule's collection becomes positive. Every completed period since then awards points,
+//! either when the balance changes again or when a batch ends after the period. A period is
+//! awarded with the balance held at its end, and periods outside the campaign window award
+//! nothing.
+
+// This is required because a diesel macro makes clippy sad
+#![allow(clippy::extra_unused_lifetimes)]
+#![allow(clippy::unused_unit)]
+
+use super::{
+    nft_points::NftPoints,
+    nft_points_rules::{HoldingTrigger, NftPointsRule},
+    token_ownerships::CurrentTokenOwnership,
+};
+use crate::{
+    schema::nft_holdings,
+    utils::{counters::NFT_POINTS_RULE_ERROR_COUNT, database::MyDbConnection},
+};
+use ahash::AHashMap;
+use bigdecimal::{BigDecimal, Zero};
+use chrono::{Duration, NaiveDateTime};
+use diesel::prelude::*;
+use diesel_async::RunQueryDsl;
+use field_count::FieldCount;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+// rule_name, rules_version, token_data_id_hash, property_version, owner_address
+pub type NftHoldingPK = (String, String, String, BigDecimal, String);
+
+#[derive(Clone, Debug, Deserialize, FieldCount, Identifiable, Insertable, Queryable, Serialize)]
+#[diesel(primary_key(
+    rule_name,
+    rules_version,
+    token_data_id_hash,
+    property_version,
+    owner_address
+))]
+#[diesel(table_name = nft_holdings)]
+pub struct NftHolding {
+    pub rule_name: String,
+    pub rules_version: String,
+    pub token_data_id_hash: String,
+    pub property_version: BigDecimal,
+    pub owner_address: String,
+    pub creator_address: String,
+    pub collection_name: String,
+    pub token_name: String,
+    // Zero once the owner no longer holds the token
+    pub amount: BigDecimal,
+    pub held_since: NaiveDateTime,
+    // End of the last awarded period
+    pub accrued_until: NaiveDateTime,
+    pub last_transaction_version: i64,
+}
+
+impl NftHolding {
+    pub fn pk(&self) -> NftHoldingPK {
+        (
+            self.rule_name.clone(),
+            self.rules_version.clone(),
+            self.token_data_id_hash.clone(),
+            self.property_version.clone(),
+            self.owner_address.clone(),
+        )
+    }
+
+    /// Holdings of a rule of the tokens in `token_data_id_hashes`, plus the held ones awarded up to
+    /// `due_before` or earlier. Locked so concurrent batches apply them one after another.
+    pub async fn get_for_update(
+        conn: &mut MyDbConnection,
+        rule_name: &str,
+        rules_version: &str,
+        token_data_id_hashes: &[String],
+        due_before: NaiveDateTime,
+    ) -> diesel::QueryResult<Vec<Self>> {
+        nft_holdings::table
+            .select((
+                nft_holdings::rule_name,
+                nft_holdings::rules_version,
+                nft_holdings::token_data_id_hash,
+                nft_holdings::property_version,
+                nft_holdings::owner_address,
+                nft_holdings::creator_address,
+                nft_holdings::collection_name,
+                nft_holdings::token_name,
+                nft_holdings::amount,
+                nft_holdings::held_since,
+                nft_holdings::accrued_until,
+                nft_holdings::last_transaction_version,
+            ))
+            .filter(nft_holdings::rule_name.eq(rule_name))
+            .filter(nft_holdings::rules_version.eq(rules_version))
+            .filter(
+                nft_holdings::token_data_id_hash
+                    .eq_any(token_data_id_hashes)
+                    .or(nft_holdings::amount
+                        .gt(BigDecimal::zero())
+                        .and(nft_holdings::accrued_until.le(due_before))),
+            )
+            .order((
+                nft_holdings::token_data_id_hash.asc(),
+                nft_holdings::property_version.asc(),
+                nft_holdings::owner_address.asc(),
+            ))
+            .for_update()
+            .load::<Self>(conn)
+            .await
+    }
+}
+
+/// Applies balance changes and ended periods to the holdings of one `holding` rule and collects
+/// the points they award
+pub struct NftHoldingLedger<'a> {
+    rule: &'a NftPointsRule,
+    trigger: &'a HoldingTrigger,
+    rules_version: &'a str,
+    holdings: AHashMap<NftHoldingPK, NftHolding>,
+    points: Vec<NftPoints>,
+}
+
+impl<'a> NftHoldingLedger<'a> {
+    pub fn new(
+        rule: &'a NftPointsRule,
+        trigger: &'a HoldingTrigger,
+        rules_version: &'a str,
+        holdings: Vec<NftHolding>,
+    ) -> Self {
+        Self {
+            rule,
+            trigger,
+            rules_version,
+            holdings: holdings.into_iter().map(|h| (h.pk(), h)).collect(),
+            points: vec![],
+        }
+    }
+
+    /// Holdings awarded up to this time have completed a period by `timestamp`
+    pub fn due_before(trigger: &HoldingTrigger, timestamp: NaiveDateTime) -> NaiveDateTime {
+        timestamp - period(trigger)
+    }
+
+    /// Applies an ownership change. Ownerships must be applied in transaction order, changes
+    /// older than the holding are ignored.
+    pub fn apply(&mut self, ownership: &CurrentTokenOwnership) {
+        if !self
+            .trigger
+            .matches(&ownership.creator_address, &ownership.collection_name)
+        {
+            return;
+        }
+        let version = ownership.last_transaction_version;
+        let timestamp = ownership.last_transaction_timestamp;
+        let pk = (
+            self.rule.name.clone(),
+            self.rules_version.to_string(),
+            ownership.token_data_id_hash.clone(),
+            ownership.property_version.clone(),
+            ownership.owner_address.clone(),
+        );
+        match self.holdings.get_mut(&pk) {
+            Some(holding) => {
+                if holding.last_transaction_version >= version {
+                    return;
+                }
+                if holding.amount > BigDecimal::zero() {
+                    self.points.extend(accrue(
+                        self.rule,
+                        self.trigger,
+                        holding,
+                        version,
+                        timestamp,
+                    ));
+                } else if ownership.amount > BigDecimal::zero() {
+                    holding.held_since = timestamp;
+                    holding.accrued_until = accrual_start(self.rule, timestamp);
+                }
+                holding.token_name = ownership.name.clone();
+                holding.amount = ownership.amount.clone();
+                holding.last_transaction_version = version;
+            },
+            None => {
+                self.holdings.insert(pk, NftHolding {
+                    rule_name: self.rule.name.clone(),
+                    rules_version: self.rules_version.to_string(),
+                    token_data_id_hash: ownership.token_data_id_hash.clone(),
+                    property_version: ownership.property_version.clone(),
+                    owner_address: ownership.owner_address.clone(),
+                    creator_address: ownership.creator_address.clone(),
+                    collection_name: ownership.collection_name.clone(),
+                    token_name: ownership.name.clone(),
+                    amount: ownership.amount.clone(),
+                    held_since: timestamp,
+                    accrued_until: accrual_start(self.rule, timestamp),
+                    last_transaction_version: version,
+                });
+            },
+        }
+    }
+
+    /// Awards the periods every holding completed by `timestamp`, the time of the batch's last
+    /// transaction
+    pub fn accrue_until(&mut self, version: i64, timestamp: NaiveDateTime) {
+        for holding in self.holdings.values_mut() {
+            if holding.amount > BigDecimal::zero() {
+                self.points
+                    .extend(accrue(self.rule, self.trigger, holding, version, timestamp));
+            }
+        }
+    }
+
+    /// Holdings to write, sorted by PK, and the points they awarded
+    pub fn into_parts(self) -> (Vec<NftHolding>, Vec<NftPoints>) {
+        let mut holdings = self.holdings.into_values().collect::<Vec<_>>();
+        holdings.sort_by_key(|h| h.pk());
+        (holdings, self.points)
+    }
+}
+
+/// Periods held since `timestamp` count from here, the start of the campaign at the earliest
+fn accrual_start(rule: &NftPointsRule, timestamp: NaiveDateTime) -> NaiveDateTime {
+    match rule.start_time {
+        Some(start_time) if start_time > timestamp => start_time,
+        _ => timestamp,
+    }
+}
+
+fn period(trigger: &HoldingTrigger) -> Duration {
+    Duration::seconds(trigger.period_secs.get() as i64)
+}
+
+/// Awards the periods a holding completed by `timestamp` and moves `accrued_until` past them
+fn accrue(
+    rule: &NftPointsRule,
+    trigger: &HoldingTrigger,
+    holding: &mut NftHolding,
+    version: i64,
+    timestamp: NaiveDateTime,
+) -> Option<NftPoints> {
+    let end = match rule.end_time {
+        Some(end_time) if end_time < timestamp => end_time,
+        _ => timestamp,
+    };
+    let period = period(trigger);
+    let periods = (end - holding.accrued_until).num_seconds() / period.num_seconds();
+    if periods <= 0 {
+        return None;
+    }
+    holding.accrued_until += Duration::seconds(period.num_seconds() * periods);
+    let context = serde_json::json!({
+        "owner": holding.owner_address,
+        "token_name": holding.token_name,
+        "creator_address": holding.creator_address,
+        "collection_name": holding.collection_name,
+        "token_amount": holding.amount.to_string(),
+        "periods": periods,
+    });
+    let event_index = holding_event_index(holding);
+    match rule.evaluate(&context) {
+        Ok(award) => award.map(|award| NftPoints {
+            transaction_version: version,
+            owner_address: award.owner_address,
+            token_name: award.token_name,
+            point_type: award.point_type,
+            amount: award.amount,
+            transaction_timestamp: timestamp,
+            event_index,
+            rule_name: holding.rule_name.clone(),
+            rules_version: holding.rules_version.clone(),
+        }),
+        Err(e) => {
+            NFT_POINTS_RULE_ERROR_COUNT
+                .with_label_values(&[rule.name.as_str(), holding.rules_version.as_str()])
+                .inc();
+            tracing::error!(
+                transaction_version = version,
+                event_index = event_index,
+                rule = rule.name.as_str(),
+                rules_version = holding.rules_version.as_str(),
+                error = ?e,
+                "Failed to evaluate nft_points rule"
+            );
+            None
+        },
+    }
+}
+
+/// Event index of the points a holding awards in a transaction. Derived from the holding so
+/// holdings awarded in the same transaction get their own row, below the -1 of entry function
+/// rules.
+fn holding_event_index(holding: &NftHolding) -> i64 {
+    let hash = Sha256::digest(
+        format!(
+            "{}:{}:{}",
+            holding.token_data_id_hash, holding.property_version, holding.owner_address
+        )
+        .as_bytes(),
+    );
+    -(i64::from_be_bytes(hash[..8].try_into().unwrap()) & (i64::MAX >> 1)) - 2
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        db::common::models::token_models::nft_points_rules::NftPointsRulesConfig,
+        utils::util::standardize_address,
+    };
+
+    fn timestamp(day: u32, hour: u32) -> NaiveDateTime {
+        chrono::NaiveDate::from_ymd_opt(2026, 10, day)
+            .unwrap()
+            .and_hms_opt(hour, 0, 0)
+            .unwrap()
+    }
+
+    fn ownership(owner: &str, amount: i64, version: i64, day: u32) -> CurrentTokenOwnership {
+        CurrentTokenOwnership {
+            token_data_id_hash: "ab".to_string(),
+            property_version: BigDecimal::zero(),
+            owner_address: standardize_address(owner),
+            creator_address: standardize_address("0x1"),
+            collection_name: "Mercato".to_string(),
+            name: "Mercato #1".to_string(),
+            amount: BigDecimal::from(amount),
+            token_properties: serde_json::json!({}),
+            last_transaction_version: version,
+            collection_data_id_hash: "cd".to_string(),
+            table_type: "0x3::token::TokenStore".to_string(),
+            last_transaction_timestamp: timestamp(day, 12),
+        }
+    }
+
+    #[test]
+    fn test_holding_ledger() {
+        let config: NftPointsRulesConfig = serde_json::from_value(serde_json::json!({
+            "version": "1",
+            "rules": [{
+                "name": "holding",
+                "trigger": {"holding": {
+                    "creator_address": "0x1",
+                    "collection_name": "Mercato",
+                    "period_secs": 86400,
+                }},
+                "owner": "$.owner",
+                "token_name": "$.token_name",
+                "point_type": "holding",
+                "amount": "$.periods * $.token_amount * 10",
+            }],
+        }))
+        .unwrap()
+        .standardized();
+        let (rule, trigger) = config.holding_rules()[0];
+
+        let mut ledger = NftHoldingLedger::new(rule, trigger, &config.version, vec![]);
+        ledger.apply(&ownership("0xa", 1, 10, 1));
+        // A day and a half later only the first day is awarded
+        ledger.accrue_until(20, timestamp(3, 0));
+        // Transferred away on day 4, the second day is awarded and the holding is released
+        ledger.apply(&ownership("0xa", 0, 30, 4));
+        ledger.apply(&ownership("0xb", 1, 30, 4));
+        // An older change written out of order doesn't restart the holding
+        ledger.apply(&ownership("0xa", 1, 25, 4));
+        ledger.accrue_until(40, timestamp(6, 12));
+
+        let (holdings, points) = ledger.into_parts();
+        let awarded = points
+            .iter()
+            .map(|p| {
+                (
+                    p.transaction_version,
+                    p.owner_address.clone(),
+                    p.amount.clone(),
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(awarded, vec![
+            (20, standardize_address("0xa"), BigDecimal::from(10)),
+            (30, standardize_address("0xa"), BigDecimal::from(20)),
+            (40, standardize_address("0xb"), BigDecimal::from(20)),
+        ]);
+        assert!(points.iter().all(|p| p.event_index < -1));
+        assert_eq!(holdings.len(), 2);
+        assert_eq!(holdings[0].amount, BigDecimal::zero());
+        assert_eq!(holdings[0].last_transaction_version, 30);
+        assert_eq!(holdings[1].accrued_until, timestamp(6, 12));
+    }
+}
@@ -5,13 +5,14 @@
 #![allow(clippy::extra_unused_lifetimes)]
 #![allow(clippy::unused_unit)]
 
+use super::nft_points_rules::{NftPointsRulesConfig, NftPointsTrigger};
 use crate::{
     schema::nft_points,
     utils::{
-        counters::PROCESSOR_UNKNOWN_TYPE_COUNT,
+        counters::{NFT_POINTS_RULE_ERROR_COUNT, PROCESSOR_UNKNOWN_TYPE_COUNT},
         util::{
             get_clean_payload, get_entry_function_from_user_request, parse_timestamp,
-            standardize_address,
+            standardize_address, standardize_entry_function_id,
         },
     },
 };
@@ -21,8 +22,11 @@ use diesel::prelude::*;
 use field_count::FieldCount;
 use serde::{Deserialize, Serialize};
 
+// Event index of points awarded by an entry function rule
+const ENTRY_FUNCTION_EVENT_INDEX: i64 = -1;
+
 #[derive(Clone, Debug, Deserialize, FieldCount, Identifiable, Insertable, Serialize)]
-#[diesel(primary_key(transaction_version))]
+#[diesel(primary_key(transaction_version, event_index, rule_name, rules_version))]
 #[diesel(table_name = nft_points)]
 pub struct NftPoints {
     pub transaction_version: i64,
@@ -31,13 +35,14 @@ pub struct NftPoints {
     pub point_type: String,
     pub amount: BigDecimal,
     pub transaction_timestamp: chrono::NaiveDateTime,
+    pub event_index: i64,
+    pub rule_name: String,
+    pub rules_version: String,
 }
 
 impl NftPoints {
-    pub fn from_transaction(
-        transaction: &Transaction,
-        nft_points_contract: Option<String>,
-    ) -> Option<Self> {
+    /// Points awarded by every rule that matches the transaction. `rules` must be standardized.
+    pub fn from_transaction(transaction: &Transaction, rules: &NftPointsRulesConfig) -> Vec<Self> {
         let txn_data = match transaction.txn_data.as_ref() {
             Some(data) => data,
             None => {
@@ -48,7 +53,7 @@ impl NftPoints {
                     transaction_version = transaction.version,
                     "Transaction data doesn't exist",
                 );
-                return None;
+                return vec![];
             },
         };
         let version = transaction.version as i64;
@@ -60,80 +65,95 @@ impl NftPoints {
             .info
             .as_ref()
             .expect("Transaction info doesn't exist!");
-        if let Some(contract) = nft_points_contract {
-            if let TxnData::User(user_txn) = txn_data {
-                let user_request = user_txn
-                    .request
-                    .as_ref()
-                    .expect("Sends is not present in user txn");
-
-                let payload = match user_request.payload.as_ref() {
-                    Some(payload) => payload,
-                    None => {
-                        tracing::warn!(transaction_version = version, "Payload is empty.");
-                        return None;
-                    },
-                };
-                let entry_function_id_str =
-                    get_entry_function_from_user_request(user_request).unwrap_or_default();
+        let user_txn = match txn_data {
+            TxnData::User(user_txn) => user_txn,
+            _ => return vec![],
+        };
+        // If failed transaction, end
+        if !transaction_info.success {
+            return vec![];
+        }
+        let transaction_timestamp = parse_timestamp(timestamp, version);
+        let user_request = user_txn
+            .request
+            .as_ref()
+            .expect("Sends is not present in user txn");
+        let entry_function_id_str = get_entry_function_from_user_request(user_request)
+            .map(|f| standardize_entry_function_id(&f))
+            .unwrap_or_default();
+        let args = user_request
+            .payload
+            .as_ref()
+            .and_then(|payload| get_clean_payload(payload, version))
+            .and_then(|payload| payload.get("arguments").cloned())
+            .unwrap_or(serde_json::Value::Array(vec![]));
+        let sender = standardize_address(&user_request.sender);
 
-                // If failed transaction, end
-                if !transaction_info.success {
-                    return None;
-                }
-                if entry_function_id_str == contract {
-                    let payload_cleaned = get_clean_payload(payload, version).unwrap();
-                    let args = payload_cleaned["arguments"]
-                        .as_array()
-                        .unwrap_or_else(|| {
-                            tracing::error!(
-                                transaction_version = version,
-                                payload = ?payload_cleaned,
-                                "Failed to get arguments from nft_points transaction"
-                            );
-                            panic!()
-                        })
-                        .iter()
-                        .map(|x| {
-                            unescape::unescape(x.as_str().unwrap_or_else(|| {
-                                tracing::error!(
-                                    transaction_version = version,
-                                    payload = ?payload_cleaned,
-                                    "Failed to parse arguments from nft_points transaction"
-                                );
-                                panic!()
-                            }))
-                            .unwrap_or_else(|| {
-                                tracing::error!(
-                                    transaction_version = version,
-                                    payload = ?payload_cleaned,
-                                    "Failed to escape arguments from nft_points transaction"
-                                );
-                                panic!()
-                            })
-                        })
-                        .collect::<Vec<String>>();
-                    let owner_address = standardize_address(&args[0]);
-                    let amount = args[2].parse().unwrap_or_else(|_| {
+        let mut points = vec![];
+        for rule in &rules.rules {
+            if !rule.is_active_at(transaction_timestamp) {
+                continue;
+            }
+            // (event index, rule context) of every match
+            let matches = match &rule.trigger {
+                NftPointsTrigger::EntryFunction(function) => {
+                    if *function != entry_function_id_str {
+                        continue;
+                    }
+                    vec![(
+                        ENTRY_FUNCTION_EVENT_INDEX,
+                        serde_json::json!({ "args": args, "sender": sender }),
+                    )]
+                },
+                NftPointsTrigger::Event(event_type) => user_txn
+                    .events
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, event)| {
+                        standardize_entry_function_id(&event.type_str) == *event_type
+                    })
+                    .map(|(index, event)| {
+                        let data = serde_json::from_str::<serde_json::Value>(&event.data)
+                            .unwrap_or(serde_json::Value::Null);
+                        (
+                            index as i64,
+                            serde_json::json!({ "data": data, "args": args, "sender": sender }),
+                        )
+                    })
+                    .collect(),
+                // Awarded from nft_holdings as periods complete
+                NftPointsTrigger::Holding(_) => continue,
+            };
+            for (event_index, context) in matches {
+                match rule.evaluate(&context) {
+                    Ok(Some(award)) => points.push(Self {
+                        transaction_version: version,
+                        owner_address: award.owner_address,
+                        token_name: award.token_name,
+                        point_type: award.point_type,
+                        amount: award.amount,
+                        transaction_timestamp,
+                        event_index,
+                        rule_name: rule.name.clone(),
+                        rules_version: rules.version.clone(),
+                    }),
+                    Ok(None) => {},
+                    Err(e) => {
+                        NFT_POINTS_RULE_ERROR_COUNT
+                            .with_label_values(&[rule.name.as_str(), rules.version.as_str()])
+                            .inc();
                         tracing::error!(
                             transaction_version = version,
-                            argument = &args[2],
-                            "Failed to parse amount from nft_points transaction"
+                            event_index = event_index,
+                            rule = rule.name.as_str(),
+                            rules_version = rules.version.as_str(),
+                            error = ?e,
+                            "Failed to evaluate nft_points rule"
                         );
-                        panic!()
-                    });
-                    let transaction_timestamp = parse_timestamp(timestamp, version);
-                    return Some(Self {
-                        transaction_version: version,
-                        owner_address,
-                        token_name: args[1].clone(),
-                        point_type: args[3].clone(),
-                        amount,
-                        transaction_timestamp,
-                    });
+                    },
                 }
             }
         }
-        None
+        points
     }
 }
@@ -0,0 +1,502 @@
+// Copyright © Aptos Foundation
+// SPDX-License-Identifier: Apache-2.0
+
+//! Rules deciding which transactions award NFT points. A rule fires on an event type or an entry
+//! function, reads the owner, token name and point type from the transaction and computes the
+//! amount with an arithmetic expression. Values are read with paths such as `$.data.price` or
+//! `$.args.0`, evaluated against:
+//!
+//! - `data`: the event data (event rules only)
+//! - `args`: the entry function arguments
+//! - `sender`: the transaction sender
+//!
+//! A `holding` rule instead awards points for every completed period a token of a collection is
+//! held, see `nft_holdings`. Its paths read `owner`, `token_name`, `creator_address`,
+//! `collection_name`, `token_amount` (the balance held) and `periods` (the number of periods
+//! awarded at once).
+
+use crate::utils::{
+    json_path::{format_path, lookup, parse_path},
+    util::{standardize_address, standardize_entry_function_id},
+};
+use anyhow::{bail, Context, Result};
+use bigdecimal::{BigDecimal, Zero};
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::{fmt, num::NonZeroU64, str::FromStr};
+
+/// Rules version written for the rule built from the legacy `nft_points_contract` setting
+pub const LEGACY_RULES_VERSION: &str = "legacy";
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct NftPointsRulesConfig {
+    // Written to every row so points of different rule sets can be told apart after a replay
+    pub version: String,
+    pub rules: Vec<NftPointsRule>,
+}
+
+impl NftPointsRulesConfig {
+    /// The rule `nft_points_contract` used to hardcode: the entry function's arguments are the
+    /// owner, token name, amount and point type
+    pub fn from_legacy_contract(entry_function: &str) -> Self {
+        Self {
+            version: LEGACY_RULES_VERSION.to_string(),
+            rules: vec![NftPointsRule {
+                name: "nft_points_contract".to_string(),
+                trigger: NftPointsTrigger::EntryFunction(entry_function.to_string()),
+                owner: ValueSource::Path(vec!["args".to_string(), "0".to_string()]),
+                token_name: ValueSource::Path(vec!["args".to_string(), "1".to_string()]),
+                point_type: ValueSource::Path(vec!["args".to_string(), "3".to_string()]),
+                amount: AmountExpression::Path(vec!["args".to_string(), "2".to_string()]),
+                start_time: None,
+                end_time: None,
+            }],
+        }
+    }
+
+    /// Rules with a `holding` trigger
+    pub fn holding_rules(&self) -> Vec<(&NftPointsRule, &HoldingTrigger)> {
+        self.rules
+            .iter()
+            .filter_map(|rule| match &rule.trigger {
+                NftPointsTrigger::Holding(trigger) => Some((rule, trigger)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Standardizes the addresses in triggers so they compare equal to chain data
+    pub fn standardized(mut self) -> Self {
+        for rule in self.rules.iter_mut() {
+            rule.trigger = match &rule.trigger {
+                NftPointsTrigger::Event(event_type) => {
+                    NftPointsTrigger::Event(standardize_entry_function_id(event_type))
+                },
+                NftPointsTrigger::EntryFunction(function) => {
+                    NftPointsTrigger::EntryFunction(standardize_entry_function_id(function))
+                },
+                NftPointsTrigger::Holding(holding) => NftPointsTrigger::Holding(HoldingTrigger {
+                    creator_address: standardize_address(&holding.creator_address),
+                    ..holding.clone()
+                }),
+            };
+        }
+        self
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NftPointsTrigger {
+    // Full event type, e.g. 0x1234::events::ListingFilledEvent
+    Event(String),
+    // Entry function id, e.g. 0x1234::points::claim
+    EntryFunction(String),
+    // Every completed period a token of the collection is held
+    Holding(HoldingTrigger),
+}
+
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct HoldingTrigger {
+    pub creator_address: String,
+    pub collection_name: String,
+    pub period_secs: NonZeroU64,
+}
+
+impl HoldingTrigger {
+    pub fn matches(&self, creator_address: &str, collection_name: &str) -> bool {
+        self.creator_address == creator_address && self.collection_name == collection_name
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct NftPointsRule {
+    pub name: String,
+    pub trigger: NftPointsTrigger,
+    pub owner: ValueSource,
+    #[serde(default = "ValueSource::empty")]
+    pub token_name: ValueSource,
+    pub point_type: ValueSource,
+    pub amount: AmountExpression,
+    // Campaign window, compared with the transaction timestamp
+    #[serde(default)]
+    pub start_time: Option<NaiveDateTime>,
+    #[serde(default)]
+    pub end_time: Option<NaiveDateTime>,
+}
+
+/// Points awarded by a rule, before the transaction fields are added
+#[derive(Clone, Debug, PartialEq)]
+pub struct NftPointsAward {
+    pub owner_address: String,
+    pub token_name: String,
+    pub point_type: String,
+    pub amount: BigDecimal,
+}
+
+impl NftPointsRule {
+    pub fn is_active_at(&self, timestamp: NaiveDateTime) -> bool {
+        self.start_time.map_or(true, |start| timestamp >= start)
+            && self.end_time.map_or(true, |end| timestamp < end)
+    }
+
+    /// Returns None when the rule computes no points
+    pub fn evaluate(&self, context: &Value) -> Result<Option<NftPointsAward>> {
+        let amount = self.amount.evaluate(context)?;
+        if amount <= BigDecimal::zero() {
+            return Ok(None);
+        }
+        Ok(Some(NftPointsAward {
+            owner_address: standardize_address(&self.owner.evaluate(context)?),
+            token_name: self.token_name.evaluate(context)?,
+            point_type: self.point_type.evaluate(context)?,
+            amount,
+        }))
+    }
+}
+
+/// A literal string, or a `$.`-prefixed path into the rule context
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum ValueSource {
+    Literal(String),
+    Path(Vec<String>),
+}
+
+impl ValueSource {
+    pub fn empty() -> Self {
+        Self::Literal(String::new())
+    }
+
+    pub fn evaluate(&self, context: &Value) -> Result<String> {
+        match self {
+            Self::Literal(value) => Ok(value.clone()),
+            Self::Path(path) => match lookup(context, path)? {
+                Value::String(value) => Ok(value.clone()),
+                value => Ok(value.to_string()),
+            },
+        }
+    }
+}
+
+impl TryFrom<String> for ValueSource {
+    type Error = anyhow::Error;
+
+    fn try_from(value: String) -> Result<Self> {
+        if value.starts_with('$') {
+            Ok(Self::Path(parse_path(&value)?))
+        } else {
+            Ok(Self::Literal(value))
+        }
+    }
+}
+
+impl From<ValueSource> for String {
+    fn from(value: ValueSource) -> Self {
+        match value {
+            ValueSource::Literal(value) => value,
+            ValueSource::Path(path) => format_path(&path),
+        }
+    }
+}
+
+/// Arithmetic over numbers and paths with `+ - * /` and parentheses, e.g.
+/// `$.data.price / 100000000 * 10`
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum AmountExpression {
+    Number(BigDecimal),
+    Path(Vec<String>),
+    Negate(Box<AmountExpression>),
+    Binary(Box<AmountExpression>, Operator, Box<AmountExpression>),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Operator {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+}
+
+impl AmountExpression {
+    pub fn evaluate(&self, context: &Value) -> Result<BigDecimal> {
+        match self {
+            Self::Number(value) => Ok(value.clone()),
+            Self::Path(path) => {
+                let value = lookup(context, path)?;
+                let number = match value {
+                    Value::Number(number) => number.to_string(),
+                    Value::String(number) => number.clone(),
+                    _ => bail!("{} is not a number: {}", format_path(path), value),
+                };
+                BigDecimal::from_str(&number)
+                    .with_context(|| format!("{} is not a number: {}", format_path(path), number))
+            },
+            Self::Negate(inner) => Ok(-inner.evaluate(context)?),
+            Self::Binary(left, operator, right) => {
+                let left = left.evaluate(context)?;
+                let right = right.evaluate(context)?;
+                match operator {
+                    Operator::Add => Ok(left + right),
+                    Operator::Subtract => Ok(left - right),
+                    Operator::Multiply => Ok(left * right),
+                    Operator::Divide => {
+                        if right.is_zero() {
+                            bail!("Division by zero");
+                        }
+                        Ok(left / right)
+                    },
+                }
+            },
+        }
+    }
+}
+
+impl TryFrom<String> for AmountExpression {
+    type Error = anyhow::Error;
+
+    fn try_from(value: String) -> Result<Self> {
+        let tokens = tokenize(&value)?;
+        let mut parser = ExpressionParser {
+            tokens: &tokens,
+            position: 0,
+        };
+        let expression = parser.parse_sum()?;
+        if parser.position != tokens.len() {
+            bail!(
+                "Unexpected {:?} in amount expression {:?}",
+                tokens[parser.position],
+                value
+            );
+        }
+        Ok(expression)
+    }
+}
+
+impl From<AmountExpression> for String {
+    fn from(value: AmountExpression) -> Self {
+        value.to_string()
+    }
+}
+
+impl fmt::Display for AmountExpression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Number(value) => write!(f, "{}", value),
+            Self::Path(path) => write!(f, "{}", format_path(path)),
+            Self::Negate(inner) => write!(f, "-({})", inner),
+            Self::Binary(left, operator, right) => {
+                let operator = match operator {
+                    Operator::Add => "+",
+                    Operator::Subtract => "-",
+                    Operator::Multiply => "*",
+                    Operator::Divide => "/",
+                };
+                write!(f, "({} {} {})", left, operator, right)
+            },
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Number(BigDecimal),
+    Path(Vec<String>),
+    Operator(Operator),
+    OpenParen,
+    CloseParen,
+}
+
+fn tokenize(expression: &str) -> Result<Vec<Token>> {
+    let chars = expression.chars().collect::<Vec<_>>();
+    let mut tokens = vec![];
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let start = i;
+        match c {
+            ' ' | '\t' | '\n' => i += 1,
+            '+' | '-' | '*' | '/' => {
+                tokens.push(Token::Operator(match c {
+                    '+' => Operator::Add,
+                    '-' => Operator::Subtract,
+                    '*' => Operator::Multiply,
+                    _ => Operator::Divide,
+                }));
+                i += 1;
+            },
+            '(' => {
+                tokens.push(Token::OpenParen);
+                i += 1;
+            },
+            ')' => {
+                tokens.push(Token::CloseParen);
+                i += 1;
+            },
+            '0'..='9' | '.' => {
+                while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '.') {
+                    i += 1;
+                }
+                let number = chars[start..i].iter().collect::<String>();
+                tokens.push(Token::Number(
+                    BigDecimal::from_str(&number)
+                        .with_context(|| format!("Invalid number {:?}", number))?,
+                ));
+            },
+            '$' => {
+                while i < chars.len()
+                    && (chars[i].is_ascii_alphanumeric() || matches!(chars[i], '$' | '.' | '_'))
+                {
+                    i += 1;
+                }
+                let path = chars[start..i].iter().collect::<String>();
+                tokens.push(Token::Path(parse_path(&path)?));
+            },
+            _ => bail!("Unexpected {:?} in amount expression {:?}", c, expression),
+        }
+    }
+    Ok(tokens)
+}
+
+struct ExpressionParser<'a> {
+    tokens: &'a [Token],
+    position: usize,
+}
+
+impl<'a> ExpressionParser<'a> {
+    fn next_operator(&self, operators: &[Operator]) -> Option<Operator> {
+        match self.tokens.get(self.position) {
+            Some(Token::Operator(operator)) if operators.contains(operator) => Some(*operator),
+            _ => None,
+        }
+    }
+
+    fn parse_sum(&mut self) -> Result<AmountExpression> {
+        let mut left = self.parse_product()?;
+        while let Some(operator) = self.next_operator(&[Operator::Add, Operator::Subtract]) {
+            self.position += 1;
+            let right = self.parse_product()?;
+            left = AmountExpression::Binary(Box::new(left), operator, Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn parse_product(&mut self) -> Result<AmountExpression> {
+        let mut left = self.parse_factor()?;
+        while let Some(operator) = self.next_operator(&[Operator::Multiply, Operator::Divide]) {
+            self.position += 1;
+            let right = self.parse_factor()?;
+            left = AmountExpression::Binary(Box::new(left), operator, Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn parse_factor(&mut self) -> Result<AmountExpression> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        match token {
+            Some(Token::Number(value)) => Ok(AmountExpression::Number(value)),
+            Some(Token::Path(path)) => Ok(AmountExpression::Path(path)),
+            Some(Token::Operator(Operator::Subtract)) => {
+                Ok(AmountExpression::Negate(Box::new(self.parse_factor()?)))
+            },
+            Some(Token::OpenParen) => {
+                let inner = self.parse_sum()?;
+                match self.tokens.get(self.position) {
+                    Some(Token::CloseParen) => {
+                        self.position += 1;
+                        Ok(inner)
+                    },
+                    _ => bail!("Missing closing parenthesis in amount expression"),
+                }
+            },
+            Some(token) => bail!("Unexpected {:?} in amount expression", token),
+            None => bail!("Amount expression ended unexpectedly"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_amount_expression() {
+        let context = serde_json::json!({
+            "data": {"price": "250000000", "token_amount": 2},
+            "args": ["0x1", "name", "5"],
+        });
+        let evaluate = |expression: &str| {
+            AmountExpression::try_from(expression.to_string())
+                .unwrap()
+                .evaluate(&context)
+                .unwrap()
+        };
+        assert_eq!(
+            evaluate("$.data.price / 100000000"),
+            BigDecimal::from_str("2.5").unwrap()
+        );
+        assert_eq!(evaluate("1 + 2 * 3"), BigDecimal::from(7));
+        assert_eq!(evaluate("(1 + 2) * 3"), BigDecimal::from(9));
+        assert_eq!(
+            evaluate("-$.args.2 + $.data.token_amount * 10"),
+            BigDecimal::from(15)
+        );
+        assert!(AmountExpression::try_from("1 +".to_string()).is_err());
+        assert!(AmountExpression::try_from("price".to_string()).is_err());
+        assert!(AmountExpression::try_from("$.data.missing".to_string())
+            .unwrap()
+            .evaluate(&context)
+            .is_err());
+    }
+
+    #[test]
+    fn test_rule_config() {
+        let rule: NftPointsRule = serde_json::from_value(serde_json::json!({
+            "name": "trading",
+            "trigger": {"event": "0x1234::events::ListingFilledEvent"},
+            "owner": "$.data.purchaser",
+            "point_type": "trade",
+            "amount": "$.data.price / 100000000",
+            "start_time": "2026-01-01T00:00:00",
+        }))
+        .unwrap();
+        let context = serde_json::json!({"data": {"purchaser": "0xa", "price": "300000000"}});
+        let award = rule.evaluate(&context).unwrap().unwrap();
+        assert_eq!(award.owner_address, standardize_address("0xa"));
+        assert_eq!(award.point_type, "trade");
+        assert_eq!(award.token_name, "");
+        assert_eq!(award.amount, BigDecimal::from(3));
+
+        let before = NaiveDateTime::parse_from_str("2025-12-31 23:59:59", "%Y-%m-%d %H:%M:%S");
+        assert!(!rule.is_active_at(before.unwrap()));
+
+        let config: NftPointsRulesConfig = serde_json::from_value(serde_json::json!({
+            "version": "1",
+            "rules": [{
+                "name": "holding",
+                "trigger": {"holding": {
+                    "creator_address": "0x1",
+                    "collection_name": "Mercato",
+                    "period_secs": 86400,
+                }},
+                "owner": "$.owner",
+                "point_type": "holding",
+                "amount": "$.periods * 5",
+            }],
+        }))
+        .unwrap();
+        match &config.standardized().rules[0].trigger {
+            NftPointsTrigger::Holding(holding) => {
+                assert!(holding.matches(&standardize_address("0x1"), "Mercato"));
+                assert_eq!(holding.period_secs.get(), 86400);
+            },
+            trigger => panic!("Unexpected trigger {:?}", trigger),
+        }
+    }
+}
@@ -0,0 +1,10 @@
+-- Batches processed past processor_status.last_success_version, i.e. ahead of a gap, so a
+-- restarted processor only has to backfill the gaps
+CREATE TABLE IF NOT EXISTS processor_ranges (
+  processor VARCHAR(50) NOT NULL,
+  start_version BIGINT NOT NULL,
+  end_version BIGINT NOT NULL,
+  last_transaction_timestamp TIMESTAMP,
+  last_updated TIMESTAMP NOT NULL DEFAULT NOW(),
+  PRIMARY KEY (processor, start_version)
+);
@@ -5,6 +5,12 @@ diesel::table! {
         #[max_length = 66]
         account_address -> Varchar,
         inserted_at -> Timestamp,
+        first_transaction_version -> Nullable<Int8>,
+        first_transaction_timestamp -> Nullable<Timestamp>,
+        last_transaction_version -> Nullable<Int8>,
+        last_transaction_timestamp -> Nullable<Timestamp>,
+        transaction_count -> Int8,
+        has_signed -> Bool,
     }
 }
 
@@ -212,6 +218,71 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    collection_holders (collection_id, owner_address) {
+        #[max_length = 66]
+        collection_id -> Varchar,
+        #[max_length = 66]
+        owner_address -> Varchar,
+        inserted_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    collection_stats_events (transaction_version, event_index) {
+        transaction_version -> Int8,
+        event_index -> Int8,
+        #[max_length = 66]
+        collection_id -> Nullable<Varchar>,
+        bucket_start -> Timestamp,
+        #[max_length = 20]
+        event_kind -> Varchar,
+        amount -> Numeric,
+        inserted_at -> Timestamp,
+        #[max_length = 66]
+        token_data_id -> Nullable<Varchar>,
+    }
+}
+
+diesel::table! {
+    collection_stats_hourly (collection_id, bucket_start) {
+        #[max_length = 66]
+        collection_id -> Varchar,
+        bucket_start -> Timestamp,
+        mint_count -> Int8,
+        burn_count -> Int8,
+        transfer_count -> Int8,
+        sales_count -> Int8,
+        volume -> Numeric,
+        inserted_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    collection_stats_ownerships (token_data_id, property_version_v1, owner_address, storage_id) {
+        #[max_length = 66]
+        token_data_id -> Varchar,
+        property_version_v1 -> Numeric,
+        #[max_length = 66]
+        owner_address -> Varchar,
+        #[max_length = 66]
+        storage_id -> Varchar,
+        amount -> Numeric,
+        last_transaction_version -> Int8,
+        inserted_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    collection_stats_tokens (token_data_id) {
+        #[max_length = 66]
+        token_data_id -> Varchar,
+        #[max_length = 66]
+        collection_id -> Varchar,
+        inserted_at -> Timestamp,
+    }
+}
+
 diesel::table! {
     collections_v2 (transaction_version, write_set_change_index) {
         transaction_version -> Int8,
@@ -311,6 +382,29 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    current_bids (offer_id) {
+        #[max_length = 66]
+        offer_id -> Varchar,
+        #[max_length = 50]
+        marketplace -> Varchar,
+        #[max_length = 66]
+        contract_address -> Varchar,
+        #[max_length = 66]
+        token_data_id -> Nullable<Varchar>,
+        #[max_length = 66]
+        collection_id -> Nullable<Varchar>,
+        #[max_length = 66]
+        buyer -> Varchar,
+        price -> Numeric,
+        token_amount -> Nullable<Numeric>,
+        is_active -> Bool,
+        last_transaction_version -> Int8,
+        last_transaction_timestamp -> Timestamp,
+        inserted_at -> Timestamp,
+    }
+}
+
 diesel::table! {
     current_coin_balances (owner_address, coin_type_hash) {
         #[max_length = 66]
@@ -350,6 +444,25 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    current_collection_stats (collection_id) {
+        #[max_length = 66]
+        collection_id -> Varchar,
+        current_supply -> Nullable<Numeric>,
+        max_supply -> Nullable<Numeric>,
+        total_minted -> Nullable<Numeric>,
+        unique_holders -> Int8,
+        floor_price -> Nullable<Numeric>,
+        volume_24h -> Numeric,
+        volume_7d -> Numeric,
+        total_volume -> Numeric,
+        sales_count -> Int8,
+        last_transaction_version -> Int8,
+        last_transaction_timestamp -> Timestamp,
+        inserted_at -> Timestamp,
+    }
+}
+
 diesel::table! {
     current_collections_v2 (collection_id) {
         #[max_length = 66]
@@ -448,6 +561,28 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    current_listings (listing_id) {
+        #[max_length = 66]
+        listing_id -> Varchar,
+        #[max_length = 50]
+        marketplace -> Varchar,
+        #[max_length = 66]
+        contract_address -> Varchar,
+        #[max_length = 66]
+        token_data_id -> Varchar,
+        #[max_length = 66]
+        collection_id -> Varchar,
+        #[max_length = 66]
+        seller -> Varchar,
+        price -> Numeric,
+        is_active -> Bool,
+        last_transaction_version -> Int8,
+        last_transaction_timestamp -> Timestamp,
+        inserted_at -> Timestamp,
+    }
+}
+
 diesel::table! {
     current_objects (object_address) {
         #[max_length = 66]
@@ -867,6 +1002,15 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    listing_invalidations (token_data_id) {
+        #[max_length = 66]
+        token_data_id -> Varchar,
+        transaction_version -> Int8,
+        transaction_timestamp -> Timestamp,
+    }
+}
+
 diesel::table! {
     move_modules (transaction_version, write_set_change_index) {
         transaction_version -> Int8,
@@ -905,7 +1049,30 @@ diesel::table! {
 }
 
 diesel::table! {
-    nft_points (transaction_version) {
+    nft_holdings (rule_name, rules_version, token_data_id_hash, property_version, owner_address) {
+        rule_name -> Text,
+        rules_version -> Text,
+        #[max_length = 64]
+        token_data_id_hash -> Varchar,
+        property_version -> Numeric,
+        #[max_length = 66]
+        owner_address -> Varchar,
+        #[max_length = 66]
+        creator_address -> Varchar,
+        #[max_length = 128]
+        collection_name -> Varchar,
+        #[max_length = 128]
+        token_name -> Varchar,
+        amount -> Numeric,
+        held_since -> Timestamp,
+        accrued_until -> Timestamp,
+        last_transaction_version -> Int8,
+        inserted_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    nft_points (transaction_version, event_index, rule_name, rules_version) {
         transaction_version -> Int8,
         #[max_length = 66]
         owner_address -> Varchar,
@@ -914,6 +1081,9 @@ diesel::table! {
         amount -> Numeric,
         transaction_timestamp -> Timestamp,
         inserted_at -> Timestamp,
+        event_index -> Int8,
+        rule_name -> Text,
+        rules_version -> Text,
     }
 }
 
@@ -935,6 +1105,17 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    processor_ranges (processor, start_version) {
+        #[max_length = 50]
+        processor -> Varchar,
+        start_version -> Int8,
+        end_version -> Int8,
+        last_transaction_timestamp -> Nullable<Timestamp>,
+        last_updated -> Timestamp,
+    }
+}
+
 diesel::table! {
     processor_status (processor) {
         #[max_length = 50]
@@ -1263,6 +1444,48 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    launchpad_mint_stages (collection_id, stage) {
+        #[max_length = 66]
+        collection_id -> Varchar,
+        #[max_length = 100]
+        stage -> Varchar,
+        #[max_length = 66]
+        contract_address -> Varchar,
+        is_allowlist -> Bool,
+        price -> Numeric,
+        per_wallet_limit -> Nullable<Numeric>,
+        start_time -> Timestamp,
+        end_time -> Timestamp,
+        last_transaction_version -> Int8,
+        last_transaction_timestamp -> Timestamp,
+        inserted_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    launchpad_mints (transaction_version, event_index) {
+        transaction_version -> Int8,
+        event_index -> Int8,
+        #[max_length = 66]
+        contract_address -> Varchar,
+        #[max_length = 66]
+        collection_id -> Varchar,
+        #[max_length = 66]
+        token_data_id -> Nullable<Varchar>,
+        #[max_length = 100]
+        stage -> Nullable<Varchar>,
+        #[max_length = 20]
+        mint_type -> Varchar,
+        #[max_length = 66]
+        minter -> Varchar,
+        price -> Numeric,
+        amount -> Numeric,
+        transaction_timestamp -> Timestamp,
+        inserted_at -> Timestamp,
+    }
+}
+
 diesel::table! {
     launchpad_transactions (id) {
         #[max_length = 100]
@@ -1274,6 +1497,119 @@ diesel::table! {
         error_count -> Int4,
         #[max_length = 500]
         error -> Nullable<Varchar>,
+        #[max_length = 20]
+        status -> Varchar,
+        next_attempt_at -> Timestamp,
+        processed_at -> Nullable<Timestamp>,
+    }
+}
+
+diesel::table! {
+    marketplace_activities (transaction_version, event_index) {
+        transaction_version -> Int8,
+        event_index -> Int8,
+        #[max_length = 50]
+        marketplace -> Varchar,
+        #[max_length = 66]
+        contract_address -> Varchar,
+        #[max_length = 50]
+        event_type -> Varchar,
+        #[max_length = 66]
+        listing_id -> Nullable<Varchar>,
+        #[max_length = 66]
+        offer_id -> Nullable<Varchar>,
+        #[max_length = 66]
+        token_data_id -> Nullable<Varchar>,
+        #[max_length = 66]
+        collection_id -> Nullable<Varchar>,
+        price -> Numeric,
+        token_amount -> Nullable<Numeric>,
+        #[max_length = 66]
+        seller -> Nullable<Varchar>,
+        #[max_length = 66]
+        buyer -> Nullable<Varchar>,
+        transaction_timestamp -> Timestamp,
+        inserted_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    mercato_indexer_dead_letters (id) {
+        id -> Int8,
+        #[max_length = 50]
+        processor -> Varchar,
+        start_version -> Int8,
+        end_version -> Int8,
+        nft_meta -> Jsonb,
+        actions -> Jsonb,
+        error -> Text,
+        inserted_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    mercato_indexer_pending_actions (tx_id, tx_index) {
+        tx_id -> Text,
+        tx_index -> Int8,
+        token_id -> Text,
+        #[sql_name = "action"]
+        action_type -> Text,
+        seller -> Nullable<Text>,
+        buyer -> Nullable<Text>,
+        new_owner -> Nullable<Text>,
+        block_height -> Int8,
+        block_time -> Timestamp,
+        nonce -> Int8,
+    }
+}
+
+diesel::table! {
+    nft_meta (id) {
+        id -> Uuid,
+        name -> Text,
+        token_id -> Text,
+        collection_id -> Uuid,
+        chain_id -> Uuid,
+        smart_contract_id -> Uuid,
+        properties -> Jsonb,
+        mint_tx -> Text,
+        owner -> Text,
+        owner_block_height -> Int8,
+        owner_tx_id -> Text,
+    }
+}
+
+diesel::table! {
+    action (tx_id, tx_index) {
+        tx_id -> Text,
+        tx_index -> Int8,
+        #[sql_name = "action"]
+        action_type -> Text,
+        seller -> Nullable<Text>,
+        buyer -> Nullable<Text>,
+        block_height -> Int8,
+        block_time -> Timestamp,
+        nonce -> Int8,
+        collection_id -> Uuid,
+        nft_meta_id -> Uuid,
+        smart_contract_id -> Uuid,
+    }
+}
+
+diesel::table! {
+    recent_action (tx_id, tx_index) {
+        tx_id -> Text,
+        tx_index -> Int8,
+        #[sql_name = "action"]
+        action_type -> Text,
+        seller -> Nullable<Text>,
+        buyer -> Nullable<Text>,
+        block_height -> Int8,
+        block_time -> Timestamp,
+        nonce -> Int8,
+        collection_id -> Uuid,
+        nft_meta_id -> Uuid,
+        smart_contract_id -> Uuid,
     }
 }
 
@@ -1314,18 +1650,26 @@ diesel::allow_tables_to_appear_in_same_query!(
     coin_infos,
     coin_supply,
     collection_datas,
+    collection_holders,
+    collection_stats_events,
+    collection_stats_hourly,
+    collection_stats_ownerships,
+    collection_stats_tokens,
     collections_v2,
     current_ans_lookup,
     current_ans_lookup_v2,
     current_ans_primary_name,
     current_ans_primary_name_v2,
+    current_bids,
     current_coin_balances,
     current_collection_datas,
+    current_collection_stats,
     current_collections_v2,
     current_delegated_staking_pool_balances,
     current_delegated_voter,
     current_delegator_balances,
     current_fungible_asset_balances,
+    current_listings,
     current_objects,
     current_staking_pool_voter,
     current_table_items,
@@ -1348,10 +1692,13 @@ diesel::allow_tables_to_appear_in_same_query!(
     fungible_asset_metadata,
     indexer_status,
     ledger_infos,
+    listing_invalidations,
     move_modules,
     move_resources,
+    nft_holdings,
     nft_points,
     objects,
+    processor_ranges,
     processor_status,
     proposal_votes,
     signatures,
@@ -1368,7 +1715,15 @@ diesel::allow_tables_to_appear_in_same_query!(
     transaction_size_info,
     transactions,
     user_transactions,
+    launchpad_mint_stages,
+    launchpad_mints,
     launchpad_transactions,
+    marketplace_activities,
+    mercato_indexer_dead_letters,
+    mercato_indexer_pending_actions,
+    nft_meta,
+    action,
+    recent_action,
     write_set_changes,
     write_set_size_info,
 );
@@ -59,6 +59,50 @@ impl DefaultGapDetector {
         }
     }
 
+    /// Starts from `starting_version` with `processed_ranges` already processed, e.g. the ranges
+    /// a previous run persisted ahead of a gap. Ranges must be sorted and at or after
+    /// `starting_version`.
+    pub fn with_processed_ranges(
+        starting_version: u64,
+        processed_ranges: Vec<DefaultProcessingResult>,
+    ) -> Self {
+        let mut gap_detector = Self::new(starting_version);
+        for range in processed_ranges {
+            if range.start_version == gap_detector.next_version_to_process {
+                gap_detector.update_prev_batch(range);
+            } else {
+                gap_detector
+                    .seen_versions
+                    .insert(range.start_version, range);
+            }
+        }
+        gap_detector
+    }
+
+    /// Batches processed past the gap at `next_version_to_process`, merged into contiguous
+    /// ranges and sorted by version
+    pub fn pending_ranges(&self) -> Vec<DefaultProcessingResult> {
+        let mut batches = self.seen_versions.values().collect::<Vec<_>>();
+        batches.sort_by_key(|batch| batch.start_version);
+        let mut ranges: Vec<DefaultProcessingResult> = vec![];
+        for batch in batches {
+            match ranges.last_mut() {
+                Some(range) if range.end_version + 1 == batch.start_version => {
+                    range.end_version = batch.end_version;
+                    range.last_transaction_timestamp = batch.last_transaction_timestamp.clone();
+                },
+                _ => ranges.push(DefaultProcessingResult {
+                    start_version: batch.start_version,
+                    end_version: batch.end_version,
+                    last_transaction_timestamp: batch.last_transaction_timestamp.clone(),
+                    processing_duration_in_secs: 0.0,
+                    db_insertion_duration_in_secs: 0.0,
+                }),
+            }
+        }
+        ranges
+    }
+
     fn update_prev_batch(&mut self, result: DefaultProcessingResult) {
         let mut new_prev_batch = result;
         while let Some(next_version) = self.seen_versions.remove(&(new_prev_batch.end_version + 1))
@@ -139,4 +183,47 @@ mod test {
             199 + (DEFAULT_GAP_DETECTION_BATCH_SIZE - 1) * 100
         );
     }
+
+    fn batch(start_version: u64, end_version: u64) -> DefaultProcessingResult {
+        DefaultProcessingResult {
+            start_version,
+            end_version,
+            last_transaction_timestamp: None,
+            processing_duration_in_secs: 0.0,
+            db_insertion_duration_in_secs: 0.0,
+        }
+    }
+
+    #[test]
+    fn pending_ranges_test() {
+        let mut default_gap_detector =
+            DefaultGapDetector::with_processed_ranges(0, vec![batch(200, 299)]);
+        for result in [
+            batch(300, 399),
+            batch(500, 599),
+            batch(400, 499),
+            batch(800, 899),
+        ] {
+            default_gap_detector
+                .process_versions(ProcessingResult::DefaultProcessingResult(result))
+                .unwrap();
+        }
+        assert_eq!(default_gap_detector.pending_ranges(), vec![
+            batch(200, 599),
+            batch(800, 899)
+        ]);
+
+        // Filling the first gap merges the persisted range into the checkpoint
+        default_gap_detector
+            .process_versions(ProcessingResult::DefaultProcessingResult(batch(0, 199)))
+            .unwrap();
+        assert_eq!(default_gap_detector.next_version_to_process, 600);
+        assert_eq!(default_gap_detector.pending_ranges(), vec![batch(800, 899)]);
+
+        // A persisted range starting at the starting version advances the checkpoint right away
+        let default_gap_detector =
+            DefaultGapDetector::with_processed_ranges(100, vec![batch(100, 199), batch(200, 249)]);
+        assert_eq!(default_gap_detector.next_version_to_process, 250);
+        assert!(default_gap_detector.pending_ranges().is_empty());
+    }
 }
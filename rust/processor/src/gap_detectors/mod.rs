@@ -61,7 +61,7 @@ pub async fn create_gap_detector_status_tracker_loop(
     gap_detector_receiver: AsyncReceiver<ProcessingResult>,
     processor: Processor,
     gap_detection_batch_size: u64,
-) {
+) -> Option<u64> {
     let processor_name = processor.name();
     tracing::info!(
         processor_name = processor_name,
@@ -70,6 +70,13 @@ pub async fn create_gap_detector_status_tracker_loop(
     );
 
     let mut last_update_time = std::time::Instant::now();
+    // Latest version not yet written to processor_status because of the update interval
+    let mut pending_checkpoint = None;
+    // End of the contiguous range processed so far, returned once the channel closes
+    let mut last_processed_version = None;
+    // Ranges ahead of the checkpoint as last written to processor_ranges. None until the first
+    // write, which also clears rows left over from a previous run.
+    let mut persisted_ranges = None;
     loop {
         match gap_detector_receiver.recv().await {
             Ok(ProcessingResult::DefaultProcessingResult(result)) => {
@@ -92,20 +99,35 @@ pub async fn create_gap_detector_status_tracker_loop(
                                     // We don't panic as everything downstream will panic if it doesn't work/receive
                                 }
                                 if let Some(res_last_success_batch) = res.last_success_batch {
-                                    if last_update_time.elapsed().as_secs()
-                                        >= UPDATE_PROCESSOR_STATUS_SECS
+                                    last_processed_version =
+                                        Some(res_last_success_batch.end_version);
+                                    pending_checkpoint = Some((
+                                        res_last_success_batch.end_version,
+                                        res_last_success_batch.last_transaction_timestamp,
+                                    ));
+                                }
+                                if last_update_time.elapsed().as_secs()
+                                    >= UPDATE_PROCESSOR_STATUS_SECS
+                                {
+                                    if let Some((version, last_transaction_timestamp)) =
+                                        pending_checkpoint.take()
                                     {
                                         processor
                                             .update_last_processed_version(
-                                                res_last_success_batch.end_version,
-                                                res_last_success_batch
-                                                    .last_transaction_timestamp
-                                                    .clone(),
+                                                version,
+                                                last_transaction_timestamp,
                                             )
                                             .await
                                             .unwrap();
-                                        last_update_time = std::time::Instant::now();
                                     }
+                                    update_processed_ranges(
+                                        &processor,
+                                        &gap_detector,
+                                        &mut persisted_ranges,
+                                    )
+                                    .await
+                                    .unwrap();
+                                    last_update_time = std::time::Instant::now();
                                 }
                             },
                             _ => {
@@ -144,6 +166,7 @@ pub async fn create_gap_detector_status_tracker_loop(
                                     );
                                     // We don't panic as everything downstream will panic if it doesn't work/receive
                                 }
+                                last_processed_version = res.next_version_to_process.checked_sub(1);
 
                                 if last_update_time.elapsed().as_secs()
                                     >= UPDATE_PROCESSOR_STATUS_SECS
@@ -161,8 +184,13 @@ pub async fn create_gap_detector_status_tracker_loop(
                                         .await
                                         .unwrap();
                                     last_update_time = std::time::Instant::now();
+                                    pending_checkpoint = None;
                                 } else {
                                     tracing::info!("Not Updating last processed version");
+                                    pending_checkpoint = Some((
+                                        res.next_version_to_process,
+                                        res.last_transaction_timestamp,
+                                    ));
                                 }
                             },
                             _ => {
@@ -188,8 +216,56 @@ pub async fn create_gap_detector_status_tracker_loop(
                     error = ?e,
                     "[Parser] Gap detector channel has been closed",
                 );
-                return;
+                // Every sender is gone, e.g. on shutdown: write the last contiguous version
+                if let Some((version, last_transaction_timestamp)) = pending_checkpoint {
+                    match processor
+                        .update_last_processed_version(version, last_transaction_timestamp)
+                        .await
+                    {
+                        Ok(()) => tracing::info!(
+                            processor_name,
+                            service_type = PROCESSOR_SERVICE_TYPE,
+                            last_processed_version = version,
+                            "[Parser] Wrote the final processor status",
+                        ),
+                        Err(e) => tracing::error!(
+                            processor_name,
+                            service_type = PROCESSOR_SERVICE_TYPE,
+                            error = ?e,
+                            "[Parser] Failed to write the final processor status",
+                        ),
+                    }
+                }
+                if let Err(e) =
+                    update_processed_ranges(&processor, &gap_detector, &mut persisted_ranges).await
+                {
+                    tracing::error!(
+                        processor_name,
+                        service_type = PROCESSOR_SERVICE_TYPE,
+                        error = ?e,
+                        "[Parser] Failed to write the final processed ranges",
+                    );
+                }
+                return last_processed_version;
             },
         };
     }
 }
+
+/// Writes the ranges processed ahead of a gap to processor_ranges if they changed since the last
+/// write. Parquet processors don't track ranges.
+async fn update_processed_ranges(
+    processor: &Processor,
+    gap_detector: &GapDetector,
+    persisted_ranges: &mut Option<Vec<DefaultProcessingResult>>,
+) -> Result<()> {
+    let ranges = match gap_detector {
+        GapDetector::DefaultGapDetector(gap_detector) => gap_detector.pending_ranges(),
+        GapDetector::ParquetFileGapDetector(_) => return Ok(()),
+    };
+    if persisted_ranges.as_ref() != Some(&ranges) {
+        processor.update_processed_ranges(ranges.clone()).await?;
+        *persisted_ranges = Some(ranges);
+    }
+    Ok(())
+}
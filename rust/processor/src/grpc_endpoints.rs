@@ -0,0 +1,287 @@
+// Copyright © Aptos Foundation
+// SPDX-License-Identifier: Apache-2.0
+
+//! Tracks the health of the configured GRPC data services and picks the one to stream from.
+//! An endpoint that fails `unhealthy_after_errors` times in a row is skipped for
+//! `unhealthy_cooldown_secs`; among the others the lowest priority wins, then the lowest latency,
+//! which averages connect times and per-message stall timeouts.
+
+use crate::utils::counters::{
+    GRPC_ACTIVE_ENDPOINT, GRPC_ENDPOINT_ERROR_COUNT, GRPC_ENDPOINT_SWITCH_COUNT,
+};
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+use tonic::transport::ClientTlsConfig;
+use url::Url;
+
+// Weight of the newest sample in the latency moving average
+const LATENCY_SMOOTHING: f64 = 0.1;
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct GrpcEndpointConfig {
+    pub address: Url,
+    // Defaults to the main auth_token
+    pub auth_token: Option<String>,
+    // Lower is preferred. The main indexer_grpc_data_service_address has priority 0.
+    #[serde(default = "GrpcEndpointConfig::default_priority")]
+    pub priority: u32,
+}
+
+impl GrpcEndpointConfig {
+    pub const fn default_priority() -> u32 {
+        1
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+#[serde(default)]
+pub struct GrpcFailoverConfig {
+    // Consecutive errors after which an endpoint is skipped
+    pub unhealthy_after_errors: u64,
+    pub unhealthy_cooldown_secs: u64,
+    // How often to try switching back to a preferred endpoint while on a fallback
+    pub failback_check_interval_secs: u64,
+}
+
+impl Default for GrpcFailoverConfig {
+    fn default() -> Self {
+        Self {
+            unhealthy_after_errors: 3,
+            unhealthy_cooldown_secs: 60,
+            failback_check_interval_secs: 60,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct GrpcEndpoint {
+    pub address: Url,
+    pub auth_token: String,
+    pub priority: u32,
+    pub tls_config: Option<ClientTlsConfig>,
+}
+
+#[derive(Clone, Debug, Default)]
+struct EndpointHealth {
+    consecutive_errors: u64,
+    unhealthy_until: Option<Instant>,
+    latency_ms: Option<f64>,
+}
+
+impl EndpointHealth {
+    fn sample_latency(&mut self, latency: Duration) {
+        let sample = latency.as_secs_f64() * 1000.0;
+        self.latency_ms = Some(match self.latency_ms {
+            Some(average) => average + LATENCY_SMOOTHING * (sample - average),
+            None => sample,
+        });
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct GrpcEndpointSelector {
+    endpoints: Vec<(GrpcEndpoint, EndpointHealth)>,
+    active: usize,
+    config: GrpcFailoverConfig,
+    processor_name: String,
+}
+
+impl GrpcEndpointSelector {
+    pub fn new(
+        address: Url,
+        auth_token: String,
+        additional_endpoints: &[GrpcEndpointConfig],
+        config: GrpcFailoverConfig,
+        tls_config: Option<ClientTlsConfig>,
+        processor_name: &str,
+    ) -> Self {
+        let mut endpoints = vec![GrpcEndpoint {
+            address,
+            auth_token: auth_token.clone(),
+            priority: 0,
+            tls_config: tls_config.clone(),
+        }];
+        endpoints.extend(additional_endpoints.iter().map(|endpoint| {
+            GrpcEndpoint {
+                address: endpoint.address.clone(),
+                auth_token: endpoint
+                    .auth_token
+                    .clone()
+                    .unwrap_or_else(|| auth_token.clone()),
+                priority: endpoint.priority,
+                tls_config: tls_config.clone(),
+            }
+        }));
+        let selector = Self {
+            endpoints: endpoints
+                .into_iter()
+                .map(|endpoint| (endpoint, EndpointHealth::default()))
+                .collect(),
+            active: 0,
+            config,
+            processor_name: processor_name.to_string(),
+        };
+        selector.update_active_metric();
+        selector
+    }
+
+    pub fn endpoint(&self, index: usize) -> &GrpcEndpoint {
+        &self.endpoints[index].0
+    }
+
+    pub fn active_index(&self) -> usize {
+        self.active
+    }
+
+    pub fn active(&self) -> &GrpcEndpoint {
+        self.endpoint(self.active)
+    }
+
+    pub fn failback_check_interval(&self) -> Duration {
+        Duration::from_secs(self.config.failback_check_interval_secs)
+    }
+
+    pub fn num_endpoints(&self) -> usize {
+        self.endpoints.len()
+    }
+
+    fn is_healthy(&self, index: usize, now: Instant) -> bool {
+        self.endpoints[index]
+            .1
+            .unhealthy_until
+            .map_or(true, |until| until <= now)
+    }
+
+    /// Endpoint indexes from most to least preferred
+    pub fn candidates(&self) -> Vec<usize> {
+        let now = Instant::now();
+        let mut candidates = (0..self.endpoints.len()).collect::<Vec<_>>();
+        candidates.sort_by(|&a, &b| {
+            let key = |index: usize| {
+                let (endpoint, health) = &self.endpoints[index];
+                (
+                    !self.is_healthy(index, now),
+                    endpoint.priority,
+                    health.latency_ms.unwrap_or(0.0),
+                )
+            };
+            let (a, b) = (key(a), key(b));
+            a.0.cmp(&b.0).then(a.1.cmp(&b.1)).then(a.2.total_cmp(&b.2))
+        });
+        candidates
+    }
+
+    /// A healthy endpoint with a better priority than the active one, if any
+    pub fn failback_candidate(&self) -> Option<usize> {
+        let now = Instant::now();
+        let active_priority = self.active().priority;
+        self.candidates().into_iter().find(|&index| {
+            self.is_healthy(index, now) && self.endpoint(index).priority < active_priority
+        })
+    }
+
+    /// Records a successful connection. `latency` is the time from connecting until the stream
+    /// response arrived.
+    pub fn record_success(&mut self, index: usize, latency: Duration) {
+        let health = &mut self.endpoints[index].1;
+        health.consecutive_errors = 0;
+        health.unhealthy_until = None;
+        health.sample_latency(latency);
+    }
+
+    /// Records a stream that went `timeout` without a message. Besides counting as an error, the
+    /// timeout is sampled as latency so an endpoint that keeps stalling ranks below its peers
+    /// even after it accepts connections again.
+    pub fn record_stall(&mut self, index: usize, timeout: Duration) {
+        self.endpoints[index].1.sample_latency(timeout);
+        self.record_error(index);
+    }
+
+    pub fn record_error(&mut self, index: usize) {
+        GRPC_ENDPOINT_ERROR_COUNT
+            .with_label_values(&[
+                self.processor_name.as_str(),
+                self.endpoints[index].0.address.as_str(),
+            ])
+            .inc();
+        let health = &mut self.endpoints[index].1;
+        health.consecutive_errors += 1;
+        if health.consecutive_errors >= self.config.unhealthy_after_errors {
+            health.unhealthy_until =
+                Some(Instant::now() + Duration::from_secs(self.config.unhealthy_cooldown_secs));
+        }
+    }
+
+    pub fn switch_to(&mut self, index: usize) {
+        if index != self.active {
+            GRPC_ENDPOINT_SWITCH_COUNT
+                .with_label_values(&[
+                    self.processor_name.as_str(),
+                    self.active().address.as_str(),
+                    self.endpoint(index).address.as_str(),
+                ])
+                .inc();
+            self.active = index;
+        }
+        self.update_active_metric();
+    }
+
+    fn update_active_metric(&self) {
+        for (index, (endpoint, _)) in self.endpoints.iter().enumerate() {
+            GRPC_ACTIVE_ENDPOINT
+                .with_label_values(&[self.processor_name.as_str(), endpoint.address.as_str()])
+                .set((index == self.active) as i64);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_failover_and_failback() {
+        let mut selector = GrpcEndpointSelector::new(
+            Url::parse("https://primary.example.com").unwrap(),
+            "token".to_string(),
+            &[
+                GrpcEndpointConfig {
+                    address: Url::parse("https://backup.example.com").unwrap(),
+                    auth_token: None,
+                    priority: 1,
+                },
+                GrpcEndpointConfig {
+                    address: Url::parse("https://fast-backup.example.com").unwrap(),
+                    auth_token: Some("other".to_string()),
+                    priority: 1,
+                },
+            ],
+            GrpcFailoverConfig {
+                unhealthy_after_errors: 2,
+                unhealthy_cooldown_secs: 0,
+                failback_check_interval_secs: 60,
+            },
+            None,
+            "test",
+        );
+        assert_eq!(selector.endpoint(1).auth_token, "token");
+        selector.record_success(1, Duration::from_millis(500));
+        selector.record_success(2, Duration::from_millis(100));
+        assert_eq!(selector.candidates(), vec![0, 2, 1]);
+
+        // A zero cooldown makes the primary healthy again right away
+        selector.record_error(0);
+        selector.record_error(0);
+        selector.switch_to(2);
+        assert_eq!(selector.failback_candidate(), Some(0));
+        selector.switch_to(0);
+        assert_eq!(selector.failback_candidate(), None);
+
+        // A stall keeps the fast backup behind the slow one even after it reconnects quickly
+        selector.record_stall(2, Duration::from_secs(30));
+        selector.record_success(2, Duration::from_millis(100));
+        assert_eq!(selector.candidates(), vec![0, 1, 2]);
+    }
+}
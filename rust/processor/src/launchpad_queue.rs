@@ -0,0 +1,354 @@
+// Copyright © Aptos Foundation
+// SPDX-License-Identifier: Apache-2.0
+
+//! Consumer side of `launchpad_transactions`. Rows written by the launchpad processor are claimed
+//! with `SELECT ... FOR UPDATE SKIP LOCKED`, so any number of workers can share the queue. Claiming
+//! moves a row to the processing state with a lease and commits before any handler runs, so no
+//! transaction or row lock is held during handler calls. If the worker dies, the row is claimed
+//! again once its lease runs out. Every claim counts as an attempt, so a row that keeps crashing
+//! or hanging its worker still reaches `max_attempts`.
+
+use crate::{
+    schema::launchpad_transactions,
+    utils::database::{count_rows_written, ArcDbPool, DbPoolConnection, MyDbConnection},
+};
+use async_trait::async_trait;
+use chrono::SubsecRound;
+use diesel::prelude::*;
+use diesel_async::{scoped_futures::ScopedFutureExt, AsyncConnection, RunQueryDsl};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tracing::{info, warn};
+
+pub const PENDING_STATUS: &str = "pending";
+pub const PROCESSING_STATUS: &str = "processing";
+pub const DONE_STATUS: &str = "done";
+pub const DEAD_STATUS: &str = "dead";
+// Rows that existed before the queue. They're never claimed, see the README for replaying them
+pub const LEGACY_STATUS: &str = "legacy";
+
+// Length of launchpad_transactions.error
+const MAX_ERROR_LENGTH: usize = 500;
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct LaunchpadQueueConfig {
+    // Rows claimed per transaction
+    #[serde(default = "LaunchpadQueueConfig::default_batch_size")]
+    pub batch_size: i64,
+    // Failed attempts after which a row is moved to the dead state
+    #[serde(default = "LaunchpadQueueConfig::default_max_attempts")]
+    pub max_attempts: i32,
+    // Delay before the first retry, doubled after every further failure
+    #[serde(default = "LaunchpadQueueConfig::default_backoff_base_secs")]
+    pub backoff_base_secs: u64,
+    #[serde(default = "LaunchpadQueueConfig::default_backoff_max_secs")]
+    pub backoff_max_secs: u64,
+    // How long a claimed row is reserved for its worker. Handlers must finish within it, or the
+    // row may be handled again
+    #[serde(default = "LaunchpadQueueConfig::default_lease_secs")]
+    pub lease_secs: u64,
+    // Wait between polls when the queue is empty
+    #[serde(default = "LaunchpadQueueConfig::default_poll_interval_ms")]
+    pub poll_interval_ms: u64,
+}
+
+impl LaunchpadQueueConfig {
+    pub const fn default_batch_size() -> i64 {
+        10
+    }
+
+    pub const fn default_max_attempts() -> i32 {
+        5
+    }
+
+    pub const fn default_backoff_base_secs() -> u64 {
+        10
+    }
+
+    pub const fn default_backoff_max_secs() -> u64 {
+        3600
+    }
+
+    pub const fn default_lease_secs() -> u64 {
+        300
+    }
+
+    pub const fn default_poll_interval_ms() -> u64 {
+        1000
+    }
+
+    /// Delay before the next attempt of a row that has failed `error_count` times
+    pub fn get_backoff(&self, error_count: i32) -> Duration {
+        let exponent = error_count.saturating_sub(1).clamp(0, 31) as u32;
+        let secs = self
+            .backoff_base_secs
+            .saturating_mul(2u64.saturating_pow(exponent))
+            .min(self.backoff_max_secs);
+        Duration::from_secs(secs)
+    }
+}
+
+impl Default for LaunchpadQueueConfig {
+    fn default() -> Self {
+        Self {
+            batch_size: Self::default_batch_size(),
+            max_attempts: Self::default_max_attempts(),
+            backoff_base_secs: Self::default_backoff_base_secs(),
+            backoff_max_secs: Self::default_backoff_max_secs(),
+            lease_secs: Self::default_lease_secs(),
+            poll_interval_ms: Self::default_poll_interval_ms(),
+        }
+    }
+}
+
+/// A claimed row of `launchpad_transactions`
+#[derive(Clone, Debug, Deserialize, Queryable, Serialize)]
+#[diesel(table_name = launchpad_transactions)]
+pub struct ClaimedLaunchpadTransaction {
+    pub id: String,
+    pub timestamp: i64,
+    pub sender: String,
+    pub payload: serde_json::Value,
+    // Failed or abandoned attempts before this one
+    pub error_count: i32,
+}
+
+#[async_trait]
+pub trait LaunchpadTransactionHandler: Send + Sync {
+    /// Returning an error schedules a retry, or moves the row to the dead state once
+    /// `max_attempts` is reached
+    async fn handle(&self, transaction: &ClaimedLaunchpadTransaction) -> anyhow::Result<()>;
+}
+
+pub struct LaunchpadQueue {
+    connection_pool: ArcDbPool,
+    config: LaunchpadQueueConfig,
+}
+
+impl LaunchpadQueue {
+    pub fn new(connection_pool: ArcDbPool, config: LaunchpadQueueConfig) -> Self {
+        Self {
+            connection_pool,
+            config,
+        }
+    }
+
+    /// Processes due rows until the queue is empty, then polls every `poll_interval_ms`
+    pub async fn run<H: LaunchpadTransactionHandler>(&self, handler: &H) -> anyhow::Result<()> {
+        loop {
+            let processed = self.process_batch(handler).await?;
+            if processed == 0 {
+                tokio::time::sleep(Duration::from_millis(self.config.poll_interval_ms)).await;
+            }
+        }
+    }
+
+    /// Claims up to `batch_size` due rows, runs the handler on each and records the outcome.
+    /// Returns the number of rows claimed.
+    pub async fn process_batch<H: LaunchpadTransactionHandler>(
+        &self,
+        handler: &H,
+    ) -> anyhow::Result<usize> {
+        let mut conn: DbPoolConnection = self.connection_pool.get().await?;
+        let (claimed, lease_until) = self.claim(&mut conn).await?;
+        for transaction in &claimed {
+            match handler.handle(transaction).await {
+                Ok(_) => mark_done(&mut conn, transaction, lease_until).await?,
+                Err(e) => {
+                    mark_failed(&mut conn, &self.config, transaction, lease_until, &e).await?
+                },
+            };
+        }
+        Ok(claimed.len())
+    }
+
+    /// Moves up to `batch_size` due rows to the processing state, counting the attempt in
+    /// `error_count`, and commits. Rows whose lease ran out are due again, unless that was their
+    /// last attempt, in which case they're moved to the dead state. Returns the rows and their
+    /// lease expiry, which also identifies this claim when the outcome is recorded.
+    async fn claim(
+        &self,
+        conn: &mut DbPoolConnection<'_>,
+    ) -> QueryResult<(Vec<ClaimedLaunchpadTransaction>, chrono::NaiveDateTime)> {
+        let config = &self.config;
+        let now = chrono::Utc::now().naive_utc();
+        let lease = chrono::Duration::from_std(Duration::from_secs(config.lease_secs))
+            .unwrap_or_else(|_| chrono::Duration::zero());
+        // Truncated to the precision of a TIMESTAMP column, as the value is compared when the
+        // outcome is recorded
+        let lease_until = (now + lease).trunc_subsecs(6);
+        let claimed = conn
+            .transaction::<_, diesel::result::Error, _>(|conn| {
+                async move {
+                    let claimed = launchpad_transactions::table
+                        .select((
+                            launchpad_transactions::id,
+                            launchpad_transactions::timestamp,
+                            launchpad_transactions::sender,
+                            launchpad_transactions::payload,
+                            launchpad_transactions::error_count,
+                        ))
+                        .filter(
+                            launchpad_transactions::status
+                                .eq_any([PENDING_STATUS, PROCESSING_STATUS]),
+                        )
+                        .filter(launchpad_transactions::next_attempt_at.le(now))
+                        .order((
+                            launchpad_transactions::timestamp.asc(),
+                            launchpad_transactions::id.asc(),
+                        ))
+                        .limit(config.batch_size)
+                        .for_update()
+                        .skip_locked()
+                        .load::<ClaimedLaunchpadTransaction>(conn)
+                        .await?;
+                    // Only a row whose lease ran out on its last attempt gets here exhausted
+                    let (exhausted, claimed): (Vec<_>, Vec<_>) = claimed
+                        .into_iter()
+                        .partition(|t| t.error_count >= config.max_attempts);
+                    for transaction in &exhausted {
+                        warn!(
+                            id = transaction.id.as_str(),
+                            error_count = transaction.error_count,
+                            "[Launchpad Queue] Giving up on launchpad transaction after its lease ran out"
+                        );
+                    }
+                    let exhausted_ids = exhausted.iter().map(|t| t.id.clone()).collect::<Vec<_>>();
+                    if !exhausted_ids.is_empty() {
+                        diesel::update(
+                            launchpad_transactions::table
+                                .filter(launchpad_transactions::id.eq_any(exhausted_ids)),
+                        )
+                        .set((
+                            launchpad_transactions::status.eq(DEAD_STATUS),
+                            launchpad_transactions::error
+                                .eq(Some("Lease ran out on the last attempt")),
+                        ))
+                        .execute(conn)
+                        .await?;
+                        count_rows_written("launchpad_transactions", exhausted.len());
+                    }
+                    let ids = claimed.iter().map(|t| t.id.clone()).collect::<Vec<_>>();
+                    diesel::update(
+                        launchpad_transactions::table
+                            .filter(launchpad_transactions::id.eq_any(ids)),
+                    )
+                    .set((
+                        launchpad_transactions::status.eq(PROCESSING_STATUS),
+                        launchpad_transactions::next_attempt_at.eq(lease_until),
+                        // Counted up front in case the handler never returns
+                        launchpad_transactions::error_count
+                            .eq(launchpad_transactions::error_count + 1),
+                    ))
+                    .execute(conn)
+                    .await?;
+                    count_rows_written("launchpad_transactions", claimed.len());
+                    Ok(claimed)
+                }
+                .scope_boxed()
+            })
+            .await?;
+        Ok((claimed, lease_until))
+    }
+}
+
+/// Records a successful attempt. The attempt counted by the claim is taken back, so `error_count`
+/// only counts failed and abandoned attempts.
+async fn mark_done(
+    conn: &mut MyDbConnection,
+    transaction: &ClaimedLaunchpadTransaction,
+    lease_until: chrono::NaiveDateTime,
+) -> QueryResult<usize> {
+    let rows = diesel::update(
+        launchpad_transactions::table
+            .filter(launchpad_transactions::id.eq(&transaction.id))
+            // Only update the row if it is still held by this claim, so a worker whose lease ran
+            // out doesn't overwrite the outcome of the worker that claimed the row after it
+            .filter(launchpad_transactions::status.eq(PROCESSING_STATUS))
+            .filter(launchpad_transactions::next_attempt_at.eq(lease_until)),
+    )
+    .set((
+        launchpad_transactions::status.eq(DONE_STATUS),
+        launchpad_transactions::error_count.eq(transaction.error_count),
+        launchpad_transactions::error.eq(None::<String>),
+        launchpad_transactions::processed_at.eq(chrono::Utc::now().naive_utc()),
+    ))
+    .execute(conn)
+    .await?;
+    count_rows_written("launchpad_transactions", rows);
+    Ok(rows)
+}
+
+async fn mark_failed(
+    conn: &mut MyDbConnection,
+    config: &LaunchpadQueueConfig,
+    transaction: &ClaimedLaunchpadTransaction,
+    lease_until: chrono::NaiveDateTime,
+    error: &anyhow::Error,
+) -> QueryResult<usize> {
+    let error_count = transaction.error_count + 1;
+    let mut error_message = format!("{:#}", error);
+    if error_message.len() > MAX_ERROR_LENGTH {
+        let mut end = MAX_ERROR_LENGTH;
+        while !error_message.is_char_boundary(end) {
+            end -= 1;
+        }
+        error_message.truncate(end);
+    }
+    let status = if error_count >= config.max_attempts {
+        warn!(
+            id = transaction.id.as_str(),
+            error_count = error_count,
+            error = error_message.as_str(),
+            "[Launchpad Queue] Giving up on launchpad transaction"
+        );
+        DEAD_STATUS
+    } else {
+        info!(
+            id = transaction.id.as_str(),
+            error_count = error_count,
+            error = error_message.as_str(),
+            "[Launchpad Queue] Launchpad transaction failed, retrying later"
+        );
+        PENDING_STATUS
+    };
+    let backoff = chrono::Duration::from_std(config.get_backoff(error_count))
+        .unwrap_or_else(|_| chrono::Duration::zero());
+    let rows = diesel::update(
+        launchpad_transactions::table
+            .filter(launchpad_transactions::id.eq(&transaction.id))
+            // Still held by this claim, see mark_done
+            .filter(launchpad_transactions::status.eq(PROCESSING_STATUS))
+            .filter(launchpad_transactions::next_attempt_at.eq(lease_until)),
+    )
+    .set((
+        launchpad_transactions::status.eq(status),
+        launchpad_transactions::error_count.eq(error_count),
+        launchpad_transactions::error.eq(Some(error_message)),
+        launchpad_transactions::next_attempt_at.eq(chrono::Utc::now().naive_utc() + backoff),
+    ))
+    .execute(conn)
+    .await?;
+    count_rows_written("launchpad_transactions", rows);
+    Ok(rows)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_backoff() {
+        let config = LaunchpadQueueConfig {
+            backoff_base_secs: 10,
+            backoff_max_secs: 60,
+            ..LaunchpadQueueConfig::default()
+        };
+        assert_eq!(config.get_backoff(1), Duration::from_secs(10));
+        assert_eq!(config.get_backoff(2), Duration::from_secs(20));
+        assert_eq!(config.get_backoff(3), Duration::from_secs(40));
+        assert_eq!(config.get_backoff(4), Duration::from_secs(60));
+        assert_eq!(config.get_backoff(100), Duration::from_secs(60));
+    }
+}
@@ -22,10 +22,15 @@ pub mod bq_analytics;
 mod config;
 pub mod db;
 pub mod gap_detectors;
+pub mod grpc_endpoints;
 pub mod grpc_stream;
+pub mod launchpad_queue;
+pub mod partition_manager;
 pub mod processors;
+pub mod retention;
 #[path = "db/postgres/schema.rs"]
 pub mod schema;
 pub mod transaction_filter;
+pub mod transaction_sources;
 pub mod utils;
 pub mod worker;
@@ -0,0 +1,676 @@
+// Copyright © Aptos Foundation
+// SPDX-License-Identifier: Apache-2.0
+
+//! Keeps high-volume history tables range partitioned by transaction version. Each run creates
+//! the partitions needed ahead of the latest version and detaches or drops partitions whose
+//! newest row is past the retention period.
+//!
+//! Tables are partitioned by version rather than by `inserted_at` because Postgres requires
+//! unique constraints on a partitioned table to include the partition key. The primary keys of
+//! the supported tables all start with the version, so they and the `ON CONFLICT` inserts that
+//! rely on them stay as they are, while expiry is still decided by age. Other unique constraints,
+//! `transactions.hash` and `user_transactions (sender, sequence_number)`, are replaced with plain
+//! indexes on conversion and are no longer enforced.
+
+use crate::utils::database::{ArcDbPool, DbPoolConnection};
+use anyhow::{bail, Context, Result};
+use diesel::sql_types::{Array, BigInt, Bool, Nullable, Text, Timestamp};
+use diesel_async::{scoped_futures::ScopedFutureExt, AsyncConnection, RunQueryDsl};
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tracing::{error, info, warn};
+
+lazy_static! {
+    static ref IDENTIFIER_REGEX: Regex = Regex::new("^[a-z_][a-z0-9_]*$").unwrap();
+    static ref RANGE_BOUND_REGEX: Regex =
+        Regex::new(r"FROM \((MINVALUE|'?-?\d+'?)\) TO \((MAXVALUE|'?-?\d+'?)\)").unwrap();
+}
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExpireAction {
+    // Keep the partition as a standalone table, e.g. to archive it
+    #[default]
+    Detach,
+    Drop,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct PartitionConfig {
+    // Wait between runs
+    #[serde(default = "PartitionConfig::default_interval_secs")]
+    pub interval_secs: u64,
+    pub tables: Vec<PartitionedTableConfig>,
+}
+
+impl PartitionConfig {
+    pub const fn default_interval_secs() -> u64 {
+        600
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct PartitionedTableConfig {
+    pub table: String,
+    // Defaults to `version` for transactions and user_transactions, `transaction_version` otherwise
+    pub partition_column: Option<String>,
+    // Versions per partition
+    pub partition_size: u64,
+    // Partitions to create ahead of the one holding the latest version
+    #[serde(default = "PartitionedTableConfig::default_premake")]
+    pub premake: u64,
+    // Partitions whose newest row is older than this are expired. Unset keeps everything.
+    pub retention_days: Option<u32>,
+    #[serde(default = "PartitionedTableConfig::default_age_column")]
+    pub age_column: String,
+    #[serde(default)]
+    pub expire_action: ExpireAction,
+}
+
+impl PartitionedTableConfig {
+    pub const fn default_premake() -> u64 {
+        2
+    }
+
+    pub fn default_age_column() -> String {
+        "inserted_at".to_string()
+    }
+
+    fn partition_column(&self) -> &str {
+        match self.partition_column.as_deref() {
+            Some(column) => column,
+            None => match self.table.as_str() {
+                "transactions" | "user_transactions" => "version",
+                _ => "transaction_version",
+            },
+        }
+    }
+
+    fn validate(&self) -> Result<()> {
+        for identifier in [&self.table, &self.age_column]
+            .into_iter()
+            .chain(self.partition_column.as_ref())
+        {
+            if !IDENTIFIER_REGEX.is_match(identifier) {
+                bail!(
+                    "Invalid identifier {:?} in partition config for {}",
+                    identifier,
+                    self.table
+                );
+            }
+        }
+        if self.partition_size == 0 {
+            bail!("partition_size of {} must be greater than 0", self.table);
+        }
+        Ok(())
+    }
+
+    fn partition_name(&self, start: i64) -> String {
+        format!("{}_p{}", self.table, start)
+    }
+}
+
+#[derive(Debug, QueryableByName)]
+struct PartitionRow {
+    #[diesel(sql_type = Text)]
+    name: String,
+    #[diesel(sql_type = Text)]
+    bound: String,
+}
+
+#[derive(Debug, QueryableByName)]
+struct MaxVersion {
+    #[diesel(sql_type = Nullable<BigInt>)]
+    version: Option<i64>,
+}
+
+#[derive(Debug, QueryableByName)]
+struct MaxAge {
+    #[diesel(sql_type = Nullable<Timestamp>)]
+    age: Option<chrono::NaiveDateTime>,
+}
+
+#[derive(Debug, QueryableByName)]
+struct IsPartitioned {
+    #[diesel(sql_type = diesel::sql_types::Bool)]
+    partitioned: bool,
+}
+
+/// A primary key or unique constraint of a table to convert
+#[derive(Debug, PartialEq, QueryableByName)]
+struct UniqueConstraint {
+    #[diesel(sql_type = Text)]
+    name: String,
+    #[diesel(sql_type = Bool)]
+    is_primary: bool,
+    #[diesel(sql_type = Array<Text>)]
+    columns: Vec<String>,
+}
+
+/// Statements replacing the unique constraints that don't include the partition column with
+/// plain indexes of the same name, since Postgres can't partition the table with them. A primary
+/// key without it can't be replaced because the inserts need it for `ON CONFLICT`.
+fn replace_unique_constraints(
+    table: &str,
+    column: &str,
+    constraints: &[UniqueConstraint],
+) -> Result<Vec<String>> {
+    let mut statements = vec![];
+    for constraint in constraints {
+        if constraint.columns.iter().any(|c| c == column) {
+            continue;
+        }
+        if constraint.is_primary {
+            bail!(
+                "Primary key {} of {} doesn't include {}, so the table can't be partitioned by it",
+                constraint.name,
+                table,
+                column
+            );
+        }
+        let columns = constraint
+            .columns
+            .iter()
+            .map(|c| quote_identifier(c))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let name = quote_identifier(&constraint.name);
+        statements.push(format!("ALTER TABLE {} DROP CONSTRAINT {}", table, name));
+        statements.push(format!("CREATE INDEX {} ON {} ({})", name, table, columns));
+    }
+    Ok(statements)
+}
+
+fn quote_identifier(identifier: &str) -> String {
+    format!("\"{}\"", identifier.replace('"', "\"\""))
+}
+
+/// A partition and its range, None standing for MINVALUE / MAXVALUE
+#[derive(Debug, PartialEq)]
+struct Partition {
+    name: String,
+    start: Option<i64>,
+    end: Option<i64>,
+}
+
+impl Partition {
+    fn from_row(row: PartitionRow) -> Option<Self> {
+        let caps = RANGE_BOUND_REGEX.captures(&row.bound)?;
+        let parse = |bound: &str| bound.trim_matches('\'').parse::<i64>().ok();
+        Some(Self {
+            start: parse(&caps[1]),
+            end: parse(&caps[2]),
+            name: row.name,
+        })
+    }
+}
+
+/// Partitions entirely below the latest version, oldest first. Rows arrive in version order, so
+/// no partition is newer than the ones above it.
+fn complete_partitions(partitions: &[Partition], latest_version: i64) -> Vec<&Partition> {
+    let mut complete = partitions
+        .iter()
+        .filter(|p| p.end.map_or(false, |end| end <= latest_version))
+        .collect::<Vec<_>>();
+    complete.sort_by_key(|p| p.start);
+    complete
+}
+
+pub struct PartitionManager {
+    connection_pool: ArcDbPool,
+    config: PartitionConfig,
+    // Lowest version partitions are created for, e.g. the processor's starting version
+    min_version: i64,
+}
+
+impl PartitionManager {
+    pub fn new(
+        connection_pool: ArcDbPool,
+        config: PartitionConfig,
+        min_version: u64,
+    ) -> Result<Self> {
+        for table in &config.tables {
+            table.validate()?;
+        }
+        Ok(Self {
+            connection_pool,
+            config,
+            min_version: min_version as i64,
+        })
+    }
+
+    /// Manages every table every `interval_secs`. Failures are retried on the next interval.
+    pub async fn run(&self) {
+        loop {
+            for table in &self.config.tables {
+                if let Err(e) = self.manage(table).await {
+                    error!(
+                        table = table.table.as_str(),
+                        error = ?e,
+                        "[Partition Manager] Failed to manage partitions"
+                    );
+                }
+            }
+            tokio::time::sleep(Duration::from_secs(self.config.interval_secs)).await;
+        }
+    }
+
+    /// Converts every configured table that isn't partitioned yet, returning the converted
+    /// tables. The existing rows become a single `<table>_legacy` partition; attaching it scans
+    /// the table under an exclusive lock, so this runs as a one-off job rather than on startup.
+    pub async fn convert_unpartitioned(&self) -> Result<Vec<String>> {
+        let mut conn: DbPoolConnection = self.connection_pool.get().await?;
+        let mut converted = vec![];
+        for table in &self.config.tables {
+            if self.is_partitioned(&mut conn, table).await? {
+                continue;
+            }
+            self.convert(&mut conn, table).await?;
+            converted.push(table.table.clone());
+        }
+        Ok(converted)
+    }
+
+    async fn manage(&self, table: &PartitionedTableConfig) -> Result<()> {
+        let mut conn: DbPoolConnection = self.connection_pool.get().await?;
+        if !self.is_partitioned(&mut conn, table).await? {
+            warn!(
+                table = table.table.as_str(),
+                "[Partition Manager] Table isn't partitioned, convert it with partition-tables. Skipping"
+            );
+            return Ok(());
+        }
+
+        let latest_version = diesel::sql_query(format!(
+            "SELECT MAX({}) AS version FROM {}",
+            table.partition_column(),
+            table.table
+        ))
+        .get_result::<MaxVersion>(&mut conn)
+        .await?
+        .version
+        .unwrap_or(0)
+        .max(self.min_version);
+        let partitions = self.list_partitions(&mut conn, table).await?;
+        self.create_partitions(&mut conn, table, &partitions, latest_version)
+            .await?;
+        if let Some(retention_days) = table.retention_days {
+            self.expire_partitions(
+                &mut conn,
+                table,
+                &partitions,
+                latest_version,
+                retention_days,
+            )
+            .await?;
+        }
+        Ok(())
+    }
+
+    async fn is_partitioned(
+        &self,
+        conn: &mut DbPoolConnection<'_>,
+        table: &PartitionedTableConfig,
+    ) -> Result<bool> {
+        Ok(diesel::sql_query(
+            "SELECT EXISTS (SELECT 1 FROM pg_partitioned_table pt \
+             JOIN pg_class c ON c.oid = pt.partrelid \
+             WHERE c.relname = $1 AND pg_table_is_visible(c.oid)) AS partitioned",
+        )
+        .bind::<Text, _>(table.table.as_str())
+        .get_result::<IsPartitioned>(conn)
+        .await?
+        .partitioned)
+    }
+
+    async fn convert(
+        &self,
+        conn: &mut DbPoolConnection<'_>,
+        table: &PartitionedTableConfig,
+    ) -> Result<()> {
+        let name = table.table.as_str();
+        let column = table.partition_column();
+        let size = table.partition_size as i64;
+        let min_version = self.min_version;
+        let premake = table.premake as i64;
+        info!(
+            table = name,
+            "[Partition Manager] Converting table to a partitioned table"
+        );
+        conn.transaction::<_, diesel::result::Error, _>(|conn| {
+            async move {
+                diesel::sql_query(format!("LOCK TABLE {} IN ACCESS EXCLUSIVE MODE", name))
+                    .execute(conn)
+                    .await?;
+                let latest_version = diesel::sql_query(format!(
+                    "SELECT MAX({}) AS version FROM {}",
+                    column, name
+                ))
+                .get_result::<MaxVersion>(conn)
+                .await?
+                .version
+                .unwrap_or(0)
+                .max(min_version);
+                let constraints = diesel::sql_query(
+                    "SELECT con.conname::TEXT AS name, con.contype = 'p' AS is_primary, \
+                     ARRAY(SELECT a.attname::TEXT FROM unnest(con.conkey) WITH ORDINALITY k(attnum, ord) \
+                     JOIN pg_attribute a ON a.attrelid = con.conrelid AND a.attnum = k.attnum \
+                     ORDER BY k.ord) AS columns \
+                     FROM pg_constraint con \
+                     JOIN pg_class c ON c.oid = con.conrelid \
+                     WHERE c.relname = $1 AND pg_table_is_visible(c.oid) AND con.contype IN ('p', 'u') \
+                     ORDER BY con.conname",
+                )
+                .bind::<Text, _>(name)
+                .load::<UniqueConstraint>(conn)
+                .await?;
+                let replace_constraints = replace_unique_constraints(name, column, &constraints)
+                    .map_err(|e| diesel::result::Error::QueryBuilderError(e.into()))?;
+                for statement in replace_constraints {
+                    diesel::sql_query(statement).execute(conn).await?;
+                }
+                // The legacy partition ends at the first partition boundary above the latest row
+                let boundary = (latest_version / size + 1) * size;
+                for statement in [
+                    format!("ALTER TABLE {name} RENAME TO {name}_legacy"),
+                    format!(
+                        "CREATE TABLE {name} (LIKE {name}_legacy INCLUDING ALL) PARTITION BY RANGE ({column})"
+                    ),
+                    format!(
+                        "ALTER TABLE {name} ATTACH PARTITION {name}_legacy FOR VALUES FROM (MINVALUE) TO ({boundary})"
+                    ),
+                ] {
+                    diesel::sql_query(statement).execute(conn).await?;
+                }
+                // Create the next partitions right away so inserts never find the range missing
+                for index in 0..=premake {
+                    let start = boundary + index * size;
+                    diesel::sql_query(format!(
+                        "CREATE TABLE {name}_p{start} PARTITION OF {name} FOR VALUES FROM ({start}) TO ({end})",
+                        end = start + size
+                    ))
+                    .execute(conn)
+                    .await?;
+                }
+                Ok(())
+            }
+            .scope_boxed()
+        })
+        .await
+        .with_context(|| format!("Failed to convert {} to a partitioned table", name))
+    }
+
+    async fn list_partitions(
+        &self,
+        conn: &mut DbPoolConnection<'_>,
+        table: &PartitionedTableConfig,
+    ) -> Result<Vec<Partition>> {
+        let rows = diesel::sql_query(
+            "SELECT c.relname::TEXT AS name, pg_get_expr(c.relpartbound, c.oid) AS bound \
+             FROM pg_inherits i \
+             JOIN pg_class c ON c.oid = i.inhrelid \
+             JOIN pg_class p ON p.oid = i.inhparent \
+             WHERE p.relname = $1 AND pg_table_is_visible(p.oid)",
+        )
+        .bind::<Text, _>(table.table.as_str())
+        .load::<PartitionRow>(conn)
+        .await?;
+        Ok(rows.into_iter().filter_map(Partition::from_row).collect())
+    }
+
+    async fn create_partitions(
+        &self,
+        conn: &mut DbPoolConnection<'_>,
+        table: &PartitionedTableConfig,
+        partitions: &[Partition],
+        latest_version: i64,
+    ) -> Result<()> {
+        let size = table.partition_size as i64;
+        let current = latest_version / size;
+        for index in current..=current + table.premake as i64 {
+            let (start, end) = (index * size, (index + 1) * size);
+            let overlaps = partitions
+                .iter()
+                .any(|p| p.start.map_or(true, |s| s < end) && p.end.map_or(true, |e| e > start));
+            if overlaps {
+                continue;
+            }
+            let name = table.partition_name(start);
+            diesel::sql_query(format!(
+                "CREATE TABLE IF NOT EXISTS {} PARTITION OF {} FOR VALUES FROM ({}) TO ({})",
+                name, table.table, start, end
+            ))
+            .execute(conn)
+            .await?;
+            info!(
+                table = table.table.as_str(),
+                partition = name.as_str(),
+                "[Partition Manager] Created partition"
+            );
+        }
+        Ok(())
+    }
+
+    async fn expire_partitions(
+        &self,
+        conn: &mut DbPoolConnection<'_>,
+        table: &PartitionedTableConfig,
+        partitions: &[Partition],
+        latest_version: i64,
+        retention_days: u32,
+    ) -> Result<()> {
+        let cutoff = chrono::Utc::now().naive_utc() - chrono::Duration::days(retention_days as i64);
+        // Going oldest first, the first partition still within retention ends the run
+        for partition in complete_partitions(partitions, latest_version) {
+            let newest = diesel::sql_query(format!(
+                "SELECT MAX({}) AS age FROM {}",
+                table.age_column, partition.name
+            ))
+            .get_result::<MaxAge>(conn)
+            .await?
+            .age;
+            if newest.map_or(false, |newest| newest >= cutoff) {
+                break;
+            }
+            let statement = match table.expire_action {
+                ExpireAction::Detach => format!(
+                    "ALTER TABLE {} DETACH PARTITION {}",
+                    table.table, partition.name
+                ),
+                ExpireAction::Drop => format!("DROP TABLE {}", partition.name),
+            };
+            diesel::sql_query(statement).execute(conn).await?;
+            info!(
+                table = table.table.as_str(),
+                partition = partition.name.as_str(),
+                action = ?table.expire_action,
+                "[Partition Manager] Expired partition"
+            );
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    // Migrations that shape the keys of the partitionable tables
+    const MIGRATIONS: [&str; 2] = [
+        include_str!("db/postgres/migrations/2022-08-08-043603_core_tables/up.sql"),
+        include_str!("db/postgres/migrations/2023-09-01-231248_events_v2/up.sql"),
+    ];
+
+    /// Primary key and unique constraints of `table` after `migrations`, named the way Postgres
+    /// names them
+    fn constraints_from_ddl(table: &str, migrations: &[&str]) -> Vec<UniqueConstraint> {
+        let columns = |list: &str| {
+            list.split(',')
+                .map(|c| c.trim().to_string())
+                .collect::<Vec<_>>()
+        };
+        let primary_key = |columns: Vec<String>| UniqueConstraint {
+            name: format!("{}_pkey", table),
+            is_primary: true,
+            columns,
+        };
+        let unique = |columns: Vec<String>| UniqueConstraint {
+            name: format!("{}_{}_key", table, columns.join("_")),
+            is_primary: false,
+            columns,
+        };
+        let list_regex = Regex::new(r"\(([^)]*)\)").unwrap();
+        let drop_regex = Regex::new(&format!(
+            r"ALTER TABLE {} DROP CONSTRAINT (?:IF EXISTS )?(\w+);",
+            table
+        ))
+        .unwrap();
+        let add_regex = Regex::new(&format!(
+            r"ALTER TABLE {}\s+ADD CONSTRAINT (\w+) PRIMARY KEY \(([^)]*)\)",
+            table
+        ))
+        .unwrap();
+
+        let mut constraints = vec![];
+        for migration in migrations {
+            if let Some(start) = migration.find(&format!("CREATE TABLE {} (", table)) {
+                let body = &migration[start..];
+                let body = &body[body.find('(').unwrap() + 1..body.find("\n);").unwrap()];
+                let body = body
+                    .lines()
+                    .filter(|line| !line.trim_start().starts_with("--"))
+                    .collect::<Vec<_>>()
+                    .join(" ");
+                // Split the definitions on commas outside parentheses
+                let mut depth = 0;
+                let mut definitions = vec![String::new()];
+                for c in body.chars() {
+                    match c {
+                        '(' => depth += 1,
+                        ')' => depth -= 1,
+                        ',' if depth == 0 => {
+                            definitions.push(String::new());
+                            continue;
+                        },
+                        _ => {},
+                    }
+                    definitions.last_mut().unwrap().push(c);
+                }
+                for definition in definitions.iter().map(|d| d.trim()) {
+                    let list = || columns(&list_regex.captures(definition).unwrap()[1]);
+                    if definition.starts_with("PRIMARY KEY") {
+                        constraints.push(primary_key(list()));
+                    } else if definition.starts_with("UNIQUE") {
+                        constraints.push(unique(list()));
+                    } else if definition.starts_with("CONSTRAINT") {
+                        // Foreign keys
+                        continue;
+                    } else {
+                        let column = definition.split_whitespace().next().unwrap().to_string();
+                        // Postgres skips a UNIQUE that repeats the primary key
+                        if definition.contains("PRIMARY KEY") {
+                            constraints.push(primary_key(vec![column]));
+                        } else if definition.contains(" UNIQUE") {
+                            constraints.push(unique(vec![column]));
+                        }
+                    }
+                }
+            }
+            for caps in drop_regex.captures_iter(migration) {
+                constraints.retain(|c| c.name != caps[1]);
+            }
+            for caps in add_regex.captures_iter(migration) {
+                constraints.push(UniqueConstraint {
+                    name: caps[1].to_string(),
+                    is_primary: true,
+                    columns: columns(&caps[2]),
+                });
+            }
+        }
+        constraints
+    }
+
+    #[test]
+    fn test_replace_unique_constraints() {
+        let replaced: [(&str, Vec<&str>); 6] = [
+            ("transactions", vec![
+                "ALTER TABLE transactions DROP CONSTRAINT \"transactions_hash_key\"",
+                "CREATE INDEX \"transactions_hash_key\" ON transactions (\"hash\")",
+            ]),
+            ("user_transactions", vec![
+                "ALTER TABLE user_transactions DROP CONSTRAINT \"user_transactions_sender_sequence_number_key\"",
+                "CREATE INDEX \"user_transactions_sender_sequence_number_key\" ON user_transactions (\"sender\", \"sequence_number\")",
+            ]),
+            ("events", vec![]),
+            ("signatures", vec![]),
+            ("move_resources", vec![]),
+            ("write_set_changes", vec![]),
+        ];
+        for (table, expected) in replaced {
+            let config: PartitionedTableConfig = serde_json::from_value(serde_json::json!({
+                "table": table,
+                "partition_size": 1000000,
+            }))
+            .unwrap();
+            let constraints = constraints_from_ddl(table, &MIGRATIONS);
+            assert!(
+                constraints.iter().any(|c| c.is_primary),
+                "No primary key found for {}",
+                table
+            );
+            let statements =
+                replace_unique_constraints(table, config.partition_column(), &constraints).unwrap();
+            assert_eq!(statements, expected, "{}", table);
+        }
+
+        // events before its primary key moved to the version
+        let constraints = constraints_from_ddl("events", &MIGRATIONS[..1]);
+        assert!(replace_unique_constraints("events", "transaction_version", &constraints).is_err());
+    }
+
+    #[test]
+    fn test_parse_partition_bound() {
+        let partition = Partition::from_row(PartitionRow {
+            name: "events_p1000".to_string(),
+            bound: "FOR VALUES FROM ('1000') TO ('2000')".to_string(),
+        });
+        assert_eq!(
+            partition,
+            Some(Partition {
+                name: "events_p1000".to_string(),
+                start: Some(1000),
+                end: Some(2000),
+            })
+        );
+        let legacy = Partition::from_row(PartitionRow {
+            name: "events_legacy".to_string(),
+            bound: "FOR VALUES FROM (MINVALUE) TO (3000)".to_string(),
+        })
+        .unwrap();
+        assert_eq!((legacy.start, legacy.end), (None, Some(3000)));
+    }
+
+    #[test]
+    fn test_complete_partitions() {
+        let partition = |name: &str, start: Option<i64>, end: Option<i64>| Partition {
+            name: name.to_string(),
+            start,
+            end,
+        };
+        let partitions = vec![
+            partition("events_p2000", Some(2000), Some(3000)),
+            partition("events_p3000", Some(3000), Some(4000)),
+            partition("events_legacy", None, Some(1000)),
+            partition("events_p1000", Some(1000), Some(2000)),
+        ];
+        let names = complete_partitions(&partitions, 3500)
+            .into_iter()
+            .map(|p| p.name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(names, vec!["events_legacy", "events_p1000", "events_p2000"]);
+    }
+}
@@ -0,0 +1,528 @@
+// Copyright © Aptos Foundation
+// SPDX-License-Identifier: Apache-2.0
+
+use super::{
+    marketplace_processor::MarketplaceContractConfig, token_v2_processor::parse_v2_token,
+    DefaultProcessingResult, ProcessorName, ProcessorTrait,
+};
+use crate::{
+    db::common::models::{
+        collection_stats_models::collection_stats::{
+            refresh_collection_holders_query, refresh_collection_stats_hourly_query,
+            refresh_collection_stats_query, refresh_volume_windows_query,
+            resolve_collection_stats_events, CollectionStatsEvent, CollectionStatsOwnership,
+            CollectionStatsToken, CurrentCollectionSupply,
+        },
+        marketplace_models::marketplace_utils::{MarketplaceDecoder, MarketplaceEventKind},
+        token_models::tokens::TableMetadataForToken,
+    },
+    gap_detectors::ProcessingResult,
+    schema,
+    utils::{
+        database::{execute_with_better_error_conn, get_config_table_chunk_size, ArcDbPool},
+        util::parse_timestamp,
+    },
+    IndexerGrpcProcessorConfig,
+};
+use ahash::{AHashMap, AHashSet};
+use anyhow::bail;
+use aptos_protos::transaction::v1::{transaction::TxnData, Transaction};
+use async_trait::async_trait;
+use chrono::NaiveDateTime;
+use diesel::{
+    pg::{upsert::excluded, Pg},
+    query_builder::QueryFragment,
+    ExpressionMethods,
+};
+use diesel_async::{scoped_futures::ScopedFutureExt, AsyncConnection};
+use serde::{Deserialize, Serialize};
+use std::fmt::Debug;
+use tracing::error;
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct CollectionStatsProcessorConfig {
+    // Marketplaces whose filled listings and offers count as sales
+    #[serde(default)]
+    pub marketplaces: Vec<MarketplaceContractConfig>,
+    #[serde(default = "IndexerGrpcProcessorConfig::default_query_retries")]
+    pub query_retries: u32,
+    #[serde(default = "IndexerGrpcProcessorConfig::default_query_retry_delay_ms")]
+    pub query_retry_delay_ms: u64,
+}
+
+pub struct CollectionStatsProcessor {
+    connection_pool: ArcDbPool,
+    config: CollectionStatsProcessorConfig,
+    decoders: Vec<Box<dyn MarketplaceDecoder>>,
+    per_table_chunk_sizes: AHashMap<String, usize>,
+}
+
+impl CollectionStatsProcessor {
+    pub fn new(
+        connection_pool: ArcDbPool,
+        config: CollectionStatsProcessorConfig,
+        per_table_chunk_sizes: AHashMap<String, usize>,
+    ) -> Self {
+        let decoders = config
+            .marketplaces
+            .iter()
+            .map(|m| m.decoder.build(&m.contract_address, &m.event_types))
+            .collect();
+        Self {
+            connection_pool,
+            config,
+            decoders,
+            per_table_chunk_sizes,
+        }
+    }
+
+    /// Filled listings and offers in the batch
+    fn parse_sales(&self, transactions: &[Transaction]) -> Vec<CollectionStatsEvent> {
+        let mut sales = vec![];
+        for txn in transactions {
+            let txn_version = txn.version as i64;
+            let user_txn = match txn.txn_data.as_ref() {
+                Some(TxnData::User(user_txn)) => user_txn,
+                _ => continue,
+            };
+            let txn_timestamp = parse_timestamp(txn.timestamp.as_ref().unwrap(), txn_version);
+            for (index, event) in user_txn.events.iter().enumerate() {
+                for decoder in &self.decoders {
+                    match decoder.decode(event.type_str.as_str(), &event.data, txn_version) {
+                        Ok(Some(decoded)) => {
+                            if matches!(
+                                decoded.kind,
+                                MarketplaceEventKind::ListingFilled
+                                    | MarketplaceEventKind::TokenOfferFilled
+                                    | MarketplaceEventKind::CollectionOfferFilled
+                            ) {
+                                sales.push(CollectionStatsEvent::from_sale(
+                                    txn_version,
+                                    index as i64,
+                                    decoded.token.collection_id,
+                                    decoded.token.token_data_id,
+                                    txn_timestamp,
+                                    decoded.price,
+                                ));
+                            }
+                        },
+                        Ok(None) => {},
+                        Err(e) => {
+                            error!(
+                                transaction_version = txn_version,
+                                error = ?e,
+                                "Failed to decode marketplace event"
+                            );
+                        },
+                    }
+                }
+            }
+        }
+        sales
+    }
+}
+
+impl Debug for CollectionStatsProcessor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let state = &self.connection_pool.state();
+        write!(
+            f,
+            "CollectionStatsProcessor {{ connections: {:?}  idle_connections: {:?} }}",
+            state.connections, state.idle_connections
+        )
+    }
+}
+
+/// Writes the tokens, ownerships, counted events and supply of the batch, then recomputes the
+/// touched hours, the rollup of every touched collection and the holders of the touched tokens, in
+/// one transaction so the rollup sees this batch. Every step is derived from rows this processor
+/// stored, so writing a batch twice gives the same result.
+async fn insert_to_db(
+    conn_pool: &ArcDbPool,
+    name: &'static str,
+    start_version: u64,
+    end_version: u64,
+    stats_events: &[CollectionStatsEvent],
+    collection_supplies: &[CurrentCollectionSupply],
+    tokens: &[CollectionStatsToken],
+    ownerships: &[CollectionStatsOwnership],
+    last_transaction: (i64, NaiveDateTime),
+    per_table_chunk_sizes: &AHashMap<String, usize>,
+) -> Result<(), diesel::result::Error> {
+    if stats_events.is_empty()
+        && collection_supplies.is_empty()
+        && tokens.is_empty()
+        && ownerships.is_empty()
+    {
+        return Ok(());
+    }
+    // Tokens written by this batch may attribute events stored without a collection, and their
+    // owners may have been written by a batch that committed first
+    let mut event_token_data_ids = stats_events
+        .iter()
+        .filter(|e| e.collection_id.is_none())
+        .filter_map(|e| e.token_data_id.clone())
+        .chain(tokens.iter().map(|t| t.token_data_id.clone()))
+        .collect::<AHashSet<_>>()
+        .into_iter()
+        .collect::<Vec<_>>();
+    event_token_data_ids.sort();
+    let mut holder_token_data_ids = ownerships
+        .iter()
+        .map(|o| o.token_data_id.clone())
+        .chain(tokens.iter().map(|t| t.token_data_id.clone()))
+        .collect::<AHashSet<_>>()
+        .into_iter()
+        .collect::<Vec<_>>();
+    holder_token_data_ids.sort();
+    tracing::trace!(
+        name = name,
+        start_version = start_version,
+        end_version = end_version,
+        "Inserting to db",
+    );
+
+    let token_chunk_size = get_config_table_chunk_size::<CollectionStatsToken>(
+        "collection_stats_tokens",
+        per_table_chunk_sizes,
+    );
+    let ownership_chunk_size = get_config_table_chunk_size::<CollectionStatsOwnership>(
+        "collection_stats_ownerships",
+        per_table_chunk_sizes,
+    );
+    let event_chunk_size = get_config_table_chunk_size::<CollectionStatsEvent>(
+        "collection_stats_events",
+        per_table_chunk_sizes,
+    );
+    let supply_chunk_size = get_config_table_chunk_size::<CurrentCollectionSupply>(
+        "current_collection_stats",
+        per_table_chunk_sizes,
+    );
+    let (last_transaction_version, last_transaction_timestamp) = last_transaction;
+    let conn = &mut conn_pool.get().await.map_err(|e| {
+        tracing::warn!("Error getting connection from pool: {:?}", e);
+        diesel::result::Error::DatabaseError(
+            diesel::result::DatabaseErrorKind::UnableToSendCommand,
+            Box::new(e.to_string()),
+        )
+    })?;
+    conn.transaction::<_, diesel::result::Error, _>(|conn| {
+        async move {
+            for chunk in tokens.chunks(token_chunk_size) {
+                let (query, additional_where_clause) =
+                    insert_collection_stats_tokens_query(chunk.to_vec());
+                execute_with_better_error_conn(conn, query, additional_where_clause).await?;
+            }
+            for chunk in ownerships.chunks(ownership_chunk_size) {
+                let (query, additional_where_clause) =
+                    insert_collection_stats_ownerships_query(chunk.to_vec());
+                execute_with_better_error_conn(conn, query, additional_where_clause).await?;
+            }
+            for chunk in stats_events.chunks(event_chunk_size) {
+                let (query, additional_where_clause) =
+                    insert_collection_stats_events_query(chunk.to_vec());
+                execute_with_better_error_conn(conn, query, additional_where_clause).await?;
+            }
+
+            let mut buckets = stats_events
+                .iter()
+                .filter_map(|e| {
+                    e.collection_id
+                        .clone()
+                        .map(|collection_id| (collection_id, e.bucket_start))
+                })
+                .collect::<AHashSet<_>>();
+            if !event_token_data_ids.is_empty() {
+                buckets.extend(
+                    resolve_collection_stats_events(conn, event_token_data_ids)
+                        .await?
+                        .into_iter()
+                        .map(|b| (b.collection_id, b.bucket_start)),
+                );
+            }
+            let mut buckets = buckets.into_iter().collect::<Vec<_>>();
+            buckets.sort();
+            let mut collection_ids = buckets
+                .iter()
+                .map(|(collection_id, _)| collection_id.clone())
+                .chain(collection_supplies.iter().map(|s| s.collection_id.clone()))
+                .collect::<AHashSet<_>>()
+                .into_iter()
+                .collect::<Vec<_>>();
+            collection_ids.sort();
+            if !buckets.is_empty() {
+                let (bucket_collection_ids, bucket_starts): (Vec<_>, Vec<_>) =
+                    buckets.into_iter().unzip();
+                execute_with_better_error_conn(
+                    conn,
+                    refresh_collection_stats_hourly_query(bucket_collection_ids, bucket_starts),
+                    None,
+                )
+                .await?;
+            }
+            for chunk in collection_supplies.chunks(supply_chunk_size) {
+                let (query, additional_where_clause) =
+                    insert_collection_supplies_query(chunk.to_vec());
+                execute_with_better_error_conn(conn, query, additional_where_clause).await?;
+            }
+            if !collection_ids.is_empty() {
+                execute_with_better_error_conn(
+                    conn,
+                    refresh_collection_stats_query(
+                        collection_ids,
+                        last_transaction_version,
+                        last_transaction_timestamp,
+                    ),
+                    None,
+                )
+                .await?;
+            }
+            if !holder_token_data_ids.is_empty() {
+                execute_with_better_error_conn(
+                    conn,
+                    refresh_collection_holders_query(
+                        holder_token_data_ids,
+                        last_transaction_version,
+                        last_transaction_timestamp,
+                    ),
+                    None,
+                )
+                .await?;
+            }
+            execute_with_better_error_conn(
+                conn,
+                refresh_volume_windows_query(last_transaction_timestamp),
+                None,
+            )
+            .await?;
+            Ok(())
+        }
+        .scope_boxed()
+    })
+    .await
+}
+
+fn insert_collection_stats_tokens_query(
+    items_to_insert: Vec<CollectionStatsToken>,
+) -> (
+    impl QueryFragment<Pg> + diesel::query_builder::QueryId + Send,
+    Option<&'static str>,
+) {
+    (
+        diesel::insert_into(schema::collection_stats_tokens::table)
+            .values(items_to_insert)
+            .on_conflict_do_nothing(),
+        None,
+    )
+}
+
+fn insert_collection_stats_ownerships_query(
+    items_to_insert: Vec<CollectionStatsOwnership>,
+) -> (
+    impl QueryFragment<Pg> + diesel::query_builder::QueryId + Send,
+    Option<&'static str>,
+) {
+    use schema::collection_stats_ownerships::dsl::*;
+
+    (
+        diesel::insert_into(schema::collection_stats_ownerships::table)
+            .values(items_to_insert)
+            .on_conflict((token_data_id, property_version_v1, owner_address, storage_id))
+            .do_update()
+            .set((
+                amount.eq(excluded(amount)),
+                last_transaction_version.eq(excluded(last_transaction_version)),
+            )),
+        Some(" WHERE collection_stats_ownerships.last_transaction_version <= excluded.last_transaction_version "),
+    )
+}
+
+fn insert_collection_stats_events_query(
+    items_to_insert: Vec<CollectionStatsEvent>,
+) -> (
+    impl QueryFragment<Pg> + diesel::query_builder::QueryId + Send,
+    Option<&'static str>,
+) {
+    (
+        diesel::insert_into(schema::collection_stats_events::table)
+            .values(items_to_insert)
+            .on_conflict_do_nothing(),
+        None,
+    )
+}
+
+fn insert_collection_supplies_query(
+    items_to_insert: Vec<CurrentCollectionSupply>,
+) -> (
+    impl QueryFragment<Pg> + diesel::query_builder::QueryId + Send,
+    Option<&'static str>,
+) {
+    use schema::current_collection_stats::dsl::*;
+
+    (
+        diesel::insert_into(schema::current_collection_stats::table)
+            .values(items_to_insert)
+            .on_conflict(collection_id)
+            .do_update()
+            .set((
+                current_supply.eq(excluded(current_supply)),
+                max_supply.eq(excluded(max_supply)),
+                total_minted.eq(excluded(total_minted)),
+                last_transaction_version.eq(excluded(last_transaction_version)),
+                last_transaction_timestamp.eq(excluded(last_transaction_timestamp)),
+            )),
+        Some(" WHERE current_collection_stats.last_transaction_version <= excluded.last_transaction_version "),
+    )
+}
+
+#[async_trait]
+impl ProcessorTrait for CollectionStatsProcessor {
+    fn name(&self) -> &'static str {
+        ProcessorName::CollectionStatsProcessor.into()
+    }
+
+    async fn process_transactions(
+        &self,
+        transactions: Vec<Transaction>,
+        start_version: u64,
+        end_version: u64,
+        _: Option<u64>,
+    ) -> anyhow::Result<ProcessingResult> {
+        let processing_start = std::time::Instant::now();
+        let last_transaction_timestamp = transactions.last().unwrap().timestamp.clone();
+        let last_transaction = (
+            transactions.last().unwrap().version as i64,
+            parse_timestamp(
+                last_transaction_timestamp.as_ref().unwrap(),
+                transactions.last().unwrap().version as i64,
+            ),
+        );
+
+        let mut conn = self.get_conn().await;
+        let table_handle_to_owner =
+            TableMetadataForToken::get_table_handle_to_owner_from_transactions(&transactions);
+        let (
+            _,
+            _,
+            _,
+            current_collections_v2,
+            current_token_datas_v2,
+            _,
+            current_token_ownerships_v2,
+            current_deleted_token_ownerships_v2,
+            token_activities_v2,
+            _,
+            _,
+            _,
+        ) = parse_v2_token(
+            &transactions,
+            &table_handle_to_owner,
+            &mut conn,
+            self.config.query_retries,
+            self.config.query_retry_delay_ms,
+        )
+        .await;
+
+        // Activities only carry the token. Events of tokens that aren't in this batch are stored
+        // without a collection and attributed from collection_stats_tokens when they're written
+        let mut tokens: AHashMap<String, CollectionStatsToken> = AHashMap::new();
+        for token_data in &current_token_datas_v2 {
+            tokens.insert(
+                token_data.token_data_id.clone(),
+                CollectionStatsToken::from(token_data),
+            );
+        }
+        let mut stats_events = token_activities_v2
+            .iter()
+            .filter_map(|activity| {
+                CollectionStatsEvent::from_token_activity(
+                    activity,
+                    tokens
+                        .get(&activity.token_data_id)
+                        .map(|token| &token.collection_id),
+                )
+            })
+            .chain(self.parse_sales(&transactions))
+            .collect::<Vec<_>>();
+        // Sort by PK to avoid deadlocks between concurrent batches
+        stats_events.sort_by_key(|e| (e.transaction_version, e.event_index));
+        let mut tokens = tokens.into_values().collect::<Vec<_>>();
+        tokens.sort_by(|a, b| a.token_data_id.cmp(&b.token_data_id));
+        // Latest amount of every ownership the batch changed, including the ones it emptied
+        let mut ownerships: AHashMap<_, CollectionStatsOwnership> = AHashMap::new();
+        for ownership in current_token_ownerships_v2
+            .iter()
+            .chain(current_deleted_token_ownerships_v2.iter())
+        {
+            let ownership = CollectionStatsOwnership::from(ownership);
+            let key = (
+                ownership.token_data_id.clone(),
+                ownership.property_version_v1.clone(),
+                ownership.owner_address.clone(),
+                ownership.storage_id.clone(),
+            );
+            match ownerships.get(&key) {
+                Some(existing)
+                    if existing.last_transaction_version > ownership.last_transaction_version => {},
+                _ => {
+                    ownerships.insert(key, ownership);
+                },
+            }
+        }
+        let mut ownerships = ownerships.into_iter().collect::<Vec<_>>();
+        ownerships.sort_by(|(a, _), (b, _)| a.cmp(b));
+        let ownerships = ownerships
+            .into_iter()
+            .map(|(_, ownership)| ownership)
+            .collect::<Vec<_>>();
+        let mut collection_supplies = current_collections_v2
+            .iter()
+            .map(CurrentCollectionSupply::from)
+            .collect::<Vec<_>>();
+        collection_supplies.sort_by(|a, b| a.collection_id.cmp(&b.collection_id));
+
+        let processing_duration_in_secs = processing_start.elapsed().as_secs_f64();
+        let db_insertion_start = std::time::Instant::now();
+
+        let tx_result = insert_to_db(
+            &self.connection_pool,
+            self.name(),
+            start_version,
+            end_version,
+            &stats_events,
+            &collection_supplies,
+            &tokens,
+            &ownerships,
+            last_transaction,
+            &self.per_table_chunk_sizes,
+        )
+        .await;
+        let db_insertion_duration_in_secs = db_insertion_start.elapsed().as_secs_f64();
+        match tx_result {
+            Ok(_) => Ok(ProcessingResult::DefaultProcessingResult(
+                DefaultProcessingResult {
+                    start_version,
+                    end_version,
+                    processing_duration_in_secs,
+                    db_insertion_duration_in_secs,
+                    last_transaction_timestamp,
+                },
+            )),
+            Err(e) => {
+                error!(
+                    start_version = start_version,
+                    end_version = end_version,
+                    processor_name = self.name(),
+                    error = ?e,
+                    "[Parser] Error inserting transactions to db",
+                );
+                bail!(e)
+            },
+        }
+    }
+
+    fn connection_pool(&self) -> &ArcDbPool {
+        &self.connection_pool
+    }
+}
@@ -0,0 +1,344 @@
+// Copyright © Aptos Foundation
+// SPDX-License-Identifier: Apache-2.0
+
+use super::{DefaultProcessingResult, ProcessorName, ProcessorTrait};
+use crate::{
+    db::common::models::launchpad_models::{
+        launchpad_mint_stages::LaunchpadMintStage,
+        launchpad_mints::{LaunchpadMint, ALLOWLIST_MINT_TYPE, PUBLIC_MINT_TYPE},
+        launchpad_utils::{LaunchpadEvent, LaunchpadEventDecoder, LaunchpadEventKind},
+        LaunchpadTransactionModel,
+    },
+    gap_detectors::ProcessingResult,
+    schema,
+    utils::{
+        counters::PROCESSOR_UNKNOWN_TYPE_COUNT,
+        database::{execute_in_chunks, get_config_table_chunk_size, ArcDbPool},
+        util::{
+            get_entry_function_from_user_request, parse_timestamp, standardize_address,
+            standardize_entry_function_id,
+        },
+    },
+};
+use ahash::AHashMap;
+use anyhow::bail;
+use aptos_protos::transaction::v1::{transaction::TxnData, Transaction};
+use async_trait::async_trait;
+use diesel::{
+    pg::{upsert::excluded, Pg},
+    query_builder::QueryFragment,
+    ExpressionMethods,
+};
+use serde::{Deserialize, Serialize};
+use std::fmt::Debug;
+use tracing::error;
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct LaunchpadProcessorConfig {
+    pub contract_address: String,
+    // Successful calls to entry functions of this module are queued in launchpad_transactions
+    #[serde(default = "LaunchpadProcessorConfig::default_module")]
+    pub module: String,
+    // Event `module::Struct` -> kind. Leave empty to use the default event names
+    #[serde(default)]
+    pub event_types: AHashMap<String, LaunchpadEventKind>,
+}
+
+impl LaunchpadProcessorConfig {
+    pub fn default_module() -> String {
+        "launchpad".to_string()
+    }
+}
+
+pub struct LaunchpadProcessor {
+    connection_pool: ArcDbPool,
+    contract_address: String,
+    // `address::module::` prefix of the entry functions to queue
+    entry_function_prefix: String,
+    decoder: LaunchpadEventDecoder,
+    per_table_chunk_sizes: AHashMap<String, usize>,
+}
+
+impl LaunchpadProcessor {
+    pub fn new(
+        connection_pool: ArcDbPool,
+        config: LaunchpadProcessorConfig,
+        per_table_chunk_sizes: AHashMap<String, usize>,
+    ) -> Self {
+        let contract_address = standardize_address(&config.contract_address.to_lowercase());
+        Self {
+            connection_pool,
+            entry_function_prefix: format!("{}::{}::", contract_address, config.module),
+            decoder: LaunchpadEventDecoder::new(&contract_address, &config.event_types),
+            contract_address,
+            per_table_chunk_sizes,
+        }
+    }
+}
+
+impl Debug for LaunchpadProcessor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let state = &self.connection_pool.state();
+        write!(
+            f,
+            "LaunchpadProcessor {{ connections: {:?}  idle_connections: {:?} }}",
+            state.connections, state.idle_connections
+        )
+    }
+}
+
+async fn insert_to_db(
+    conn: ArcDbPool,
+    name: &'static str,
+    start_version: u64,
+    end_version: u64,
+    launchpad_transactions: &[LaunchpadTransactionModel],
+    mint_stages: &[LaunchpadMintStage],
+    mints: &[LaunchpadMint],
+    per_table_chunk_sizes: &AHashMap<String, usize>,
+) -> Result<(), diesel::result::Error> {
+    tracing::trace!(
+        name = name,
+        start_version = start_version,
+        end_version = end_version,
+        "Inserting to db",
+    );
+
+    let lt = execute_in_chunks(
+        conn.clone(),
+        insert_launchpad_transactions_query,
+        launchpad_transactions,
+        get_config_table_chunk_size::<LaunchpadTransactionModel>(
+            "launchpad_transactions",
+            per_table_chunk_sizes,
+        ),
+    );
+    let ms = execute_in_chunks(
+        conn.clone(),
+        insert_launchpad_mint_stages_query,
+        mint_stages,
+        get_config_table_chunk_size::<LaunchpadMintStage>(
+            "launchpad_mint_stages",
+            per_table_chunk_sizes,
+        ),
+    );
+    let lm = execute_in_chunks(
+        conn,
+        insert_launchpad_mints_query,
+        mints,
+        get_config_table_chunk_size::<LaunchpadMint>("launchpad_mints", per_table_chunk_sizes),
+    );
+    let (lt_res, ms_res, lm_res) = futures::join!(lt, ms, lm);
+    for res in [lt_res, ms_res, lm_res] {
+        res?;
+    }
+    Ok(())
+}
+
+fn insert_launchpad_transactions_query(
+    items_to_insert: Vec<LaunchpadTransactionModel>,
+) -> (
+    impl QueryFragment<Pg> + diesel::query_builder::QueryId + Send,
+    Option<&'static str>,
+) {
+    use schema::launchpad_transactions::dsl::*;
+
+    (
+        diesel::insert_into(schema::launchpad_transactions::table)
+            .values(items_to_insert)
+            .on_conflict(id)
+            .do_nothing(),
+        None,
+    )
+}
+
+fn insert_launchpad_mint_stages_query(
+    items_to_insert: Vec<LaunchpadMintStage>,
+) -> (
+    impl QueryFragment<Pg> + diesel::query_builder::QueryId + Send,
+    Option<&'static str>,
+) {
+    use schema::launchpad_mint_stages::dsl::*;
+
+    (
+        diesel::insert_into(schema::launchpad_mint_stages::table)
+            .values(items_to_insert)
+            .on_conflict((collection_id, stage))
+            .do_update()
+            .set((
+                contract_address.eq(excluded(contract_address)),
+                is_allowlist.eq(excluded(is_allowlist)),
+                price.eq(excluded(price)),
+                per_wallet_limit.eq(excluded(per_wallet_limit)),
+                start_time.eq(excluded(start_time)),
+                end_time.eq(excluded(end_time)),
+                last_transaction_version.eq(excluded(last_transaction_version)),
+                last_transaction_timestamp.eq(excluded(last_transaction_timestamp)),
+                inserted_at.eq(excluded(inserted_at)),
+            )),
+        Some(" WHERE launchpad_mint_stages.last_transaction_version <= excluded.last_transaction_version "),
+    )
+}
+
+fn insert_launchpad_mints_query(
+    items_to_insert: Vec<LaunchpadMint>,
+) -> (
+    impl QueryFragment<Pg> + diesel::query_builder::QueryId + Send,
+    Option<&'static str>,
+) {
+    use schema::launchpad_mints::dsl::*;
+
+    (
+        diesel::insert_into(schema::launchpad_mints::table)
+            .values(items_to_insert)
+            .on_conflict((transaction_version, event_index))
+            .do_nothing(),
+        None,
+    )
+}
+
+#[async_trait]
+impl ProcessorTrait for LaunchpadProcessor {
+    fn name(&self) -> &'static str {
+        ProcessorName::LaunchpadProcessor.into()
+    }
+
+    async fn process_transactions(
+        &self,
+        transactions: Vec<Transaction>,
+        start_version: u64,
+        end_version: u64,
+        _: Option<u64>,
+    ) -> anyhow::Result<ProcessingResult> {
+        let processing_start = std::time::Instant::now();
+        let last_transaction_timestamp = transactions.last().unwrap().timestamp.clone();
+
+        let mut launchpad_transactions = vec![];
+        // Keyed by PK so only the latest stage update in the batch is written
+        let mut mint_stages: AHashMap<(String, String), LaunchpadMintStage> = AHashMap::new();
+        let mut mints = vec![];
+        for txn in &transactions {
+            let txn_version = txn.version as i64;
+            let user_txn = match txn.txn_data.as_ref() {
+                Some(TxnData::User(user_txn)) => user_txn,
+                Some(_) => continue,
+                None => {
+                    PROCESSOR_UNKNOWN_TYPE_COUNT
+                        .with_label_values(&["LaunchpadProcessor"])
+                        .inc();
+                    tracing::warn!(
+                        transaction_version = txn_version,
+                        "Transaction data doesn't exist"
+                    );
+                    continue;
+                },
+            };
+            let txn_info = txn.info.as_ref().unwrap();
+            if !txn_info.success {
+                continue;
+            }
+            let txn_timestamp = parse_timestamp(txn.timestamp.as_ref().unwrap(), txn_version);
+
+            let is_launchpad_call =
+                get_entry_function_from_user_request(user_txn.request.as_ref().unwrap())
+                    .map(|f| {
+                        standardize_entry_function_id(&f).starts_with(&self.entry_function_prefix)
+                    })
+                    .unwrap_or(false);
+            if is_launchpad_call {
+                launchpad_transactions
+                    .push(LaunchpadTransactionModel::from_transaction(user_txn, txn));
+            }
+
+            for (index, event) in user_txn.events.iter().enumerate() {
+                let event_type = event.type_str.as_str();
+                let decoded = match self.decoder.decode(event_type, &event.data, txn_version) {
+                    Ok(Some(decoded)) => decoded,
+                    Ok(None) => continue,
+                    Err(e) => {
+                        error!(
+                            transaction_version = txn_version,
+                            error = ?e,
+                            "Failed to decode launchpad event"
+                        );
+                        continue;
+                    },
+                };
+                match decoded {
+                    LaunchpadEvent::MintStageUpdated(inner) => {
+                        let stage = LaunchpadMintStage::from_event(
+                            &self.contract_address,
+                            &inner,
+                            txn_version,
+                            txn_timestamp,
+                        );
+                        mint_stages
+                            .insert((stage.collection_id.clone(), stage.stage.clone()), stage);
+                    },
+                    LaunchpadEvent::AllowlistMint(inner) => mints.push(LaunchpadMint::from_event(
+                        &self.contract_address,
+                        &inner,
+                        ALLOWLIST_MINT_TYPE,
+                        txn_version,
+                        index as i64,
+                        txn_timestamp,
+                    )),
+                    LaunchpadEvent::PublicMint(inner) => mints.push(LaunchpadMint::from_event(
+                        &self.contract_address,
+                        &inner,
+                        PUBLIC_MINT_TYPE,
+                        txn_version,
+                        index as i64,
+                        txn_timestamp,
+                    )),
+                }
+            }
+        }
+
+        // Sort by PK to avoid deadlocks between concurrent batches
+        let mut mint_stages = mint_stages.into_values().collect::<Vec<_>>();
+        mint_stages.sort_by(|a, b| (&a.collection_id, &a.stage).cmp(&(&b.collection_id, &b.stage)));
+
+        let processing_duration_in_secs = processing_start.elapsed().as_secs_f64();
+        let db_insertion_start = std::time::Instant::now();
+
+        let tx_result = insert_to_db(
+            self.get_pool(),
+            self.name(),
+            start_version,
+            end_version,
+            &launchpad_transactions,
+            &mint_stages,
+            &mints,
+            &self.per_table_chunk_sizes,
+        )
+        .await;
+        let db_insertion_duration_in_secs = db_insertion_start.elapsed().as_secs_f64();
+        match tx_result {
+            Ok(_) => Ok(ProcessingResult::DefaultProcessingResult(
+                DefaultProcessingResult {
+                    start_version,
+                    end_version,
+                    processing_duration_in_secs,
+                    db_insertion_duration_in_secs,
+                    last_transaction_timestamp,
+                },
+            )),
+            Err(e) => {
+                error!(
+                    start_version = start_version,
+                    end_version = end_version,
+                    processor_name = self.name(),
+                    error = ?e,
+                    "[Parser] Error inserting transactions to db",
+                );
+                bail!(e)
+            },
+        }
+    }
+
+    fn connection_pool(&self) -> &ArcDbPool {
+        &self.connection_pool
+    }
+}
@@ -0,0 +1,484 @@
+// Copyright © Aptos Foundation
+// SPDX-License-Identifier: Apache-2.0
+
+use super::{DefaultProcessingResult, ProcessorName, ProcessorTrait};
+use crate::{
+    db::common::models::{
+        marketplace_models::{
+            marketplace_activities::MarketplaceActivity,
+            marketplace_bids::CurrentBid,
+            marketplace_listings::{CurrentListing, ListingInvalidation},
+            marketplace_utils::{MarketplaceDecoder, MarketplaceDecoderKind, MarketplaceEventKind},
+        },
+        token_models::token_utils::TokenEvent,
+        token_v2_models::v2_token_utils::V2TokenEvent,
+    },
+    gap_detectors::ProcessingResult,
+    schema,
+    utils::{
+        counters::{MARKETPLACE_DECODE_ERROR_COUNT, PROCESSOR_UNKNOWN_TYPE_COUNT},
+        database::{
+            execute_in_chunks, execute_with_better_error, get_config_table_chunk_size, ArcDbPool,
+        },
+        util::{parse_timestamp, standardize_address},
+    },
+};
+use ahash::{AHashMap, AHashSet};
+use anyhow::bail;
+use aptos_protos::transaction::v1::{transaction::TxnData, Transaction};
+use async_trait::async_trait;
+use diesel::{
+    pg::{upsert::excluded, Pg},
+    query_builder::QueryFragment,
+    sql_types::{Array, Text},
+    ExpressionMethods,
+};
+use serde::{Deserialize, Serialize};
+use std::fmt::Debug;
+use tracing::error;
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct MarketplaceContractConfig {
+    // Written to the marketplace column
+    pub name: String,
+    pub contract_address: String,
+    pub decoder: MarketplaceDecoderKind,
+    // Event `module::Struct` -> kind. Leave empty to use the decoder's defaults
+    #[serde(default)]
+    pub event_types: AHashMap<String, MarketplaceEventKind>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct MarketplaceProcessorConfig {
+    pub marketplaces: Vec<MarketplaceContractConfig>,
+}
+
+struct Marketplace {
+    name: String,
+    contract_address: String,
+    decoder: Box<dyn MarketplaceDecoder>,
+}
+
+pub struct MarketplaceProcessor {
+    connection_pool: ArcDbPool,
+    marketplaces: Vec<Marketplace>,
+    per_table_chunk_sizes: AHashMap<String, usize>,
+}
+
+impl MarketplaceProcessor {
+    pub fn new(
+        connection_pool: ArcDbPool,
+        config: MarketplaceProcessorConfig,
+        per_table_chunk_sizes: AHashMap<String, usize>,
+    ) -> Self {
+        let marketplaces = config
+            .marketplaces
+            .iter()
+            .map(|m| Marketplace {
+                name: m.name.clone(),
+                contract_address: standardize_address(&m.contract_address),
+                decoder: m.decoder.build(&m.contract_address, &m.event_types),
+            })
+            .collect();
+        Self {
+            connection_pool,
+            marketplaces,
+            per_table_chunk_sizes,
+        }
+    }
+}
+
+impl Debug for MarketplaceProcessor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let state = &self.connection_pool.state();
+        write!(
+            f,
+            "MarketplaceProcessor {{ connections: {:?}  idle_connections: {:?} }}",
+            state.connections, state.idle_connections
+        )
+    }
+}
+
+async fn insert_to_db(
+    conn: ArcDbPool,
+    name: &'static str,
+    start_version: u64,
+    end_version: u64,
+    marketplace_activities: &[MarketplaceActivity],
+    current_listings: &[CurrentListing],
+    current_bids: &[CurrentBid],
+    listing_invalidations: &[ListingInvalidation],
+    per_table_chunk_sizes: &AHashMap<String, usize>,
+) -> Result<(), diesel::result::Error> {
+    tracing::trace!(
+        name = name,
+        start_version = start_version,
+        end_version = end_version,
+        "Inserting to db",
+    );
+
+    let ma = execute_in_chunks(
+        conn.clone(),
+        insert_marketplace_activities_query,
+        marketplace_activities,
+        get_config_table_chunk_size::<MarketplaceActivity>(
+            "marketplace_activities",
+            per_table_chunk_sizes,
+        ),
+    );
+    let cl = execute_in_chunks(
+        conn.clone(),
+        insert_current_listings_query,
+        current_listings,
+        get_config_table_chunk_size::<CurrentListing>("current_listings", per_table_chunk_sizes),
+    );
+    let cb = execute_in_chunks(
+        conn.clone(),
+        insert_current_bids_query,
+        current_bids,
+        get_config_table_chunk_size::<CurrentBid>("current_bids", per_table_chunk_sizes),
+    );
+    let li = execute_in_chunks(
+        conn.clone(),
+        insert_listing_invalidations_query,
+        listing_invalidations,
+        get_config_table_chunk_size::<ListingInvalidation>(
+            "listing_invalidations",
+            per_table_chunk_sizes,
+        ),
+    );
+    let (ma_res, cl_res, cb_res, li_res) = futures::join!(ma, cl, cb, li);
+    for res in [ma_res, cl_res, cb_res, li_res] {
+        res?;
+    }
+
+    // Must run after both upserts. A batch can be written before the batch that placed a listing
+    // it invalidates, so active listings of this batch are checked against stored invalidations too.
+    // Whichever of the two batches commits last applies the invalidation.
+    let token_data_ids = listing_invalidations
+        .iter()
+        .map(|i| i.token_data_id.clone())
+        .chain(
+            current_listings
+                .iter()
+                .filter(|l| l.is_active)
+                .map(|l| l.token_data_id.clone()),
+        )
+        .collect::<AHashSet<_>>()
+        .into_iter()
+        .collect::<Vec<_>>();
+    if !token_data_ids.is_empty() {
+        execute_with_better_error(
+            conn.clone(),
+            invalidate_listings_query(token_data_ids),
+            None,
+        )
+        .await?;
+    }
+
+    // Every batch up to the checkpoint is committed, so its invalidations have been applied
+    execute_with_better_error(conn, delete_applied_invalidations_query(name), None).await?;
+    Ok(())
+}
+
+fn insert_marketplace_activities_query(
+    items_to_insert: Vec<MarketplaceActivity>,
+) -> (
+    impl QueryFragment<Pg> + diesel::query_builder::QueryId + Send,
+    Option<&'static str>,
+) {
+    use schema::marketplace_activities::dsl::*;
+
+    (
+        diesel::insert_into(schema::marketplace_activities::table)
+            .values(items_to_insert)
+            .on_conflict((transaction_version, event_index))
+            .do_nothing(),
+        None,
+    )
+}
+
+fn insert_current_listings_query(
+    items_to_insert: Vec<CurrentListing>,
+) -> (
+    impl QueryFragment<Pg> + diesel::query_builder::QueryId + Send,
+    Option<&'static str>,
+) {
+    use schema::current_listings::dsl::*;
+
+    (
+        diesel::insert_into(schema::current_listings::table)
+            .values(items_to_insert)
+            .on_conflict(listing_id)
+            .do_update()
+            .set((
+                marketplace.eq(excluded(marketplace)),
+                contract_address.eq(excluded(contract_address)),
+                token_data_id.eq(excluded(token_data_id)),
+                collection_id.eq(excluded(collection_id)),
+                seller.eq(excluded(seller)),
+                price.eq(excluded(price)),
+                is_active.eq(excluded(is_active)),
+                last_transaction_version.eq(excluded(last_transaction_version)),
+                last_transaction_timestamp.eq(excluded(last_transaction_timestamp)),
+                inserted_at.eq(excluded(inserted_at)),
+            )),
+        Some(" WHERE current_listings.last_transaction_version <= excluded.last_transaction_version "),
+    )
+}
+
+fn insert_current_bids_query(
+    items_to_insert: Vec<CurrentBid>,
+) -> (
+    impl QueryFragment<Pg> + diesel::query_builder::QueryId + Send,
+    Option<&'static str>,
+) {
+    use schema::current_bids::dsl::*;
+
+    (
+        diesel::insert_into(schema::current_bids::table)
+            .values(items_to_insert)
+            .on_conflict(offer_id)
+            .do_update()
+            .set((
+                marketplace.eq(excluded(marketplace)),
+                contract_address.eq(excluded(contract_address)),
+                token_data_id.eq(excluded(token_data_id)),
+                collection_id.eq(excluded(collection_id)),
+                buyer.eq(excluded(buyer)),
+                price.eq(excluded(price)),
+                token_amount.eq(excluded(token_amount)),
+                is_active.eq(excluded(is_active)),
+                last_transaction_version.eq(excluded(last_transaction_version)),
+                last_transaction_timestamp.eq(excluded(last_transaction_timestamp)),
+                inserted_at.eq(excluded(inserted_at)),
+            )),
+        Some(" WHERE current_bids.last_transaction_version <= excluded.last_transaction_version "),
+    )
+}
+
+fn insert_listing_invalidations_query(
+    items_to_insert: Vec<ListingInvalidation>,
+) -> (
+    impl QueryFragment<Pg> + diesel::query_builder::QueryId + Send,
+    Option<&'static str>,
+) {
+    use schema::listing_invalidations::dsl::*;
+
+    (
+        diesel::insert_into(schema::listing_invalidations::table)
+            .values(items_to_insert)
+            .on_conflict(token_data_id)
+            .do_update()
+            .set((
+                transaction_version.eq(excluded(transaction_version)),
+                transaction_timestamp.eq(excluded(transaction_timestamp)),
+            )),
+        Some(" WHERE listing_invalidations.transaction_version <= excluded.transaction_version "),
+    )
+}
+
+/// Deactivates the active listings of the given tokens that are older than the stored invalidation
+/// of their token, in one statement
+fn invalidate_listings_query(
+    token_data_ids: Vec<String>,
+) -> impl QueryFragment<Pg> + diesel::query_builder::QueryId + Send {
+    diesel::sql_query(
+        "UPDATE current_listings SET
+            is_active = FALSE,
+            last_transaction_version = i.transaction_version,
+            last_transaction_timestamp = i.transaction_timestamp
+        FROM listing_invalidations i
+        JOIN unnest($1) AS t(token_data_id) ON i.token_data_id = t.token_data_id
+        WHERE current_listings.token_data_id = i.token_data_id
+            AND current_listings.is_active
+            AND current_listings.last_transaction_version < i.transaction_version",
+    )
+    .bind::<Array<Text>, _>(token_data_ids)
+}
+
+fn delete_applied_invalidations_query(
+    name: &'static str,
+) -> impl QueryFragment<Pg> + diesel::query_builder::QueryId + Send {
+    diesel::sql_query(
+        "DELETE FROM listing_invalidations WHERE transaction_version <= (
+            SELECT last_success_version FROM processor_status WHERE processor = $1
+        )",
+    )
+    .bind::<Text, _>(name)
+}
+
+#[async_trait]
+impl ProcessorTrait for MarketplaceProcessor {
+    fn name(&self) -> &'static str {
+        ProcessorName::MarketplaceProcessor.into()
+    }
+
+    async fn process_transactions(
+        &self,
+        transactions: Vec<Transaction>,
+        start_version: u64,
+        end_version: u64,
+        _: Option<u64>,
+    ) -> anyhow::Result<ProcessingResult> {
+        let processing_start = std::time::Instant::now();
+        let last_transaction_timestamp = transactions.last().unwrap().timestamp.clone();
+
+        let mut marketplace_activities = vec![];
+        // Keyed by listing/offer id so only the latest state in the batch is written
+        let mut current_listings: AHashMap<String, CurrentListing> = AHashMap::new();
+        let mut current_bids: AHashMap<String, CurrentBid> = AHashMap::new();
+        let mut listing_invalidations: AHashMap<String, ListingInvalidation> = AHashMap::new();
+        for txn in &transactions {
+            let txn_version = txn.version as i64;
+            let user_txn = match txn.txn_data.as_ref() {
+                Some(TxnData::User(user_txn)) => user_txn,
+                Some(_) => continue,
+                None => {
+                    PROCESSOR_UNKNOWN_TYPE_COUNT
+                        .with_label_values(&["MarketplaceProcessor"])
+                        .inc();
+                    tracing::warn!(
+                        transaction_version = txn_version,
+                        "Transaction data doesn't exist"
+                    );
+                    continue;
+                },
+            };
+            let txn_timestamp = parse_timestamp(txn.timestamp.as_ref().unwrap(), txn_version);
+
+            let mut is_marketplace_txn = false;
+            let mut transferred_tokens = vec![];
+            for (index, event) in user_txn.events.iter().enumerate() {
+                let event_type = event.type_str.as_str();
+                for marketplace in &self.marketplaces {
+                    let decoded =
+                        match marketplace
+                            .decoder
+                            .decode(event_type, &event.data, txn_version)
+                        {
+                            Ok(Some(decoded)) => decoded,
+                            Ok(None) => continue,
+                            Err(e) => {
+                                // Still a marketplace event, so don't treat its transfers as external
+                                is_marketplace_txn = true;
+                                MARKETPLACE_DECODE_ERROR_COUNT
+                                    .with_label_values(&[
+                                        self.name(),
+                                        marketplace.name.as_str(),
+                                        event_type,
+                                    ])
+                                    .inc();
+                                error!(
+                                    transaction_version = txn_version,
+                                    marketplace = marketplace.name.as_str(),
+                                    error = ?e,
+                                    "Failed to decode marketplace event"
+                                );
+                                continue;
+                            },
+                        };
+                    is_marketplace_txn = true;
+                    marketplace_activities.push(MarketplaceActivity::from_event(
+                        &marketplace.name,
+                        &marketplace.contract_address,
+                        &decoded,
+                        txn_version,
+                        index as i64,
+                        txn_timestamp,
+                    ));
+                    if let Some(listing) = CurrentListing::from_event(
+                        &marketplace.name,
+                        &marketplace.contract_address,
+                        &decoded,
+                        txn_version,
+                        txn_timestamp,
+                    ) {
+                        current_listings.insert(listing.listing_id.clone(), listing);
+                    }
+                    if let Some(bid) = CurrentBid::from_event(
+                        &marketplace.name,
+                        &marketplace.contract_address,
+                        &decoded,
+                        txn_version,
+                        txn_timestamp,
+                    ) {
+                        current_bids.insert(bid.offer_id.clone(), bid);
+                    }
+                }
+
+                if let Some(V2TokenEvent::TransferEvent(transfer)) =
+                    V2TokenEvent::from_event(event_type, &event.data, txn_version).unwrap()
+                {
+                    transferred_tokens.push(transfer.get_object_address());
+                } else if let Some(TokenEvent::WithdrawTokenEvent(withdraw)) =
+                    TokenEvent::from_event(event_type, &event.data, txn_version).unwrap()
+                {
+                    transferred_tokens.push(withdraw.id.token_data_id.to_id());
+                }
+            }
+
+            if !is_marketplace_txn {
+                for token_data_id in transferred_tokens {
+                    listing_invalidations.insert(token_data_id.clone(), ListingInvalidation {
+                        token_data_id,
+                        transaction_version: txn_version,
+                        transaction_timestamp: txn_timestamp,
+                    });
+                }
+            }
+        }
+
+        // Sort by PK to avoid deadlocks between concurrent batches
+        let mut current_listings = current_listings.into_values().collect::<Vec<_>>();
+        current_listings.sort_by(|a, b| a.listing_id.cmp(&b.listing_id));
+        let mut current_bids = current_bids.into_values().collect::<Vec<_>>();
+        current_bids.sort_by(|a, b| a.offer_id.cmp(&b.offer_id));
+        let mut listing_invalidations = listing_invalidations.into_values().collect::<Vec<_>>();
+        listing_invalidations.sort_by(|a, b| a.token_data_id.cmp(&b.token_data_id));
+
+        let processing_duration_in_secs = processing_start.elapsed().as_secs_f64();
+        let db_insertion_start = std::time::Instant::now();
+
+        let tx_result = insert_to_db(
+            self.get_pool(),
+            self.name(),
+            start_version,
+            end_version,
+            &marketplace_activities,
+            &current_listings,
+            &current_bids,
+            &listing_invalidations,
+            &self.per_table_chunk_sizes,
+        )
+        .await;
+        let db_insertion_duration_in_secs = db_insertion_start.elapsed().as_secs_f64();
+        match tx_result {
+            Ok(_) => Ok(ProcessingResult::DefaultProcessingResult(
+                DefaultProcessingResult {
+                    start_version,
+                    end_version,
+                    processing_duration_in_secs,
+                    db_insertion_duration_in_secs,
+                    last_transaction_timestamp,
+                },
+            )),
+            Err(e) => {
+                error!(
+                    start_version = start_version,
+                    end_version = end_version,
+                    processor_name = self.name(),
+                    error = ?e,
+                    "[Parser] Error inserting transactions to db",
+                );
+                bail!(e)
+            },
+        }
+    }
+
+    fn connection_pool(&self) -> &ArcDbPool {
+        &self.connection_pool
+    }
+}
@@ -1,6 +1,7 @@
 use super::{DefaultProcessingResult, ProcessorName, ProcessorTrait};
 use crate::{
     db::common::models::account_models::account::Account,
+    gap_detectors::ProcessingResult,
     schema,
     utils::database::{execute_in_chunks, get_config_table_chunk_size, ArcDbPool},
 };
@@ -8,10 +9,15 @@ use ahash::AHashMap;
 use anyhow::bail;
 use aptos_protos::transaction::v1::Transaction;
 use async_trait::async_trait;
-use diesel::{pg::Pg, query_builder::QueryFragment};
+use diesel::{
+    dsl::sql,
+    pg::{upsert::excluded, Pg},
+    query_builder::QueryFragment,
+    sql_types::{BigInt, Nullable, Timestamp},
+    BoolExpressionMethods, ExpressionMethods,
+};
 use std::fmt::Debug;
 use tracing::error;
-use crate::gap_detectors::ProcessingResult;
 
 pub struct MercatoAccountProcessor {
     connection_pool: ArcDbPool,
@@ -56,10 +62,7 @@ async fn insert_to_db(
         conn.clone(),
         insert_account_query,
         accounts,
-        get_config_table_chunk_size::<Account>(
-            "accounts",
-            per_table_chunk_sizes,
-        ),
+        get_config_table_chunk_size::<Account>("accounts", per_table_chunk_sizes),
     )
     .await?;
     Ok(())
@@ -73,12 +76,32 @@ fn insert_account_query(
 ) {
     use schema::accounts::dsl::*;
 
+    // Only a batch that moves last_transaction_version forward is applied, so replayed batches
+    // aren't counted again. first_transaction_version stays the lowest version seen.
     (
         diesel::insert_into(schema::accounts::table)
             .values(item_to_insert)
             .on_conflict(account_address)
-            .do_nothing(),
-        None,
+            .do_update()
+            .set((
+                first_transaction_version.eq(sql::<Nullable<BigInt>>(
+                    "LEAST(accounts.first_transaction_version, excluded.first_transaction_version)",
+                )),
+                first_transaction_timestamp.eq(sql::<Nullable<Timestamp>>(
+                    "CASE WHEN accounts.first_transaction_version IS NULL \
+                    OR excluded.first_transaction_version < accounts.first_transaction_version \
+                    THEN excluded.first_transaction_timestamp \
+                    ELSE accounts.first_transaction_timestamp END",
+                )),
+                last_transaction_version.eq(excluded(last_transaction_version)),
+                last_transaction_timestamp.eq(excluded(last_transaction_timestamp)),
+                transaction_count.eq(transaction_count + excluded(transaction_count)),
+                has_signed.eq(has_signed.or(excluded(has_signed))),
+            )),
+        Some(
+            " WHERE accounts.last_transaction_version IS NULL \
+             OR accounts.last_transaction_version < excluded.last_transaction_version ",
+        ),
     )
 }
 
@@ -98,14 +121,21 @@ impl ProcessorTrait for MercatoAccountProcessor {
         let processing_start = std::time::Instant::now();
         let last_transaction_timestamp = transactions.last().unwrap().timestamp.clone();
 
-        let mut accounts = AHashMap::new();
+        let mut accounts: AHashMap<String, Account> = AHashMap::new();
 
         for txn in &transactions {
-            accounts.extend(Account::from_transaction(txn));
+            for (address, account) in Account::from_transaction(txn) {
+                match accounts.get_mut(&address) {
+                    Some(existing) => existing.merge(&account),
+                    None => {
+                        accounts.insert(address, account);
+                    },
+                }
+            }
         }
-        let accounts = accounts
-            .into_values()
-            .collect::<Vec<Account>>();
+        // Sort by PK to avoid deadlocks between concurrent batches
+        let mut accounts = accounts.into_values().collect::<Vec<Account>>();
+        accounts.sort_by(|a, b| a.account_address.cmp(&b.account_address));
 
         let processing_duration_in_secs = processing_start.elapsed().as_secs_f64();
         let db_insertion_start = std::time::Instant::now();
@@ -121,13 +151,15 @@ impl ProcessorTrait for MercatoAccountProcessor {
 
         let db_insertion_duration_in_secs = db_insertion_start.elapsed().as_secs_f64();
         match tx_result {
-            Ok(_) => Ok(ProcessingResult::DefaultProcessingResult(DefaultProcessingResult {
-                start_version,
-                end_version,
-                processing_duration_in_secs,
-                db_insertion_duration_in_secs,
-                last_transaction_timestamp,
-            })),
+            Ok(_) => Ok(ProcessingResult::DefaultProcessingResult(
+                DefaultProcessingResult {
+                    start_version,
+                    end_version,
+                    processing_duration_in_secs,
+                    db_insertion_duration_in_secs,
+                    last_transaction_timestamp,
+                },
+            )),
             Err(err) => {
                 error!(
                     start_version = start_version,
@@ -136,7 +168,13 @@ impl ProcessorTrait for MercatoAccountProcessor {
                     "[Parser] Error inserting transactions to db: {:?}",
                     err
                 );
-                bail!(format!("Error inserting accounts to db. Processor {}. Start {}. End {}. Error {:?}", self.name(), start_version, end_version, err))
+                bail!(format!(
+                    "Error inserting accounts to db. Processor {}. Start {}. End {}. Error {:?}",
+                    self.name(),
+                    start_version,
+                    end_version,
+                    err
+                ))
             },
         }
     }
@@ -1,80 +1,532 @@
 use super::{DefaultProcessingResult, ProcessorName, ProcessorTrait};
-use  crate::db::common::models::token_v2_models::v2_token_utils::{PropertyMapModel, TokenV2, TransferEvent, V2TokenEvent};
-use crate::utils::database::{execute_in_chunks, ArcDbPool};
 use crate::{
-    utils::util::standardize_address,
+    db::common::models::{
+        mercato_indexer_models::{
+            actions::{
+                Action, BURN_ACTION, BUY_ACTION, DELIST_ACTION, LIST_ACTION, MINT_ACTION,
+                TRANSFER_ACTION,
+            },
+            dead_letters::{MercatoIndexerDeadLetter, MercatoIndexerDeadLetterQuery},
+            nft_meta::{NftMeta, NftMetaOwner, NftMetaQuery},
+            token_activities::TokenActivity,
+        },
+        object_models::v2_object_utils::ObjectWithMetadata,
+        token_v2_models::v2_token_utils::{PropertyMapModel, TokenV2, V2TokenEvent},
+    },
+    gap_detectors::ProcessingResult,
+    schema,
+    utils::{
+        counters::{
+            DEAD_LETTER_BATCH_COUNT, DEAD_LETTER_REPLAYED_BATCH_COUNT,
+            UNKNOWN_COLLECTION_MINT_COUNT,
+        },
+        database::{
+            clean_data_for_db, execute_with_better_error, execute_with_better_error_conn,
+            get_config_table_chunk_size, ArcDbPool,
+        },
+        util::{
+            get_entry_function_from_user_request, parse_timestamp, standardize_address,
+            standardize_entry_function_id,
+        },
+    },
     IndexerGrpcProcessorConfig,
 };
-use ahash::AHashMap;
+use ahash::{AHashMap, AHashSet};
 use anyhow::bail;
 use aptos_protos::transaction::v1::{transaction::TxnData, write_set_change::Change, Transaction};
-use aptos_protos::util::timestamp::Timestamp;
 use async_trait::async_trait;
-use chrono::DateTime;
 use core::option::Option;
-use diesel::pg::Pg;
-use diesel::prelude::*;
-use diesel::query_builder::QueryFragment;
-use itertools::Itertools;
+use diesel::{
+    pg::Pg,
+    prelude::*,
+    query_builder::{AstPass, QueryFragment},
+    sql_types::{Array, BigInt},
+};
+use diesel_async::{scoped_futures::ScopedFutureExt, AsyncConnection, RunQueryDsl};
 use serde::{Deserialize, Serialize};
-use serde_json::Value;
+use sha2::{Digest, Sha256};
 use std::fmt::Debug;
+use tokio::sync::OnceCell;
 use tracing::error;
 use uuid::Uuid;
-use crate::gap_detectors::ProcessingResult;
-
-#[derive(Clone, Debug, Deserialize, Serialize)]
-pub struct IndexerNftMeta {
-    pub id: String,
-    pub name: String,
-    pub image: String,
-    pub token_id: String,
-    pub properties: Value,
-    pub minted: bool,
-    pub mint_tx: String,
-    pub owner: String,
-    pub sender: String,
-    pub owner_block_height: u64,
-    pub owner_tx_id: String,
-    pub owner_tx_version: u64,
-    pub owner_tx_time: Timestamp,
-    pub owner_tx_index: u64,
-}
 
 #[derive(Clone, Debug, Deserialize, Serialize, Queryable)]
 pub struct InsertResult {
     pub id: Option<String>,
 }
 
+/// Catalog ids written to `nft_meta` and `action` for an on-chain collection
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct CollectionMapping {
+    pub collection_id: Uuid,
+    pub smart_contract_id: Uuid,
+}
+
+/// Action recorded for token transfers made by a marketplace entry function
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MarketplaceAction {
+    List,
+    Delist,
+    Buy,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct MarketplaceFunction {
+    // Entry function id, e.g. 0x1234::marketplace::buy
+    pub entry_function: String,
+    pub action: MarketplaceAction,
+}
+
 #[derive(Clone, Debug, Deserialize, Serialize)]
 #[serde(deny_unknown_fields)]
 pub struct MercatoIndexerProcessorConfig {
+    // Catalog id of the chain
+    pub chain_id: Uuid,
+    // On-chain collection address to catalog ids. Activity of other collections is skipped
+    #[serde(default)]
+    pub collections: AHashMap<String, CollectionMapping>,
+    // Transfers made by these entry functions are recorded as listings, delistings or sales
+    // instead of plain transfers
+    #[serde(default)]
+    pub marketplace_functions: Vec<MarketplaceFunction>,
     #[serde(default = "IndexerGrpcProcessorConfig::default_query_retries")]
     pub query_retries: u32,
     #[serde(default = "IndexerGrpcProcessorConfig::default_query_retry_delay_ms")]
     pub query_retry_delay_ms: u64,
+    // When set, a batch that still fails after all retries is written to
+    // mercato_indexer_dead_letters and acknowledged instead of stopping the processor. Dead
+    // letters are replayed when the processor starts
+    #[serde(default)]
+    pub dead_letter_on_failure: bool,
 }
 
-const COLLECTION_ID: &str = "330f0d93-86ed-4a55-a18c-a4c7e4d5eaf2";
-const COLLECTION_CHAIN_ID: &str = "0x9a6f1b16323c428756b439553ab2a6a4cbdd46ade55d0da17f3a7c7d3e4c6ac8";
-const SMART_CONTRACT_ID: &str = "bd280fe5-f59f-405e-82d7-71e3ff2065cb"; /*"c568a492-de7e-44f2-9a6f-bcce6e7775fc" */ //"";
-const CHAIN_ID: &str = "f395c6c8-2d11-419f-856c-d28a8f1c0bca";
+/// `MercatoIndexerProcessorConfig::collections` keyed by standardized address, so config entries
+/// and chain data can use any form of an address
+pub struct CollectionMappings {
+    collections: AHashMap<String, CollectionMapping>,
+}
+
+impl CollectionMappings {
+    pub fn new(collections: &AHashMap<String, CollectionMapping>) -> Self {
+        Self {
+            collections: collections
+                .iter()
+                .map(|(address, mapping)| {
+                    (
+                        standardize_address(&address.to_lowercase()),
+                        mapping.clone(),
+                    )
+                })
+                .collect(),
+        }
+    }
+
+    /// Returns the catalog ids of a collection, None if the collection isn't tracked
+    pub fn get(&self, collection_address: &str) -> Option<&CollectionMapping> {
+        self.collections
+            .get(&standardize_address(&collection_address.to_lowercase()))
+    }
+
+    pub fn collection_ids(&self) -> Vec<Uuid> {
+        self.collections.values().map(|c| c.collection_id).collect()
+    }
+}
 
 pub struct MercatoIndexerProcessor {
     connection_pool: ArcDbPool,
+    config: MercatoIndexerProcessorConfig,
+    collections: CollectionMappings,
+    marketplace_functions: AHashMap<String, MarketplaceAction>,
     per_table_chunk_sizes: AHashMap<String, usize>,
+    dead_letters_replayed: OnceCell<()>,
 }
 
 impl MercatoIndexerProcessor {
     pub fn new(
         connection_pool: ArcDbPool,
+        config: MercatoIndexerProcessorConfig,
         per_table_chunk_sizes: AHashMap<String, usize>,
     ) -> Self {
+        let collections = CollectionMappings::new(&config.collections);
+        let marketplace_functions = config
+            .marketplace_functions
+            .iter()
+            .map(|f| (standardize_entry_function_id(&f.entry_function), f.action))
+            .collect();
         Self {
             connection_pool,
+            config,
+            collections,
+            marketplace_functions,
             per_table_chunk_sizes,
+            dead_letters_replayed: OnceCell::new(),
         }
     }
+
+    /// Collects new NFTs of tracked collections and every mint, transfer and burn of a
+    /// transaction. Transfers and burns don't say which collection the token belongs to, so
+    /// they're matched against `nft_meta` in `resolve_activities`. Tokens the transaction shows
+    /// to be in a tracked collection are added to `parkable_token_ids`.
+    fn parse_transaction(
+        &self,
+        txn: &Transaction,
+        nft_metas: &mut Vec<NftMeta>,
+        activities: &mut Vec<TokenActivity>,
+        parkable_token_ids: &mut AHashSet<String>,
+    ) {
+        let txn_version = txn.version as i64;
+        let user_txn = match txn.txn_data.as_ref() {
+            Some(TxnData::User(user_txn)) => user_txn,
+            Some(_) => return,
+            None => {
+                tracing::warn!(
+                    transaction_version = txn.version,
+                    "Transaction data doesn't exist"
+                );
+                return;
+            },
+        };
+        let transaction_info = txn.info.as_ref().expect("Transaction info doesn't exist!");
+        let user_request = user_txn
+            .request
+            .as_ref()
+            .expect("Getting user request failed.");
+        let marketplace_action = get_entry_function_from_user_request(user_request).and_then(|f| {
+            self.marketplace_functions
+                .get(&standardize_entry_function_id(&f))
+                .copied()
+        });
+
+        // Token resources written by this transaction, keyed by token address
+        let mut tokens: AHashMap<String, TokenV2> = AHashMap::new();
+        let mut property_maps: AHashMap<String, PropertyMapModel> = AHashMap::new();
+        let mut owners: AHashMap<String, String> = AHashMap::new();
+        for wsc in transaction_info.changes.iter() {
+            if let Change::WriteResource(wr) = wsc.change.as_ref().unwrap() {
+                let address = standardize_address(&wr.address);
+                if let Some(property_map) =
+                    PropertyMapModel::from_write_resource(wr, txn_version).unwrap()
+                {
+                    property_maps.insert(address.clone(), property_map);
+                }
+                if let Some(token) = TokenV2::from_write_resource(wr, txn_version).unwrap() {
+                    tokens.insert(address.clone(), token);
+                }
+                if let Some(object) =
+                    ObjectWithMetadata::from_write_resource(wr, txn_version).unwrap()
+                {
+                    owners.insert(address, object.object_core.get_owner_address());
+                }
+            }
+        }
+
+        let transaction_hash =
+            standardize_address(hex::encode(transaction_info.hash.as_slice()).as_str());
+        let block_height = txn.block_height as i64;
+        let block_time = parse_timestamp(txn.timestamp.as_ref().unwrap(), txn_version);
+        // tx_index counts every mint, transfer and burn event of the transaction, tracked or not,
+        // so it only depends on the transaction and keeps several actions of one transaction apart
+        let activity = |token_address: &str,
+                        action_type: &str,
+                        seller: Option<String>,
+                        buyer: Option<String>,
+                        new_owner: Option<String>,
+                        tx_index: i64| TokenActivity {
+            tx_id: transaction_hash.clone(),
+            tx_index,
+            token_id: remove_leading_zeros(token_address),
+            action_type: action_type.to_string(),
+            seller,
+            buyer,
+            new_owner,
+            block_height,
+            block_time,
+            nonce: txn_version,
+        };
+
+        let mut token_event_count = 0;
+        for event in user_txn.events.iter() {
+            let token_event =
+                V2TokenEvent::from_event(event.type_str.as_str(), &event.data, txn_version)
+                    .unwrap();
+            let event_index = token_event_count;
+            if matches!(
+                token_event,
+                Some(
+                    V2TokenEvent::Mint(_)
+                        | V2TokenEvent::MintEvent(_)
+                        | V2TokenEvent::Burn(_)
+                        | V2TokenEvent::BurnEvent(_)
+                        | V2TokenEvent::TransferEvent(_)
+                )
+            ) {
+                token_event_count += 1;
+            }
+            let (token_address, collection_address) = match token_event {
+                Some(V2TokenEvent::Mint(mint)) => (
+                    mint.get_token_address(),
+                    Some(standardize_address(&mint.collection)),
+                ),
+                Some(V2TokenEvent::MintEvent(mint)) => (mint.get_token_address(), None),
+                Some(V2TokenEvent::Burn(burn)) => {
+                    self.push_activity(
+                        activity(
+                            &burn.get_token_address(),
+                            BURN_ACTION,
+                            burn.get_previous_owner_address(),
+                            None,
+                            None,
+                            event_index,
+                        ),
+                        Some(burn.get_collection_address()),
+                        activities,
+                        parkable_token_ids,
+                    );
+                    continue;
+                },
+                Some(V2TokenEvent::BurnEvent(burn)) => {
+                    let token_address = burn.get_token_address();
+                    self.push_activity(
+                        activity(&token_address, BURN_ACTION, None, None, None, event_index),
+                        tokens
+                            .get(&token_address)
+                            .map(|token| token.get_collection_address()),
+                        activities,
+                        parkable_token_ids,
+                    );
+                    continue;
+                },
+                Some(V2TokenEvent::TransferEvent(transfer)) => {
+                    let from = transfer.get_from_address();
+                    let to = transfer.get_to_address();
+                    let (action_type, seller, buyer) = match marketplace_action {
+                        Some(MarketplaceAction::List) => (LIST_ACTION, Some(from), None),
+                        Some(MarketplaceAction::Delist) => (DELIST_ACTION, None, Some(to.clone())),
+                        Some(MarketplaceAction::Buy) => (BUY_ACTION, Some(from), Some(to.clone())),
+                        None => (TRANSFER_ACTION, Some(from), Some(to.clone())),
+                    };
+                    // The token resource is in the write set of any transaction that moves it
+                    let token_address = transfer.get_object_address();
+                    self.push_activity(
+                        activity(
+                            &token_address,
+                            action_type,
+                            seller,
+                            buyer,
+                            Some(to),
+                            event_index,
+                        ),
+                        tokens
+                            .get(&token_address)
+                            .map(|token| token.get_collection_address()),
+                        activities,
+                        parkable_token_ids,
+                    );
+                    continue;
+                },
+                _ => continue,
+            };
+
+            // Only mints get here. They carry everything needed for the nft_meta row
+            let collection_address = match collection_address {
+                Some(collection_address) => collection_address,
+                None => match tokens.get(&token_address) {
+                    Some(token) => token.get_collection_address(),
+                    None => continue,
+                },
+            };
+            let collection = match self.collections.get(&collection_address) {
+                Some(collection) => collection,
+                None => {
+                    UNKNOWN_COLLECTION_MINT_COUNT
+                        .with_label_values(&[self.name()])
+                        .inc();
+                    tracing::debug!(
+                        transaction_version = txn_version,
+                        collection = collection_address,
+                        "Skipping mint for unknown collection"
+                    );
+                    continue;
+                },
+            };
+            let token = match tokens.get(&token_address) {
+                Some(token) => token,
+                None => {
+                    tracing::warn!(
+                        transaction_version = txn_version,
+                        token_address = token_address,
+                        "No token data found in WriteResources"
+                    );
+                    continue;
+                },
+            };
+            let owner = owners
+                .get(&token_address)
+                .cloned()
+                .unwrap_or_else(|| standardize_address(&user_request.sender));
+
+            nft_metas.push(NftMeta {
+                id: nft_meta_id(&transaction_hash, event_index),
+                name: token.get_name_trunc(),
+                token_id: remove_leading_zeros(&token_address),
+                collection_id: collection.collection_id,
+                chain_id: self.config.chain_id,
+                smart_contract_id: collection.smart_contract_id,
+                properties: property_maps
+                    .get(&token_address)
+                    .map(|property_map| property_map.inner.clone())
+                    .unwrap_or_else(|| serde_json::json!({})),
+                mint_tx: transaction_hash.clone(),
+                owner: owner.clone(),
+                owner_block_height: block_height,
+                owner_tx_id: transaction_hash.clone(),
+            });
+            activities.push(activity(
+                &token_address,
+                MINT_ACTION,
+                None,
+                Some(owner),
+                None,
+                event_index,
+            ));
+        }
+    }
+
+    /// Keeps an activity unless the transaction shows its token is in a collection that isn't
+    /// tracked. Activities of tokens in tracked collections may be parked until the mint is written.
+    fn push_activity(
+        &self,
+        activity: TokenActivity,
+        collection_address: Option<String>,
+        activities: &mut Vec<TokenActivity>,
+        parkable_token_ids: &mut AHashSet<String>,
+    ) {
+        if let Some(collection_address) = collection_address {
+            if self.collections.get(&collection_address).is_none() {
+                return;
+            }
+            parkable_token_ids.insert(activity.token_id.clone());
+        }
+        activities.push(activity);
+    }
+
+    /// Inserts the batch, retrying the whole transaction up to `query_retries` times.
+    /// If every attempt fails the batch is dead lettered when enabled, otherwise the error is returned.
+    async fn persist_batch(
+        &self,
+        start_version: u64,
+        end_version: u64,
+        nft_metas: &[NftMeta],
+        activities: &[TokenActivity],
+        parkable_token_ids: &AHashSet<String>,
+    ) -> Result<(), diesel::result::Error> {
+        let collection_ids = self.collections.collection_ids();
+        let query_retries = self.config.query_retries.max(1);
+        let mut tried = 0;
+        let mut last_error = None;
+        while tried < query_retries {
+            tried += 1;
+            match insert_to_db(
+                self.connection_pool(),
+                self.name(),
+                start_version,
+                end_version,
+                &collection_ids,
+                nft_metas,
+                activities,
+                parkable_token_ids,
+                &self.per_table_chunk_sizes,
+                None,
+            )
+            .await
+            {
+                Ok(_) => return Ok(()),
+                Err(e) => {
+                    tracing::warn!(
+                        start_version = start_version,
+                        end_version = end_version,
+                        processor_name = self.name(),
+                        tried = tried,
+                        error = ?e,
+                        "[Parser] Error inserting batch to db",
+                    );
+                    last_error = Some(e);
+                    if tried < query_retries {
+                        tokio::time::sleep(std::time::Duration::from_millis(
+                            self.config.query_retry_delay_ms,
+                        ))
+                        .await;
+                    }
+                },
+            }
+        }
+        let e = last_error.unwrap();
+        if !self.config.dead_letter_on_failure {
+            return Err(e);
+        }
+
+        // Activities are stored unresolved, replaying them also restores the owners
+        let dead_letter = MercatoIndexerDeadLetter::new(
+            self.name(),
+            start_version,
+            end_version,
+            nft_metas,
+            activities,
+            &e,
+        );
+        insert_dead_letter(self.connection_pool(), dead_letter).await?;
+        DEAD_LETTER_BATCH_COUNT
+            .with_label_values(&[self.name()])
+            .inc();
+        error!(
+            start_version = start_version,
+            end_version = end_version,
+            processor_name = self.name(),
+            error = ?e,
+            "[Parser] Batch dead lettered after exhausting retries",
+        );
+        Ok(())
+    }
+
+    /// Writes the batches dead lettered by earlier runs, oldest first. Each dead letter is deleted
+    /// in the transaction that writes its rows. Replay stops at the first batch that fails again,
+    /// so transfers aren't resolved before the mints of an earlier dead letter are written. Dead
+    /// letters are behind the processor status, so their unmatched activities are never parked.
+    async fn replay_dead_letters(&self) -> anyhow::Result<()> {
+        let dead_letters = {
+            let mut conn = self.connection_pool().get().await?;
+            MercatoIndexerDeadLetterQuery::get_by_processor(&mut conn, self.name()).await?
+        };
+        let collection_ids = self.collections.collection_ids();
+        for dead_letter in dead_letters {
+            insert_to_db(
+                self.connection_pool(),
+                self.name(),
+                dead_letter.start_version as u64,
+                dead_letter.end_version as u64,
+                &collection_ids,
+                &dead_letter.nft_metas()?,
+                &dead_letter.activities()?,
+                &AHashSet::new(),
+                &self.per_table_chunk_sizes,
+                Some(dead_letter.id),
+            )
+            .await?;
+            DEAD_LETTER_REPLAYED_BATCH_COUNT
+                .with_label_values(&[self.name()])
+                .inc();
+            tracing::info!(
+                start_version = dead_letter.start_version,
+                end_version = dead_letter.end_version,
+                processor_name = self.name(),
+                "[Parser] Replayed dead lettered batch",
+            );
+        }
+        Ok(())
+    }
 }
 
 impl Debug for MercatoIndexerProcessor {
@@ -104,165 +556,346 @@ fn remove_leading_zeros(v: &str) -> String {
     }
 }
 
-fn wrap_quotes(v: &str) -> String {
-    format!("'{}'", v)
+/// Id of the `nft_meta` row of a mint, derived from the mint event so a replayed mint gets the same
+/// id. Rows written before ids were derived keep their random id, so ids are always read back
+/// from `nft_meta` before they're referenced.
+fn nft_meta_id(tx_id: &str, tx_index: i64) -> Uuid {
+    let hash = Sha256::digest(format!("{}:{}", tx_id, tx_index).as_bytes());
+    uuid::Builder::from_custom_bytes(hash[..16].try_into().unwrap()).into_uuid()
+}
+
+/// Advisory lock keys of the tokens of a batch, sorted so concurrent batches take shared locks in
+/// the same order and can't deadlock
+fn token_lock_keys<'a>(name: &str, token_ids: impl Iterator<Item = &'a str>) -> Vec<i64> {
+    let mut keys: Vec<i64> = token_ids
+        .map(|token_id| {
+            let hash = Sha256::digest(format!("{}:{}", name, token_id).as_bytes());
+            i64::from_be_bytes(hash[..8].try_into().unwrap())
+        })
+        .collect();
+    keys.sort_unstable();
+    keys.dedup();
+    keys
 }
 
-fn wrap_properties(v: &Value) -> String {
-    format!("'{}'::jsonb", v)
+/// token_id -> (nft_meta_id, collection_id, smart_contract_id) of indexed NFTs
+type KnownTokens = AHashMap<String, (Uuid, Uuid, Uuid)>;
+
+/// Turns activities into `action` rows plus the latest owner of every NFT that moved. Activities
+/// of tokens that aren't in `known_tokens` are returned as they are.
+fn resolve_activities(
+    known_tokens: &KnownTokens,
+    mut activities: Vec<TokenActivity>,
+) -> (Vec<Action>, Vec<NftMetaOwner>, Vec<TokenActivity>) {
+    // Pending activities come from later batches, so sort to let the last owner win
+    activities.sort_by_key(|a| (a.nonce, a.tx_index));
+    let mut actions = vec![];
+    let mut owners: AHashMap<Uuid, NftMetaOwner> = AHashMap::new();
+    let mut unresolved = vec![];
+    for activity in activities {
+        let (nft_meta_id, collection_id, smart_contract_id) =
+            match known_tokens.get(&activity.token_id) {
+                Some(ids) => *ids,
+                None => {
+                    unresolved.push(activity);
+                    continue;
+                },
+            };
+        if let Some(new_owner) = activity.new_owner {
+            owners.insert(nft_meta_id, NftMetaOwner {
+                id: nft_meta_id,
+                owner: new_owner,
+                owner_block_height: activity.block_height,
+                owner_tx_id: activity.tx_id.clone(),
+            });
+        }
+        actions.push(Action {
+            tx_id: activity.tx_id,
+            tx_index: activity.tx_index,
+            action_type: activity.action_type,
+            seller: activity.seller,
+            buyer: activity.buyer,
+            block_height: activity.block_height,
+            block_time: activity.block_time,
+            nonce: activity.nonce,
+            collection_id,
+            nft_meta_id,
+            smart_contract_id,
+        });
+    }
+    (actions, owners.into_values().collect(), unresolved)
 }
 
+/// Column list shared by `action` and `recent_action`
+const ACTION_COLUMNS: &str = r#""tx_id", "tx_index", "action", "seller", "buyer", "block_height", "block_time", "nonce", "collection_id", "nft_meta_id", "smart_contract_id""#;
+
+/// Writes all rows of a batch in a single transaction, so a batch is either fully persisted or not
+/// at all. Activities are matched to `nft_meta` inside the transaction: transfers and burns of
+/// tokens that may still be minted by an earlier, uncommitted batch are parked in
+/// `mercato_indexer_pending_actions` until that mint is written, if they're in `parkable_token_ids`.
+/// When replaying a dead letter, the dead letter is deleted in the same transaction.
 async fn insert_to_db(
     conn_pool: &ArcDbPool,
     name: &'static str,
     start_version: u64,
     end_version: u64,
-    nfts: &[IndexerNftMeta],
-    _per_table_chunk_sizes: &AHashMap<String, usize>,
+    collection_ids: &[Uuid],
+    nft_metas: &[NftMeta],
+    activities: &[TokenActivity],
+    parkable_token_ids: &AHashSet<String>,
+    per_table_chunk_sizes: &AHashMap<String, usize>,
+    dead_letter_id: Option<i64>,
 ) -> Result<(), diesel::result::Error> {
-    if !nfts.is_empty() {
-        tracing::trace!(
-            name = name,
-            start_version = start_version,
-            end_version = end_version,
-            "Inserting into indexer DB",
-        );
+    if nft_metas.is_empty() && activities.is_empty() && dead_letter_id.is_none() {
+        return Ok(());
+    }
+    tracing::trace!(
+        name = name,
+        start_version = start_version,
+        end_version = end_version,
+        "Inserting into indexer DB",
+    );
 
-        let _ = execute_in_chunks(conn_pool.clone(), insert_nft_meta_query, nfts, 200).await;
+    let nft_meta_chunk_size =
+        get_config_table_chunk_size::<NftMeta>("nft_meta", per_table_chunk_sizes);
+    let action_chunk_size = get_config_table_chunk_size::<Action>("action", per_table_chunk_sizes);
+    let pending_chunk_size = get_config_table_chunk_size::<TokenActivity>(
+        "mercato_indexer_pending_actions",
+        per_table_chunk_sizes,
+    );
+    let conn = &mut conn_pool.get().await.map_err(|e| {
+        tracing::warn!("Error getting connection from pool: {:?}", e);
+        diesel::result::Error::DatabaseError(
+            diesel::result::DatabaseErrorKind::UnableToSendCommand,
+            Box::new(e.to_string()),
+        )
+    })?;
+    conn.transaction::<_, diesel::result::Error, _>(|conn| {
+        async move {
+            // Batches are written concurrently. Locking every token of the batch until commit means a
+            // batch sees the mints and pending activities of the batches that touched the same
+            // tokens before it, while batches of other tokens go ahead
+            let token_ids = nft_metas
+                .iter()
+                .map(|n| n.token_id.as_str())
+                .chain(activities.iter().map(|a| a.token_id.as_str()));
+            diesel::sql_query("SELECT pg_advisory_xact_lock(key) FROM unnest($1) AS key")
+                .bind::<Array<BigInt>, _>(token_lock_keys(name, token_ids))
+                .execute(conn)
+                .await?;
+            for chunk in nft_metas.chunks(nft_meta_chunk_size) {
+                let (query, additional_where_clause) = insert_nft_meta_query(chunk.to_vec());
+                execute_with_better_error_conn(conn, query, additional_where_clause).await?;
+            }
 
-        let _ = execute_in_chunks(conn_pool.clone(), insert_actions_query, nfts, 500).await;
-    }
+            // Every version up to the checkpoint is committed, so pending activities at or below
+            // it belong to tokens that are never going to be minted in a tracked collection
+            let checkpoint = schema::processor_status::table
+                .select(schema::processor_status::last_success_version)
+                .filter(schema::processor_status::processor.eq(name))
+                .first::<i64>(conn)
+                .await
+                .optional()?;
+            if let Some(checkpoint) = checkpoint {
+                execute_with_better_error_conn(
+                    conn,
+                    delete_expired_pending_activities_query(checkpoint),
+                    None,
+                )
+                .await?;
+            }
 
+            let minted_token_ids: Vec<String> =
+                nft_metas.iter().map(|n| n.token_id.clone()).collect();
+            let mut activities = activities.to_vec();
+            if !minted_token_ids.is_empty() {
+                activities.extend(TokenActivity::get_by_token_ids(conn, &minted_token_ids).await?);
+            }
+            let token_ids: Vec<String> = activities
+                .iter()
+                .map(|a| a.token_id.clone())
+                .collect::<AHashSet<_>>()
+                .into_iter()
+                .collect();
+            let known_tokens: KnownTokens =
+                NftMetaQuery::get_by_token_ids(conn, &token_ids, collection_ids)
+                    .await?
+                    .into_iter()
+                    .map(|n| (n.token_id, (n.id, n.collection_id, n.smart_contract_id)))
+                    .collect();
+            let (actions, owners, unresolved) = resolve_activities(&known_tokens, activities);
+
+            // The mint of an unmatched token can only still arrive if an earlier batch is in flight,
+            // and is only written if the token is in a tracked collection
+            let earlier_batch_in_flight =
+                checkpoint.map_or(true, |checkpoint| start_version as i64 > checkpoint + 1);
+            if earlier_batch_in_flight {
+                let unresolved: Vec<TokenActivity> = unresolved
+                    .into_iter()
+                    .filter(|a| parkable_token_ids.contains(&a.token_id))
+                    .collect();
+                for chunk in unresolved.chunks(pending_chunk_size) {
+                    let (query, additional_where_clause) =
+                        insert_pending_activities_query(chunk.to_vec());
+                    execute_with_better_error_conn(conn, query, additional_where_clause).await?;
+                }
+            }
+            if !minted_token_ids.is_empty() {
+                execute_with_better_error_conn(
+                    conn,
+                    delete_resolved_pending_activities_query(minted_token_ids),
+                    None,
+                )
+                .await?;
+            }
+
+            for chunk in actions.chunks(action_chunk_size) {
+                let (query, additional_where_clause) = insert_actions_query(chunk.to_vec());
+                execute_with_better_error_conn(conn, query, additional_where_clause).await?;
+            }
+            for owner in owners {
+                execute_with_better_error_conn(conn, update_nft_meta_owner_query(owner), None)
+                    .await?;
+            }
+            if let Some(dead_letter_id) = dead_letter_id {
+                execute_with_better_error_conn(
+                    conn,
+                    delete_dead_letter_query(dead_letter_id),
+                    None,
+                )
+                .await?;
+            }
+            Ok(())
+        }
+        .scope_boxed()
+    })
+    .await
+}
+
+async fn insert_dead_letter(
+    conn_pool: &ArcDbPool,
+    dead_letter: MercatoIndexerDeadLetter,
+) -> Result<(), diesel::result::Error> {
+    execute_with_better_error(
+        conn_pool.clone(),
+        diesel::insert_into(schema::mercato_indexer_dead_letters::table).values(dead_letter),
+        None,
+    )
+    .await?;
     Ok(())
 }
 
+fn delete_dead_letter_query(
+    dead_letter_id: i64,
+) -> impl QueryFragment<Pg> + diesel::query_builder::QueryId + Send {
+    use schema::mercato_indexer_dead_letters::dsl::*;
+
+    diesel::delete(mercato_indexer_dead_letters.filter(id.eq(dead_letter_id)))
+}
+
 fn insert_nft_meta_query(
-    items_to_insert: Vec<IndexerNftMeta>,
+    items_to_insert: Vec<NftMeta>,
 ) -> (
     impl QueryFragment<Pg> + diesel::query_builder::QueryId + Send,
     Option<&'static str>,
 ) {
-    let values_sql = items_to_insert
-        .iter()
-        .filter_map(|n| {
-            format!(
-                "({})",
-                vec![
-                    wrap_quotes(&n.id),
-                    wrap_quotes(&n.name),
-                    wrap_quotes(&remove_leading_zeros(&n.token_id)),
-                    wrap_quotes(COLLECTION_ID),
-                    wrap_quotes(CHAIN_ID),
-                    wrap_quotes(SMART_CONTRACT_ID),
-                    wrap_properties(&n.properties),
-                    wrap_quotes(&n.mint_tx),
-                    wrap_quotes(&n.owner),
-                    n.owner_block_height.to_string(),
-                    wrap_quotes(&n.owner_tx_id)
-                ]
-                .join(",")
-            )
-            .into()
-        })
-        .join(",\n");
-    let query = format!(
-        r#"
-        INSERT INTO nft_meta (
-            id, name, token_id, collection_id, chain_id, smart_contract_id, properties, mint_tx,  owner, owner_block_height, owner_tx_id
-        ) VALUES
-            {}
-        ON CONFLICT (collection_id, token_id) DO NOTHING;"#,
-        values_sql
-    );
+    use schema::nft_meta::dsl::*;
+
+    (
+        diesel::insert_into(schema::nft_meta::table)
+            .values(items_to_insert)
+            .on_conflict((collection_id, token_id))
+            .do_nothing(),
+        None,
+    )
+}
 
-    (diesel::sql_query(query), None)
+/// Inserts into `action` and copies the inserted rows into `recent_action` in one statement:
+/// WITH new_actions AS (INSERT INTO action ... RETURNING *) INSERT INTO recent_action ... SELECT ... FROM new_actions
+#[derive(QueryId)]
+pub struct InsertActionsWithRecentQuery<T> {
+    insert_actions: T,
+}
+
+impl<T> QueryFragment<Pg> for InsertActionsWithRecentQuery<T>
+where
+    T: QueryFragment<Pg>,
+{
+    fn walk_ast<'b>(&'b self, mut out: AstPass<'_, 'b, Pg>) -> QueryResult<()> {
+        out.push_sql("WITH new_actions AS (");
+        self.insert_actions.walk_ast(out.reborrow())?;
+        out.push_sql(" RETURNING *) INSERT INTO recent_action (");
+        out.push_sql(ACTION_COLUMNS);
+        out.push_sql(") SELECT ");
+        out.push_sql(ACTION_COLUMNS);
+        out.push_sql(" FROM new_actions");
+        Ok(())
+    }
 }
 
 fn insert_actions_query(
-    nfts: Vec<IndexerNftMeta>,
+    items_to_insert: Vec<Action>,
 ) -> (
     impl QueryFragment<Pg> + diesel::query_builder::QueryId + Send,
     Option<&'static str>,
 ) {
-    let fields = vec![
-        "tx_id",
-        "tx_index",
-        "action",
-        "seller",
-        "buyer",
-        "block_height",
-        "block_time",
-        "nonce",
-        "collection_id",
-        "nft_meta_id",
-        "smart_contract_id"
-    ];
-    let mut action_values: Vec<String> = Vec::new();
-    for nft in nfts {
-        action_values.push(
-            [
-                wrap_quotes(&nft.owner_tx_id),
-                (nft.owner_tx_index * 10).to_string(),
-                wrap_quotes("mint"),
-                "NULL".to_string(),
-                wrap_quotes(&nft.owner),
-                nft.owner_block_height.to_string(),
-                wrap_quotes(
-                    &DateTime::from_timestamp(
-                        nft.owner_tx_time.seconds,
-                        nft.owner_tx_time.nanos as u32,
-                    ).unwrap().to_string(),
-                ),
-                nft.owner_tx_version.to_string(),
-                wrap_quotes(COLLECTION_ID),
-                wrap_quotes(&nft.id),
-                wrap_quotes(SMART_CONTRACT_ID)
-            ]
-            .join(", "),
-        );
-        action_values.push(
-            [
-                wrap_quotes(&nft.owner_tx_id),
-                (nft.owner_tx_index * 10 + 1).to_string(),
-                wrap_quotes("transfer"),
-                wrap_quotes(&nft.sender),
-                wrap_quotes(&nft.owner),
-                nft.owner_block_height.to_string(),
-                wrap_quotes(
-                    &DateTime::from_timestamp(
-                        nft.owner_tx_time.seconds,
-                        nft.owner_tx_time.nanos as u32,
-                    ).unwrap()
-                    .to_string(),
-                ),
-                nft.owner_tx_version.to_string(),
-                wrap_quotes(COLLECTION_ID),
-                wrap_quotes(&nft.id),
-                wrap_quotes(SMART_CONTRACT_ID),
-            ]
-            .join(", "),
-        );
-    }
-    let sql_field_names = fields.iter().map(|v| format!("\"{}\"", v)).join(", ");
-    let sql_values = action_values.iter().map(|v| format!("({})", v)).join(",\n");
-
-    let query = format!(
-        r#"
-            WITH new_actions AS (
-                INSERT INTO "action"({sql_field_names})
-                VALUES
-                    {sql_values}
-                RETURNING *
-            )
-            INSERT INTO "recent_action"({sql_field_names})
-            SELECT {sql_field_names} FROM new_actions
-            ;"#,
-        sql_field_names = sql_field_names,
-        sql_values = sql_values
-    );
+    // Actions already written by an earlier run over the same versions are skipped. RETURNING
+    // only yields inserted rows, so they aren't copied to recent_action twice either
+    (
+        InsertActionsWithRecentQuery {
+            insert_actions: diesel::insert_into(schema::action::table)
+                .values(items_to_insert)
+                .on_conflict_do_nothing(),
+        },
+        None,
+    )
+}
+
+fn insert_pending_activities_query(
+    items_to_insert: Vec<TokenActivity>,
+) -> (
+    impl QueryFragment<Pg> + diesel::query_builder::QueryId + Send,
+    Option<&'static str>,
+) {
+    (
+        diesel::insert_into(schema::mercato_indexer_pending_actions::table)
+            .values(items_to_insert)
+            .on_conflict_do_nothing(),
+        None,
+    )
+}
 
+fn delete_resolved_pending_activities_query(
+    token_ids: Vec<String>,
+) -> impl QueryFragment<Pg> + diesel::query_builder::QueryId + Send {
+    use schema::mercato_indexer_pending_actions::dsl::*;
 
-    (diesel::sql_query(query), None)
+    diesel::delete(mercato_indexer_pending_actions.filter(token_id.eq_any(token_ids)))
+}
+
+fn delete_expired_pending_activities_query(
+    checkpoint: i64,
+) -> impl QueryFragment<Pg> + diesel::query_builder::QueryId + Send {
+    use schema::mercato_indexer_pending_actions::dsl::*;
+
+    diesel::delete(mercato_indexer_pending_actions.filter(nonce.le(checkpoint)))
+}
+
+fn update_nft_meta_owner_query(
+    item: NftMetaOwner,
+) -> impl QueryFragment<Pg> + diesel::query_builder::QueryId + Send {
+    use schema::nft_meta::dsl::*;
+
+    diesel::update(
+        nft_meta
+            .filter(id.eq(item.id))
+            .filter(owner_block_height.le(item.owner_block_height)),
+    )
+    .set((
+        owner.eq(item.owner),
+        owner_block_height.eq(item.owner_block_height),
+        owner_tx_id.eq(item.owner_tx_id),
+    ))
 }
 
 #[async_trait]
@@ -284,129 +917,66 @@ impl ProcessorTrait for MercatoIndexerProcessor {
             end_version = end_version,
             "Processing new transactions",
         );
-        let mut nfts = Vec::new();
-
-        for txn in transactions {
-            let txn_data = match txn.txn_data.as_ref() {
-                Some(data) => data,
-                None => {
-                    tracing::warn!(
-                        transaction_version = txn.version,
-                        "Transaction data doesn't exist"
+        // Batches wait for the replay, so dead letters are written before any newer batch
+        self.dead_letters_replayed
+            .get_or_init(|| async {
+                if let Err(e) = self.replay_dead_letters().await {
+                    error!(
+                        processor_name = self.name(),
+                        error = ?e,
+                        "[Parser] Error replaying dead letters, the rest are kept for the next start",
                     );
-                    continue;
-                },
-            };
-
-            let txn_version = txn.version as i64;
-            let transaction_info = txn.info.as_ref().expect("Transaction info doesn't exist!");
-            let mut token_id = "".to_string();
-            let mut collection_id = "".to_string();
-            let mut sender = "".to_string();
-            let mut owner = "".to_string();
-            let mut token_property_map: Option<PropertyMapModel> = None;
-            let mut token_data: Option<TokenV2> = None;
-            let mut txn_index: Option<u64> = None;
-
-            if let TxnData::User(user_txn) = txn_data {
-                let user_request = user_txn
-                    .request
-                    .as_ref()
-                    .expect("Getting user request failed.");
-                txn_index = Some(user_request.sequence_number);
-
-
-                for (_index, event) in user_txn.events.iter().enumerate() {
-                    if let Some(V2TokenEvent::Mint(mint_event)) =
-                        V2TokenEvent::from_event(event.type_str.as_str(), &event.data, txn_version).unwrap()
-                    {
-                        token_id= mint_event.get_token_address();
-                        collection_id = mint_event.collection;
-                        continue;
-                    }
-                    if let Some(transfer_event) =
-                        TransferEvent::from_event(event, txn_version).unwrap()
-                    {
-                        sender = transfer_event.get_from_address();
-                        owner = transfer_event.get_to_address();
-                        continue;
-                    };
-                }
-            }
-
-            if token_id.is_empty() || collection_id.is_empty() || sender.is_empty() || owner.is_empty() || collection_id != COLLECTION_CHAIN_ID {
-                continue;
-            };
-
-            for wsc in transaction_info.changes.iter() {
-                match wsc.change.as_ref().unwrap() {
-                    Change::WriteResource(wr) => {
-                        if let Some(property_map) =
-                            PropertyMapModel::from_write_resource(wr, txn_version).unwrap()
-                        {
-                            token_property_map = Some(property_map);
-                        }
-                        if let Some(token) =
-                            TokenV2::from_write_resource(wr, txn_version).unwrap()
-                        {
-                            token_data = Some(token);
-                        };
-                    },
-                    _default => (),
                 }
-            }
+            })
+            .await;
 
-            if token_data.is_none() {
-                tracing::warn!("No token data found in WriteResources");
-                continue;
-            };
+        let processing_start = std::time::Instant::now();
+        let last_transaction_timestamp = transactions.last().unwrap().timestamp.clone();
+        let mut nft_metas = vec![];
+        let mut activities = vec![];
+        let mut parkable_token_ids = AHashSet::new();
+        for txn in &transactions {
+            self.parse_transaction(
+                txn,
+                &mut nft_metas,
+                &mut activities,
+                &mut parkable_token_ids,
+            );
+        }
 
-            let token = token_data.as_ref().unwrap();
-            let transaction_hash =
-                standardize_address(hex::encode(transaction_info.hash.as_slice()).as_str());
+        let nft_metas = clean_data_for_db(nft_metas, true);
+        let activities = clean_data_for_db(activities, true);
 
-            nfts.push(IndexerNftMeta {
-                id: Uuid::new_v4().to_string(),
-                name: token.get_name_trunc(),
-                image: token.get_uri_trunc(),
-                token_id,
-                properties: token_property_map.unwrap().inner,
-                minted: true,
-                mint_tx: transaction_hash.clone(),
-                owner,
-                sender,
-                owner_block_height: txn.block_height,
-                owner_tx_id: transaction_hash,
-                owner_tx_version: txn.version,
-                owner_tx_time: txn.timestamp.unwrap(),
-                owner_tx_index: txn_index.unwrap_or_default(),
-            });
-        }
+        let processing_duration_in_secs = processing_start.elapsed().as_secs_f64();
+        let db_insertion_start = std::time::Instant::now();
 
-        let tx_result = insert_to_db(
-            self.connection_pool(),
-            self.name(),
-            start_version,
-            end_version,
-            &nfts,
-            &self.per_table_chunk_sizes,
-        )
-        .await;
-        match tx_result {
-            Ok(_) => Ok(ProcessingResult::DefaultProcessingResult(DefaultProcessingResult {
+        let tx_result = self
+            .persist_batch(
                 start_version,
                 end_version,
-                processing_duration_in_secs: 0.0,
-                db_insertion_duration_in_secs: 0.0,
-                last_transaction_timestamp: None,
-            })),
+                &nft_metas,
+                &activities,
+                &parkable_token_ids,
+            )
+            .await;
+        let db_insertion_duration_in_secs = db_insertion_start.elapsed().as_secs_f64();
+        match tx_result {
+            Ok(_) => Ok(ProcessingResult::DefaultProcessingResult(
+                DefaultProcessingResult {
+                    start_version,
+                    end_version,
+                    processing_duration_in_secs,
+                    db_insertion_duration_in_secs,
+                    last_transaction_timestamp,
+                },
+            )),
             Err(e) => {
                 error!(
                     start_version = start_version,
                     end_version = end_version,
                     processor_name = self.name(),
                     error = ?e,
-                    "[Parser] Error inserting nft_meta to db",
+                    "[Parser] Error inserting transactions to db",
                 );
                 bail!(e)
             },
@@ -417,3 +987,142 @@ impl ProcessorTrait for MercatoIndexerProcessor {
         &self.connection_pool
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use chrono::NaiveDateTime;
+
+    #[test]
+    fn test_collection_mappings() {
+        let config: MercatoIndexerProcessorConfig = serde_json::from_value(serde_json::json!({
+            "chain_id": "00000000-0000-0000-0000-000000000001",
+            "collections": {
+                "0x00ABC": {
+                    "collection_id": "00000000-0000-0000-0000-00000000000a",
+                    "smart_contract_id": "00000000-0000-0000-0000-0000000000a0",
+                },
+                "0x0000000000000000000000000000000000000000000000000000000000000def": {
+                    "collection_id": "00000000-0000-0000-0000-00000000000d",
+                    "smart_contract_id": "00000000-0000-0000-0000-0000000000d0",
+                },
+            },
+        }))
+        .unwrap();
+        let collections = CollectionMappings::new(&config.collections);
+
+        let abc = collections
+            .get("0x0000000000000000000000000000000000000000000000000000000000000abc")
+            .unwrap();
+        assert_eq!(abc.collection_id.as_u128(), 0xA);
+        assert_eq!(abc.smart_contract_id.as_u128(), 0xA0);
+        let def = collections.get("0xDEF").unwrap();
+        assert_eq!(def.collection_id.as_u128(), 0xD);
+        assert_eq!(def.smart_contract_id.as_u128(), 0xD0);
+
+        // Activity of collections that aren't mapped is skipped
+        assert!(collections.get("0xabcd").is_none());
+        assert!(CollectionMappings::new(&AHashMap::new())
+            .get("0xabc")
+            .is_none());
+
+        let mut collection_ids = collections
+            .collection_ids()
+            .iter()
+            .map(Uuid::as_u128)
+            .collect::<Vec<_>>();
+        collection_ids.sort();
+        assert_eq!(collection_ids, vec![0xA, 0xD]);
+    }
+
+    fn test_activity(token_id: &str, nonce: i64, new_owner: &str) -> TokenActivity {
+        TokenActivity {
+            tx_id: format!("0x{}", nonce),
+            tx_index: 0,
+            token_id: token_id.to_string(),
+            action_type: TRANSFER_ACTION.to_string(),
+            seller: None,
+            buyer: Some(new_owner.to_string()),
+            new_owner: Some(new_owner.to_string()),
+            block_height: nonce,
+            block_time: NaiveDateTime::default(),
+            nonce,
+        }
+    }
+
+    #[test]
+    fn test_nft_meta_id() {
+        assert_eq!(nft_meta_id("0xabc", 1), nft_meta_id("0xabc", 1));
+        assert_ne!(nft_meta_id("0xabc", 1), nft_meta_id("0xabc", 2));
+        assert_ne!(nft_meta_id("0xabc", 1), nft_meta_id("0xabd", 1));
+    }
+
+    #[test]
+    fn test_token_lock_keys() {
+        let keys = token_lock_keys("p", ["0x2", "0x1", "0x2"].into_iter());
+        assert_eq!(keys.len(), 2);
+        assert!(keys[0] < keys[1]);
+        assert_eq!(keys, token_lock_keys("p", ["0x1", "0x2"].into_iter()));
+        assert_ne!(keys, token_lock_keys("q", ["0x1", "0x2"].into_iter()));
+    }
+
+    #[test]
+    fn test_resolve_activities() {
+        let nft_meta_id = Uuid::from_u128(1);
+        let known_tokens: KnownTokens = [(
+            "0x1".to_string(),
+            (nft_meta_id, Uuid::from_u128(2), Uuid::from_u128(3)),
+        )]
+        .into_iter()
+        .collect();
+
+        // Pending activities are appended after the batch but may be newer
+        let (actions, owners, unresolved) = resolve_activities(&known_tokens, vec![
+            test_activity("0x1", 20, "0xb"),
+            test_activity("0x2", 15, "0xc"),
+            test_activity("0x1", 10, "0xa"),
+        ]);
+        assert_eq!(actions.iter().map(|a| a.nonce).collect::<Vec<_>>(), vec![
+            10, 20
+        ]);
+        assert!(actions.iter().all(|a| a.nft_meta_id == nft_meta_id
+            && a.collection_id.as_u128() == 2
+            && a.smart_contract_id.as_u128() == 3));
+        assert_eq!(owners.len(), 1);
+        assert_eq!(owners[0].owner, "0xb");
+        assert_eq!(owners[0].owner_block_height, 20);
+        assert_eq!(unresolved.len(), 1);
+        assert_eq!(unresolved[0].token_id, "0x2");
+    }
+
+    #[test]
+    fn test_dead_letter_round_trip() {
+        let activities = vec![test_activity("0x1", 10, "0xa")];
+        let dead_letter = MercatoIndexerDeadLetter::new(
+            "mercato_indexer_processor",
+            10,
+            20,
+            &[],
+            &activities,
+            &diesel::result::Error::NotFound,
+        );
+        let stored = MercatoIndexerDeadLetterQuery {
+            id: 1,
+            processor: dead_letter.processor,
+            start_version: dead_letter.start_version,
+            end_version: dead_letter.end_version,
+            nft_meta: dead_letter.nft_meta,
+            actions: dead_letter.actions,
+            error: dead_letter.error,
+            inserted_at: NaiveDateTime::default(),
+        };
+
+        // Replay writes the batch exactly as it was dead lettered
+        assert!(stored.nft_metas().unwrap().is_empty());
+        let replayed = stored.activities().unwrap();
+        assert_eq!(replayed.len(), 1);
+        assert_eq!(replayed[0].token_id, "0x1");
+        assert_eq!(replayed[0].nonce, 10);
+        assert_eq!(replayed[0].new_owner.as_deref(), Some("0xa"));
+    }
+}
@@ -1,52 +1,359 @@
 use super::{
-    events_processor::EventsProcessor, /*mercato_account_processor::MercatoAccountProcessor, */user_transaction_processor::UserTransactionProcessor, ProcessorName, ProcessorTrait
+    events_processor::EventsProcessor, mercato_account_processor::MercatoAccountProcessor,
+    user_transaction_processor::UserTransactionProcessor, DefaultProcessingResult, ProcessorName,
+    ProcessorTrait,
 };
 use crate::{
-    db::common::models::default_models::{block_metadata_transactions::BlockMetadataTransactionModel, transactions::TransactionModel},
+    db::common::models::default_models::{
+        block_metadata_transactions::BlockMetadataTransactionModel,
+        move_resource_projections::{CompiledResourceProjection, ResourceProjection},
+        move_resources::MoveResource,
+        transactions::TransactionModel,
+        write_set_changes::WriteSetChangeDetail,
+    },
+    gap_detectors::ProcessingResult,
     schema,
-    utils::database::{execute_in_chunks, get_config_table_chunk_size, ArcDbPool},
-    utils::util::get_entry_function_from_user_request,
+    utils::{
+        counters::{ENTRY_FUNCTION_RULE_MATCHED_COUNT, PROCESSOR_UNKNOWN_TYPE_COUNT},
+        database::{execute_in_chunks, get_config_table_chunk_size, ArcDbPool},
+        pattern::{glob_match, type_matches},
+        util::{
+            get_entry_function_from_user_request, standardize_address, standardize_type_addresses,
+        },
+    },
     worker::TableFlags,
-    db::common::models::default_models::move_resources::MoveResource,
-    db::common::models::default_models::write_set_changes::WriteSetChangeDetail,
 };
 use ahash::AHashMap;
 use anyhow::bail;
-use aptos_protos::transaction::v1::Transaction;
+use aptos_protos::transaction::v1::{transaction::TxnData, Transaction};
 use async_trait::async_trait;
 use diesel::{
     pg::{upsert::excluded, Pg},
     query_builder::QueryFragment,
     ExpressionMethods,
 };
+use serde::{Deserialize, Serialize};
 use std::fmt::Debug;
-use aptos_protos::transaction::v1::transaction::TxnData;
-use tracing::error;
 use tokio::join;
-use crate::utils::counters::PROCESSOR_UNKNOWN_TYPE_COUNT;
-use super::DefaultProcessingResult;
-use crate::gap_detectors::ProcessingResult;
+use tracing::error;
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EntryFunctionRuleAction {
+    Include,
+    Exclude,
+}
+
+/// Matches user transactions by entry function. `address`, `module` and `function` are
+/// optional and accept `*` wildcards (e.g. `function: "play"` or `module: "dex_*"`); a
+/// part that is not set matches anything.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct EntryFunctionRule {
+    // Used as the metrics label, defaults to the position of the rule in the list
+    pub name: Option<String>,
+    pub action: EntryFunctionRuleAction,
+    pub address: Option<String>,
+    pub module: Option<String>,
+    pub function: Option<String>,
+}
+
+/// Selects resources to index by type. `type` accepts `*` wildcards anywhere, including inside
+/// type arguments (e.g. `0x1::coin::CoinStore<*>`). Omitted trailing parts match anything, so
+/// `0xcafe` matches every resource declared at that address, and a pattern without type
+/// arguments matches the type with any type arguments.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct ResourceTypeRule {
+    #[serde(rename = "type")]
+    pub type_pattern: String,
+    // Write matching resources to move_resources
+    #[serde(default = "ResourceTypeRule::default_store_move_resource")]
+    pub store_move_resource: bool,
+    // Also write selected fields of the resource data to a table of their own
+    pub projection: Option<ResourceProjection>,
+}
+
+impl ResourceTypeRule {
+    pub const fn default_store_move_resource() -> bool {
+        true
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct MercatoProcessorConfig {
+    // Evaluated in order, the first matching rule decides. Unmatched transactions are kept.
+    #[serde(default = "MercatoProcessorConfig::default_entry_function_rules")]
+    pub entry_function_rules: Vec<EntryFunctionRule>,
+    // Evaluated in order, the first matching rule decides. Unmatched resources are skipped.
+    #[serde(default = "MercatoProcessorConfig::default_resource_types")]
+    pub resource_types: Vec<ResourceTypeRule>,
+}
+
+impl MercatoProcessorConfig {
+    /// The entry functions this processor always dropped before the rules were configurable
+    pub fn default_entry_function_rules() -> Vec<EntryFunctionRule> {
+        let exclude = |name: &str, address: &str, module: Option<&str>, function: Option<&str>| {
+            EntryFunctionRule {
+                name: Some(name.to_string()),
+                action: EntryFunctionRuleAction::Exclude,
+                address: Some(address.to_string()),
+                module: module.map(str::to_string),
+                function: function.map(str::to_string),
+            }
+        };
+        vec![
+            exclude(
+                "dice_play",
+                "0x7de3fea83cd5ca0e1def27c3f3803af619882db51f34abf30dd04ad12ee6af31",
+                None,
+                Some("play"),
+            ),
+            exclude(
+                "router",
+                "0x3c1d4a86594d681ff7e5d5a233965daeabdc6a15fe5672ceeda5260038857183",
+                None,
+                None,
+            ),
+            exclude(
+                "game",
+                "0x664f1da7f6256b26a7808e0e5b02e747c4c6450e92b602740a2a5514bba91e52",
+                Some("game"),
+                None,
+            ),
+            exclude(
+                "esports_game_tracker",
+                "0x2387f5f16330dbb0236b1776a0d86c7a4901daaa25cd61ecb33709e025d3172f",
+                Some("esports_game_tracker"),
+                None,
+            ),
+            exclude(
+                "dex_admin",
+                "0x50ead22afd6ffd9769e3b3d6e0e64a2a350d68e8b102c4e72e33d0b8cfdfdb06",
+                Some("admin_apis"),
+                None,
+            ),
+            exclude(
+                "dex_accounts",
+                "0x50ead22afd6ffd9769e3b3d6e0e64a2a350d68e8b102c4e72e33d0b8cfdfdb06",
+                Some("dex_accounts_entry"),
+                None,
+            ),
+            exclude(
+                "photon_campaign",
+                "0x87eaa1f3f6e80882f9535344f2ef5bb5efde9ecd25fbe6b36a0f290e199a9d04",
+                Some("PhotonRouterModule"),
+                Some("participate_in_campaign_router"),
+            ),
+            exclude(
+                "coin_store_migration",
+                "0x1",
+                Some("coin"),
+                Some("migrate_coin_store_to_fungible_store"),
+            ),
+        ]
+    }
+
+    pub fn default_resource_types() -> Vec<ResourceTypeRule> {
+        vec![ResourceTypeRule {
+            type_pattern: "0x4::royalty::Royalty".to_string(),
+            store_move_resource: ResourceTypeRule::default_store_move_resource(),
+            projection: None,
+        }]
+    }
+}
+
+impl Default for MercatoProcessorConfig {
+    fn default() -> Self {
+        Self {
+            entry_function_rules: Self::default_entry_function_rules(),
+            resource_types: Self::default_resource_types(),
+        }
+    }
+}
+
+struct CompiledEntryFunctionRule {
+    name: String,
+    action: EntryFunctionRuleAction,
+    // Patterns, with plain addresses standardized
+    address: Option<String>,
+    module: Option<String>,
+    function: Option<String>,
+}
+
+/// Compiled form of `MercatoProcessorConfig::entry_function_rules`
+pub struct EntryFunctionRules {
+    rules: Vec<CompiledEntryFunctionRule>,
+}
+
+impl EntryFunctionRules {
+    pub fn new(rules: &[EntryFunctionRule]) -> Self {
+        let rules = rules
+            .iter()
+            .enumerate()
+            .map(|(index, rule)| CompiledEntryFunctionRule {
+                name: rule
+                    .name
+                    .clone()
+                    .unwrap_or_else(|| format!("rule_{}", index)),
+                action: rule.action,
+                address: rule.address.as_deref().map(|address| {
+                    // Wildcard patterns are matched as written, plain addresses are normalized
+                    if address.contains('*') {
+                        address.to_lowercase()
+                    } else {
+                        standardize_address(&address.to_lowercase())
+                    }
+                }),
+                module: rule.module.clone(),
+                function: rule.function.clone(),
+            })
+            .collect();
+        Self { rules }
+    }
+
+    /// Returns the first rule matching the entry function id, if any
+    fn find(&self, entry_function_id_str: &str) -> Option<&CompiledEntryFunctionRule> {
+        let mut parts = entry_function_id_str.splitn(3, "::");
+        let (address, module, function) = match (parts.next(), parts.next(), parts.next()) {
+            (Some(address), Some(module), Some(function)) => (
+                standardize_address(&address.to_lowercase()),
+                module,
+                function,
+            ),
+            _ => return None,
+        };
+        self.rules.iter().find(|rule| {
+            rule.address
+                .as_ref()
+                .map_or(true, |pattern| glob_match(pattern, &address))
+                && rule
+                    .module
+                    .as_ref()
+                    .map_or(true, |pattern| glob_match(pattern, module))
+                && rule
+                    .function
+                    .as_ref()
+                    .map_or(true, |pattern| glob_match(pattern, function))
+        })
+    }
+
+    /// Returns true if the entry function should be indexed, and counts the rule that decided it
+    pub fn include(&self, processor_name: &str, entry_function_id_str: &str) -> bool {
+        match self.find(entry_function_id_str) {
+            Some(rule) => {
+                ENTRY_FUNCTION_RULE_MATCHED_COUNT
+                    .with_label_values(&[processor_name, rule.name.as_str(), match rule.action {
+                        EntryFunctionRuleAction::Include => "include",
+                        EntryFunctionRuleAction::Exclude => "exclude",
+                    }])
+                    .inc();
+                rule.action == EntryFunctionRuleAction::Include
+            },
+            None => true,
+        }
+    }
+}
+
+struct CompiledResourceTypeRule {
+    // Type pattern with standardized addresses and every part of the name
+    pattern: String,
+    store_move_resource: bool,
+    // Index into ResourceTypeRules::projections
+    projection: Option<usize>,
+}
+
+/// Compiled form of `MercatoProcessorConfig::resource_types`
+pub struct ResourceTypeRules {
+    rules: Vec<CompiledResourceTypeRule>,
+    projections: Vec<CompiledResourceProjection>,
+}
+
+impl ResourceTypeRules {
+    pub fn new(rules: &[ResourceTypeRule]) -> anyhow::Result<Self> {
+        let mut compiled_rules = vec![];
+        let mut projections: Vec<CompiledResourceProjection> = vec![];
+        for rule in rules {
+            let projection = match &rule.projection {
+                Some(projection) => {
+                    if projections.iter().any(|p| p.table() == projection.table) {
+                        bail!(
+                            "Projection table {} is used more than once",
+                            projection.table
+                        );
+                    }
+                    projections.push(CompiledResourceProjection::new(projection)?);
+                    Some(projections.len() - 1)
+                },
+                None => None,
+            };
+            compiled_rules.push(CompiledResourceTypeRule {
+                pattern: complete_type_pattern(&rule.type_pattern)?,
+                store_move_resource: rule.store_move_resource,
+                projection,
+            });
+        }
+        Ok(Self {
+            rules: compiled_rules,
+            projections,
+        })
+    }
+
+    /// Returns the first rule matching the resource type, if any
+    fn find(&self, type_str: &str) -> Option<&CompiledResourceTypeRule> {
+        let type_str = standardize_type_addresses(type_str);
+        self.rules
+            .iter()
+            .find(|rule| type_matches(&rule.pattern, &type_str))
+    }
+}
+
+/// Standardizes the addresses of the pattern and fills omitted trailing parts with `*`, so that
+/// `0xcafe` becomes `0x...cafe::*::*`
+fn complete_type_pattern(pattern: &str) -> anyhow::Result<String> {
+    let pattern = standardize_type_addresses(pattern.trim());
+    let (outer, type_args) = match pattern.find('<') {
+        Some(index) => (&pattern[..index], &pattern[index..]),
+        None => (pattern.as_str(), ""),
+    };
+    let mut parts = outer.split("::").collect::<Vec<_>>();
+    if parts.len() > 3 || parts.iter().any(|part| part.is_empty()) {
+        bail!("Invalid resource type pattern {:?}", pattern);
+    }
+    parts.resize(3, "*");
+    Ok(format!("{}{}", parts.join("::"), type_args))
+}
 
-static INDEXED_RESOURCE_TYPES: &'static [&str] = &["0x4::royalty::Royalty"];
 pub struct MercatoProcessor {
     connection_pool: ArcDbPool,
     per_table_chunk_sizes: AHashMap<String, usize>,
+    entry_function_rules: EntryFunctionRules,
+    resource_type_rules: ResourceTypeRules,
     events_processor: EventsProcessor,
     user_transaction_processor: UserTransactionProcessor,
-    // account_processor: MercatoAccountProcessor,
+    account_processor: MercatoAccountProcessor,
 }
 
 impl MercatoProcessor {
-    pub fn new(connection_pool: ArcDbPool, per_table_chunk_sizes: AHashMap<String, usize>,  deprecated_tables: TableFlags) -> Self {
+    pub fn new(
+        connection_pool: ArcDbPool,
+        config: MercatoProcessorConfig,
+        per_table_chunk_sizes: AHashMap<String, usize>,
+        deprecated_tables: TableFlags,
+    ) -> Self {
         let events_processor_connection_pool = connection_pool.clone();
         let user_transaction_processor_connection_pool = connection_pool.clone();
-        //let account_processor_pool = connection_pool.clone();
+        let account_processor_pool = connection_pool.clone();
         let events_processor_per_table_chunk_sizes = per_table_chunk_sizes.clone();
         let user_transaction_processor_per_table_chunk_sizes = per_table_chunk_sizes.clone();
-        //let account_processor_per_table_chunk_sizes = per_table_chunk_sizes.clone();
+        let account_processor_per_table_chunk_sizes = per_table_chunk_sizes.clone();
         Self {
             connection_pool,
             per_table_chunk_sizes,
+            entry_function_rules: EntryFunctionRules::new(&config.entry_function_rules),
+            resource_type_rules: ResourceTypeRules::new(&config.resource_types)
+                .unwrap_or_else(|e| panic!("Invalid resource_types config: {:#}", e)),
             events_processor: EventsProcessor::new(
                 events_processor_connection_pool,
                 events_processor_per_table_chunk_sizes,
@@ -56,10 +363,10 @@ impl MercatoProcessor {
                 user_transaction_processor_per_table_chunk_sizes,
                 deprecated_tables,
             ),
-            // account_processor: MercatoAccountProcessor::new(
-            //     account_processor_pool,
-            //     account_processor_per_table_chunk_sizes,
-            // ),
+            account_processor: MercatoAccountProcessor::new(
+                account_processor_pool,
+                account_processor_per_table_chunk_sizes,
+            ),
         }
     }
 }
@@ -82,6 +389,7 @@ async fn insert_to_db(
     end_version: u64,
     txns: &[TransactionModel],
     move_resources: &[MoveResource],
+    projected_resources: &[(&CompiledResourceProjection, Vec<&MoveResource>)],
     per_table_chunk_sizes: &AHashMap<String, usize>,
 ) -> Result<(), diesel::result::Error> {
     tracing::trace!(
@@ -105,14 +413,18 @@ async fn insert_to_db(
         get_config_table_chunk_size::<MoveResource>("move_resources", per_table_chunk_sizes),
     );
 
-    let (txns_res,  mr_res) =
-        join!(txns_res, mr_res);
+    let projections_res = futures::future::try_join_all(
+        projected_resources
+            .iter()
+            .map(|(projection, resources)| projection.insert(conn.clone(), resources)),
+    );
+
+    let (txns_res, mr_res, projections_res) = join!(txns_res, mr_res, projections_res);
 
-    for res in [
-        txns_res, mr_res
-    ] {
+    for res in [txns_res, mr_res] {
         res?;
     }
+    projections_res?;
 
     Ok(())
 }
@@ -140,7 +452,8 @@ async fn insert_block_metadata_to_db(
             "block_metadata_transactions",
             per_table_chunk_sizes,
         ),
-    ).await?;
+    )
+    .await?;
 
     Ok(())
 }
@@ -236,14 +549,9 @@ impl ProcessorTrait for MercatoProcessor {
                     .as_ref()
                     .and_then(|req| get_entry_function_from_user_request(req))
                     .unwrap_or_default();
-                if !(entry_function_id_str.starts_with("0x7de3fea83cd5ca0e1def27c3f3803af619882db51f34abf30dd04ad12ee6af31::") && entry_function_id_str.ends_with("::play")) &&
-                    !entry_function_id_str.starts_with("0x3c1d4a86594d681ff7e5d5a233965daeabdc6a15fe5672ceeda5260038857183::") &&
-                    !entry_function_id_str.starts_with("0x664f1da7f6256b26a7808e0e5b02e747c4c6450e92b602740a2a5514bba91e52::game::") &&
-                    !entry_function_id_str.starts_with("0x2387f5f16330dbb0236b1776a0d86c7a4901daaa25cd61ecb33709e025d3172f::esports_game_tracker::") &&
-                    !entry_function_id_str.starts_with("0x50ead22afd6ffd9769e3b3d6e0e64a2a350d68e8b102c4e72e33d0b8cfdfdb06::admin_apis::") &&
-                    !entry_function_id_str.starts_with("0x50ead22afd6ffd9769e3b3d6e0e64a2a350d68e8b102c4e72e33d0b8cfdfdb06::dex_accounts_entry::") &&
-                    !entry_function_id_str.eq("0x87eaa1f3f6e80882f9535344f2ef5bb5efde9ecd25fbe6b36a0f290e199a9d04::PhotonRouterModule::participate_in_campaign_router") &&
-                    !entry_function_id_str.eq("0x1::coin::migrate_coin_store_to_fungible_store")
+                if self
+                    .entry_function_rules
+                    .include(self.name(), &entry_function_id_str)
                 {
                     filtered_transactions.push(txn.clone());
                 }
@@ -261,18 +569,21 @@ impl ProcessorTrait for MercatoProcessor {
         );
 
         if filtered_transactions.len() == 0 {
-            return Ok(ProcessingResult::DefaultProcessingResult(DefaultProcessingResult {
-                start_version,
-                end_version,
-                processing_duration_in_secs: 0.0,
-                db_insertion_duration_in_secs: 0.0,
-                last_transaction_timestamp: None,
-            }));
+            return Ok(ProcessingResult::DefaultProcessingResult(
+                DefaultProcessingResult {
+                    start_version,
+                    end_version,
+                    processing_duration_in_secs: 0.0,
+                    db_insertion_duration_in_secs: 0.0,
+                    last_transaction_timestamp: None,
+                },
+            ));
         }
 
         let processing_start = std::time::Instant::now();
         let last_transaction_timestamp = transactions.last().unwrap().timestamp.clone();
-        let (txns, block_metadata_txns, _, wsc_details) = TransactionModel::from_transactions(&filtered_transactions);
+        let (txns, block_metadata_txns, _, wsc_details) =
+            TransactionModel::from_transactions(&filtered_transactions);
         let processing_duration_in_secs = processing_start.elapsed().as_secs_f64();
         let db_insertion_start = std::time::Instant::now();
 
@@ -282,14 +593,22 @@ impl ProcessorTrait for MercatoProcessor {
         }
 
         let mut move_resources = vec![];
-        for detail in wsc_details {
-            match detail {
-                WriteSetChangeDetail::Resource(resource) => {
-                    if INDEXED_RESOURCE_TYPES.contains(&resource.type_.as_str()) {
+        let mut projected_resources = self
+            .resource_type_rules
+            .projections
+            .iter()
+            .map(|projection| (projection, vec![]))
+            .collect::<Vec<(&CompiledResourceProjection, Vec<&MoveResource>)>>();
+        for detail in &wsc_details {
+            if let WriteSetChangeDetail::Resource(resource) = detail {
+                if let Some(rule) = self.resource_type_rules.find(&resource.type_) {
+                    if rule.store_move_resource {
                         move_resources.push(resource.clone());
                     }
-                },
-                _ => ()
+                    if let Some(index) = rule.projection {
+                        projected_resources[index].1.push(resource);
+                    }
+                }
             }
         }
 
@@ -300,19 +619,22 @@ impl ProcessorTrait for MercatoProcessor {
             end_version,
             &txns,
             &move_resources,
+            &projected_resources,
             &self.per_table_chunk_sizes,
         )
-            .await;
+        .await;
 
         let db_insertion_duration_in_secs = db_insertion_start.elapsed().as_secs_f64();
         let result = match tx_result {
-            Ok(_) => Ok(ProcessingResult::DefaultProcessingResult(DefaultProcessingResult {
-                start_version,
-                end_version,
-                processing_duration_in_secs,
-                db_insertion_duration_in_secs,
-                last_transaction_timestamp,
-            })),
+            Ok(_) => Ok(ProcessingResult::DefaultProcessingResult(
+                DefaultProcessingResult {
+                    start_version,
+                    end_version,
+                    processing_duration_in_secs,
+                    db_insertion_duration_in_secs,
+                    last_transaction_timestamp,
+                },
+            )),
             Err(e) => {
                 error!(
                     start_version = start_version,
@@ -331,7 +653,12 @@ impl ProcessorTrait for MercatoProcessor {
             "Processing events",
         );
         self.events_processor
-            .process_transactions(filtered_transactions.clone(), start_version, end_version, None)
+            .process_transactions(
+                filtered_transactions.clone(),
+                start_version,
+                end_version,
+                None,
+            )
             .await?;
 
         tracing::trace!(
@@ -341,18 +668,23 @@ impl ProcessorTrait for MercatoProcessor {
             "Processing user transactions",
         );
         self.user_transaction_processor
-            .process_transactions(filtered_transactions.clone(), start_version, end_version, None)
+            .process_transactions(
+                filtered_transactions.clone(),
+                start_version,
+                end_version,
+                None,
+            )
             .await?;
 
-        // tracing::trace!(
-        //     name = self.name(),
-        //     start_version = start_version,
-        //     end_version = end_version,
-        //     "Processing accounts",
-        // );
-        // self.account_processor
-        //     .process_transactions(filtered_transactions, start_version, end_version, None)
-        //     .await?;
+        tracing::trace!(
+            name = self.name(),
+            start_version = start_version,
+            end_version = end_version,
+            "Processing accounts",
+        );
+        self.account_processor
+            .process_transactions(filtered_transactions, start_version, end_version, None)
+            .await?;
 
         insert_block_metadata_to_db(
             self.get_pool(),
@@ -361,7 +693,8 @@ impl ProcessorTrait for MercatoProcessor {
             end_version,
             &block_metadata_transactions,
             &self.per_table_chunk_sizes,
-        ).await?;
+        )
+        .await?;
         tracing::info!(
             name = self.name(),
             start_version = start_version,
@@ -375,3 +708,122 @@ impl ProcessorTrait for MercatoProcessor {
         &self.connection_pool
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_entry_function_rules() {
+        let config: MercatoProcessorConfig = serde_json::from_value(serde_json::json!({
+            "entry_function_rules": [
+                {
+                    "name": "keep_coin_transfer",
+                    "action": "include",
+                    "address": "0x1",
+                    "module": "coin",
+                    "function": "transfer",
+                },
+                {
+                    "name": "games",
+                    "action": "exclude",
+                    "address": "0x7de3fea83cd5ca0e1def27c3f3803af619882db51f34abf30dd04ad12ee6af31",
+                    "function": "play",
+                },
+                {
+                    "action": "exclude",
+                    "address": "0x1",
+                    "module": "coin*",
+                },
+            ]
+        }))
+        .unwrap();
+        let rules = EntryFunctionRules::new(&config.entry_function_rules);
+
+        assert!(rules.include("test", "0x1::coin::transfer"));
+        assert!(!rules.include("test", "0x1::coin::migrate_coin_store_to_fungible_store"));
+        assert!(!rules.include("test", "0x1::coins::transfer"));
+        assert!(rules.include("test", "0x1::aptos_account::transfer"));
+        assert!(!rules.include(
+            "test",
+            "0x7de3fea83cd5ca0e1def27c3f3803af619882db51f34abf30dd04ad12ee6af31::dice::play"
+        ));
+        assert!(rules.include(
+            "test",
+            "0x7de3fea83cd5ca0e1def27c3f3803af619882db51f34abf30dd04ad12ee6af31::dice::player"
+        ));
+        assert_eq!(
+            rules.find("0x1::coin::burn").map(|rule| rule.name.as_str()),
+            Some("rule_2")
+        );
+        assert!(rules.include("test", ""));
+    }
+
+    #[test]
+    fn test_default_entry_function_rules() {
+        let config: MercatoProcessorConfig = serde_json::from_value(serde_json::json!({})).unwrap();
+        let rules = EntryFunctionRules::new(&config.entry_function_rules);
+
+        assert!(!rules.include(
+            "test",
+            "0x7de3fea83cd5ca0e1def27c3f3803af619882db51f34abf30dd04ad12ee6af31::dice::play"
+        ));
+        assert!(!rules.include(
+            "test",
+            "0x3c1d4a86594d681ff7e5d5a233965daeabdc6a15fe5672ceeda5260038857183::router::swap"
+        ));
+        assert!(!rules.include(
+            "test",
+            "0x87eaa1f3f6e80882f9535344f2ef5bb5efde9ecd25fbe6b36a0f290e199a9d04::PhotonRouterModule::participate_in_campaign_router"
+        ));
+        assert!(!rules.include("test", "0x1::coin::migrate_coin_store_to_fungible_store"));
+        assert!(rules.include("test", "0x1::coin::transfer"));
+        assert!(rules.include(
+            "test",
+            "0x664f1da7f6256b26a7808e0e5b02e747c4c6450e92b602740a2a5514bba91e52::lobby::join"
+        ));
+
+        // Configured rules replace the defaults
+        let config: MercatoProcessorConfig =
+            serde_json::from_value(serde_json::json!({ "entry_function_rules": [] })).unwrap();
+        let rules = EntryFunctionRules::new(&config.entry_function_rules);
+        assert!(rules.include("test", "0x1::coin::migrate_coin_store_to_fungible_store"));
+    }
+
+    #[test]
+    fn test_resource_type_rules() {
+        let config: MercatoProcessorConfig = serde_json::from_value(serde_json::json!({
+            "resource_types": [
+                {"type": "0x1::coin::CoinStore<0x1::aptos_coin::*>"},
+                {"type": "0x4::royalty::Royalty", "store_move_resource": false},
+                {"type": "0xCAFE"},
+                {"type": "0x1::object::ObjectCore"},
+            ]
+        }))
+        .unwrap();
+        let rules = ResourceTypeRules::new(&config.resource_types).unwrap();
+
+        assert!(rules
+            .find("0x1::coin::CoinStore<0x1::aptos_coin::AptosCoin>")
+            .is_some());
+        assert!(rules
+            .find("0x1::coin::CoinStore<0xcafe::token::Token>")
+            .is_none());
+        assert!(!rules
+            .find("0x0000000000000000000000000000000000000000000000000000000000000004::royalty::Royalty")
+            .unwrap()
+            .store_move_resource);
+        assert!(rules
+            .find("0xcafe::vault::Vault<0x1::aptos_coin::AptosCoin>")
+            .is_some());
+        assert!(rules.find("0xcafe1::vault::Vault").is_none());
+        assert!(rules.find("0x1::object::ObjectCore").is_some());
+        assert!(rules.find("0x1::object::ObjectGroup").is_none());
+        assert!(ResourceTypeRules::new(&[ResourceTypeRule {
+            type_pattern: "0x1::a::b::c".to_string(),
+            store_move_resource: true,
+            projection: None,
+        }])
+        .is_err());
+    }
+}
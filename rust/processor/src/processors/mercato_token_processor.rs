@@ -2,6 +2,9 @@ use super::{DefaultProcessingResult, ProcessorName, ProcessorTrait};
 use crate::{
     db::common::models::token_models::{
         collection_datas::CurrentCollectionData,
+        nft_holdings::{NftHolding, NftHoldingLedger},
+        nft_points::NftPoints,
+        nft_points_rules::NftPointsRulesConfig,
         token_datas::CurrentTokenData,
         token_ownerships::CurrentTokenOwnership,
         tokens::{
@@ -10,10 +13,16 @@ use crate::{
         },
     },
     schema,
-    utils::database::{execute_in_chunks, get_config_table_chunk_size, ArcDbPool},
+    utils::{
+        database::{
+            execute_in_chunks, execute_with_better_error_conn, get_config_table_chunk_size,
+            ArcDbPool,
+        },
+        util::parse_timestamp,
+    },
     IndexerGrpcProcessorConfig,
 };
-use ahash::AHashMap;
+use ahash::{AHashMap, AHashSet};
 use anyhow::bail;
 use aptos_protos::transaction::v1::Transaction;
 use async_trait::async_trait;
@@ -22,6 +31,7 @@ use diesel::{
     query_builder::QueryFragment,
     ExpressionMethods,
 };
+use diesel_async::{scoped_futures::ScopedFutureExt, AsyncConnection};
 use serde::{Deserialize, Serialize};
 use std::fmt::Debug;
 use tracing::error;
@@ -30,7 +40,11 @@ use crate::gap_detectors::ProcessingResult;
 #[derive(Clone, Debug, Deserialize, Serialize)]
 #[serde(deny_unknown_fields)]
 pub struct MercatoTokenProcessorConfig {
+    // Deprecated, same as a single rule calling this entry function. Ignored when
+    // nft_points_rules is set
     pub nft_points_contract: Option<String>,
+    #[serde(default)]
+    pub nft_points_rules: Option<NftPointsRulesConfig>,
     #[serde(default = "IndexerGrpcProcessorConfig::default_query_retries")]
     pub query_retries: u32,
     #[serde(default = "IndexerGrpcProcessorConfig::default_query_retry_delay_ms")]
@@ -40,6 +54,7 @@ pub struct MercatoTokenProcessorConfig {
 pub struct MercatoTokenProcessor {
     connection_pool: ArcDbPool,
     config: MercatoTokenProcessorConfig,
+    nft_points_rules: Option<NftPointsRulesConfig>,
     per_table_chunk_sizes: AHashMap<String, usize>,
 }
 
@@ -49,9 +64,20 @@ impl MercatoTokenProcessor {
         config: MercatoTokenProcessorConfig,
         per_table_chunk_sizes: AHashMap<String, usize>,
     ) -> Self {
+        let nft_points_rules = config
+            .nft_points_rules
+            .clone()
+            .or_else(|| {
+                config
+                    .nft_points_contract
+                    .as_deref()
+                    .map(NftPointsRulesConfig::from_legacy_contract)
+            })
+            .map(|rules| rules.standardized());
         Self {
             connection_pool,
             config,
+            nft_points_rules,
             per_table_chunk_sizes,
         }
     }
@@ -73,10 +99,16 @@ async fn insert_to_db(
     name: &'static str,
     start_version: u64,
     end_version: u64,
-    (current_token_ownerships, current_token_datas, current_collection_datas): (
+    (current_token_ownerships, current_token_datas, current_collection_datas, nft_points): (
         &[CurrentTokenOwnership],
         &[CurrentTokenData],
         &[CurrentCollectionData],
+        &[NftPoints],
+    ),
+    (nft_points_rules, holding_ownerships, last_transaction): (
+        Option<&NftPointsRulesConfig>,
+        &[CurrentTokenOwnership],
+        (i64, chrono::NaiveDateTime),
     ),
     per_table_chunk_sizes: &AHashMap<String, usize>,
 ) -> Result<(), diesel::result::Error> {
@@ -115,17 +147,94 @@ async fn insert_to_db(
         ),
     );
 
-    let (cto_res, ctd_res, ccd_res) =
-        tokio::join!(cto, ctd, ccd);
+    let np = execute_in_chunks(
+        conn.clone(),
+        insert_nft_points_query,
+        nft_points,
+        get_config_table_chunk_size::<NftPoints>("nft_points", per_table_chunk_sizes),
+    );
+
+    let nh = insert_nft_holdings(
+        conn.clone(),
+        nft_points_rules,
+        holding_ownerships,
+        last_transaction,
+        per_table_chunk_sizes,
+    );
+
+    let (cto_res, ctd_res, ccd_res, np_res, nh_res) = tokio::join!(cto, ctd, ccd, np, nh);
 
-    for res in [
-        cto_res, ctd_res, ccd_res,
-    ] {
+    for res in [cto_res, ctd_res, ccd_res, np_res, nh_res] {
         res?;
     }
     Ok(())
 }
 
+/// Applies the batch's ownership changes to the holdings of every `holding` rule and awards the
+/// periods they completed. One transaction, so each period is awarded once.
+async fn insert_nft_holdings(
+    conn_pool: ArcDbPool,
+    nft_points_rules: Option<&NftPointsRulesConfig>,
+    ownerships: &[CurrentTokenOwnership],
+    (last_transaction_version, last_transaction_timestamp): (i64, chrono::NaiveDateTime),
+    per_table_chunk_sizes: &AHashMap<String, usize>,
+) -> Result<(), diesel::result::Error> {
+    let rules = match nft_points_rules {
+        Some(rules) if !rules.holding_rules().is_empty() => rules,
+        _ => return Ok(()),
+    };
+    let holding_chunk_size =
+        get_config_table_chunk_size::<NftHolding>("nft_holdings", per_table_chunk_sizes);
+    let points_chunk_size =
+        get_config_table_chunk_size::<NftPoints>("nft_points", per_table_chunk_sizes);
+    let mut token_data_id_hashes = ownerships
+        .iter()
+        .map(|o| o.token_data_id_hash.clone())
+        .collect::<AHashSet<_>>()
+        .into_iter()
+        .collect::<Vec<_>>();
+    token_data_id_hashes.sort();
+    let conn = &mut conn_pool.get().await.map_err(|e| {
+        tracing::warn!("Error getting connection from pool: {:?}", e);
+        diesel::result::Error::DatabaseError(
+            diesel::result::DatabaseErrorKind::UnableToSendCommand,
+            Box::new(e.to_string()),
+        )
+    })?;
+    conn.transaction::<_, diesel::result::Error, _>(|conn| {
+        async move {
+            for (rule, trigger) in rules.holding_rules() {
+                let holdings = NftHolding::get_for_update(
+                    conn,
+                    &rule.name,
+                    &rules.version,
+                    &token_data_id_hashes,
+                    NftHoldingLedger::due_before(trigger, last_transaction_timestamp),
+                )
+                .await?;
+                let mut ledger = NftHoldingLedger::new(rule, trigger, &rules.version, holdings);
+                for ownership in ownerships {
+                    ledger.apply(ownership);
+                }
+                ledger.accrue_until(last_transaction_version, last_transaction_timestamp);
+                let (holdings, points) = ledger.into_parts();
+                for chunk in holdings.chunks(holding_chunk_size) {
+                    let (query, additional_where_clause) =
+                        insert_nft_holdings_query(chunk.to_vec());
+                    execute_with_better_error_conn(conn, query, additional_where_clause).await?;
+                }
+                for chunk in points.chunks(points_chunk_size) {
+                    let (query, additional_where_clause) = insert_nft_points_query(chunk.to_vec());
+                    execute_with_better_error_conn(conn, query, additional_where_clause).await?;
+                }
+            }
+            Ok(())
+        }
+        .scope_boxed()
+    })
+    .await
+}
+
 fn insert_current_token_ownerships_query(
     items_to_insert: Vec<CurrentTokenOwnership>,
 ) -> (
@@ -220,6 +329,66 @@ fn insert_current_collection_datas_query(
     )
 }
 
+fn insert_nft_points_query(
+    items_to_insert: Vec<NftPoints>,
+) -> (
+    impl QueryFragment<Pg> + diesel::query_builder::QueryId + Send,
+    Option<&'static str>,
+) {
+    use schema::nft_points::dsl::*;
+
+    // Replaying with the same rules version overwrites the earlier result
+    (
+        diesel::insert_into(schema::nft_points::table)
+            .values(items_to_insert)
+            .on_conflict((transaction_version, event_index, rule_name, rules_version))
+            .do_update()
+            .set((
+                owner_address.eq(excluded(owner_address)),
+                token_name.eq(excluded(token_name)),
+                point_type.eq(excluded(point_type)),
+                amount.eq(excluded(amount)),
+                transaction_timestamp.eq(excluded(transaction_timestamp)),
+                inserted_at.eq(excluded(inserted_at)),
+            )),
+        None,
+    )
+}
+
+fn insert_nft_holdings_query(
+    items_to_insert: Vec<NftHolding>,
+) -> (
+    impl QueryFragment<Pg> + diesel::query_builder::QueryId + Send,
+    Option<&'static str>,
+) {
+    use schema::nft_holdings::dsl::*;
+
+    // Rows another batch moved past since they were read are left alone
+    (
+        diesel::insert_into(schema::nft_holdings::table)
+            .values(items_to_insert)
+            .on_conflict((
+                rule_name,
+                rules_version,
+                token_data_id_hash,
+                property_version,
+                owner_address,
+            ))
+            .do_update()
+            .set((
+                token_name.eq(excluded(token_name)),
+                amount.eq(excluded(amount)),
+                held_since.eq(excluded(held_since)),
+                accrued_until.eq(excluded(accrued_until)),
+                last_transaction_version.eq(excluded(last_transaction_version)),
+                inserted_at.eq(excluded(inserted_at)),
+            )),
+        Some(
+            " WHERE nft_holdings.last_transaction_version <= excluded.last_transaction_version \
+             AND nft_holdings.accrued_until <= excluded.accrued_until ",
+        ),
+    )
+}
 
 #[async_trait]
 impl ProcessorTrait for MercatoTokenProcessor {
@@ -243,6 +412,13 @@ impl ProcessorTrait for MercatoTokenProcessor {
         
         let processing_start = std::time::Instant::now();
         let last_transaction_timestamp = transactions.last().unwrap().timestamp.clone();
+        let last_transaction = (
+            transactions.last().unwrap().version as i64,
+            parse_timestamp(
+                last_transaction_timestamp.as_ref().unwrap(),
+                transactions.last().unwrap().version as i64,
+            ),
+        );
 
         let mut conn = self.get_conn().await;
         let query_retries = self.config.query_retries;
@@ -262,6 +438,9 @@ impl ProcessorTrait for MercatoTokenProcessor {
             AHashMap::new();
         let mut all_current_collection_datas: AHashMap<TokenDataIdHash, CurrentCollectionData> =
             AHashMap::new();
+        let mut all_nft_points = vec![];
+        // Ownership changes of collections with a holding rule, in transaction order
+        let mut holding_ownerships = vec![];
 
         for txn in &transactions {
             let (
@@ -280,9 +459,25 @@ impl ProcessorTrait for MercatoTokenProcessor {
                 query_retry_delay_ms,
             )
             .await;
+            if let Some(rules) = &self.nft_points_rules {
+                let holding_rules = rules.holding_rules();
+                holding_ownerships.extend(
+                    current_token_ownerships
+                        .values()
+                        .filter(|o| {
+                            holding_rules.iter().any(|(_, trigger)| {
+                                trigger.matches(&o.creator_address, &o.collection_name)
+                            })
+                        })
+                        .cloned(),
+                );
+            }
             all_current_token_ownerships.extend(current_token_ownerships);
             all_current_token_datas.extend(current_token_datas);
             all_current_collection_datas.extend(current_collection_datas);
+            if let Some(rules) = &self.nft_points_rules {
+                all_nft_points.extend(NftPoints::from_transaction(txn, rules));
+            }
         }
 
         // Getting list of values and sorting by pk in order to avoid postgres deadlock since we're doing multi threaded db writes
@@ -321,6 +516,12 @@ impl ProcessorTrait for MercatoTokenProcessor {
                 &all_current_token_ownerships,
                 &all_current_token_datas,
                 &all_current_collection_datas,
+                &all_nft_points,
+            ),
+            (
+                self.nft_points_rules.as_ref(),
+                &holding_ownerships,
+                last_transaction,
             ),
             &self.per_table_chunk_sizes,
         )
@@ -6,9 +6,12 @@
 
 pub mod account_transactions_processor;
 pub mod ans_processor;
+pub mod collection_stats_processor;
 pub mod default_processor;
 pub mod events_processor;
 pub mod fungible_asset_processor;
+pub mod launchpad_processor;
+pub mod marketplace_processor;
 pub mod mercato_processor;
 pub mod monitoring_processor;
 pub mod nft_metadata_processor;
@@ -26,10 +29,13 @@ pub mod mercato_account_processor;
 use self::{
     account_transactions_processor::AccountTransactionsProcessor,
     ans_processor::{AnsProcessor, AnsProcessorConfig},
+    collection_stats_processor::{CollectionStatsProcessor, CollectionStatsProcessorConfig},
     default_processor::DefaultProcessor,
     events_processor::EventsProcessor,
     fungible_asset_processor::FungibleAssetProcessor,
-    mercato_processor::MercatoProcessor,
+    launchpad_processor::{LaunchpadProcessor, LaunchpadProcessorConfig},
+    marketplace_processor::{MarketplaceProcessor, MarketplaceProcessorConfig},
+    mercato_processor::{MercatoProcessor, MercatoProcessorConfig},
     monitoring_processor::MonitoringProcessor,
     nft_metadata_processor::{NftMetadataProcessor, NftMetadataProcessorConfig},
     objects_processor::{ObjectsProcessor, ObjectsProcessorConfig},
@@ -37,13 +43,13 @@ use self::{
     token_v2_processor::{TokenV2Processor, TokenV2ProcessorConfig},
     mercato_token_processor::{MercatoTokenProcessor, MercatoTokenProcessorConfig},
     mercato_token_v2_processor::{MercatoTokenV2Processor, MercatoTokenV2ProcessorConfig},
-    mercato_indexer_processor::{MercatoIndexerProcessor},
+    mercato_indexer_processor::{MercatoIndexerProcessor, MercatoIndexerProcessorConfig},
     transaction_metadata_processor::TransactionMetadataProcessor,
     user_transaction_processor::UserTransactionProcessor,
     mercato_account_processor::MercatoAccountProcessor,
 };
 use crate::{
-    db::common::models::processor_status::ProcessorStatus,
+    db::common::models::{processor_ranges::ProcessorRange, processor_status::ProcessorStatus},
     gap_detectors::ProcessingResult,
     processors::parquet_processors::{
         parquet_ans_processor::{ParquetAnsProcessor, ParquetAnsProcessorConfig},
@@ -57,16 +63,19 @@ use crate::{
             ParquetTransactionMetadataProcessor, ParquetTransactionMetadataProcessorConfig,
         },
     },
-    schema::processor_status,
+    schema::{processor_ranges, processor_status},
     utils::{
         counters::{GOT_CONNECTION_COUNT, UNABLE_TO_GET_CONNECTION_COUNT},
-        database::{execute_with_better_error, ArcDbPool, DbPoolConnection},
+        database::{
+            execute_with_better_error, execute_with_better_error_conn, ArcDbPool, DbPoolConnection,
+        },
         util::parse_timestamp,
     },
 };
 use aptos_protos::transaction::v1::Transaction as ProtoTransaction;
 use async_trait::async_trait;
-use diesel::{pg::upsert::excluded, ExpressionMethods};
+use diesel::{pg::upsert::excluded, ExpressionMethods, QueryDsl};
+use diesel_async::{scoped_futures::ScopedFutureExt, AsyncConnection};
 use enum_dispatch::enum_dispatch;
 use serde::{Deserialize, Serialize};
 use std::fmt::Debug;
@@ -162,6 +171,52 @@ pub trait ProcessorTrait: Send + Sync + Debug {
         .await?;
         Ok(())
     }
+
+    /// Replaces the ranges stored for this processor with `ranges`, the batches processed past
+    /// the last success version. These are skipped on restart so only the gaps are refetched.
+    async fn update_processed_ranges(
+        &self,
+        ranges: Vec<DefaultProcessingResult>,
+    ) -> anyhow::Result<()> {
+        let processor_name = self.name();
+        let ranges = ranges
+            .into_iter()
+            .map(|range| ProcessorRange {
+                processor: processor_name.to_string(),
+                start_version: range.start_version as i64,
+                end_version: range.end_version as i64,
+                last_transaction_timestamp: range
+                    .last_transaction_timestamp
+                    .map(|t| parse_timestamp(&t, range.end_version as i64)),
+            })
+            .collect::<Vec<_>>();
+        let mut conn = self.get_conn().await;
+        conn.transaction::<_, diesel::result::Error, _>(|conn| {
+            async move {
+                execute_with_better_error_conn(
+                    conn,
+                    diesel::delete(
+                        processor_ranges::table
+                            .filter(processor_ranges::processor.eq(processor_name)),
+                    ),
+                    None,
+                )
+                .await?;
+                if !ranges.is_empty() {
+                    execute_with_better_error_conn(
+                        conn,
+                        diesel::insert_into(processor_ranges::table).values(ranges),
+                        None,
+                    )
+                    .await?;
+                }
+                Ok(())
+            }
+            .scope_boxed()
+        })
+        .await?;
+        Ok(())
+    }
 }
 
 /// This enum captures the configs for all the different processors that are defined.
@@ -199,7 +254,7 @@ pub enum ProcessorConfig {
     AccountTransactionsProcessor,
     AnsProcessor(AnsProcessorConfig),
     DefaultProcessor,
-    MercatoProcessor,
+    MercatoProcessor(MercatoProcessorConfig),
     EventsProcessor,
     FungibleAssetProcessor,
     MonitoringProcessor,
@@ -209,8 +264,11 @@ pub enum ProcessorConfig {
     TokenV2Processor(TokenV2ProcessorConfig),
     MercatoTokenProcessor(MercatoTokenProcessorConfig),
     MercatoTokenV2Processor(MercatoTokenV2ProcessorConfig),
-    MercatoIndexerProcessor,
+    MercatoIndexerProcessor(MercatoIndexerProcessorConfig),
     MercatoAccountProcessor,
+    MarketplaceProcessor(MarketplaceProcessorConfig),
+    CollectionStatsProcessor(CollectionStatsProcessorConfig),
+    LaunchpadProcessor(LaunchpadProcessorConfig),
     TransactionMetadataProcessor,
     UserTransactionProcessor,
     ParquetDefaultProcessor(ParquetDefaultProcessorConfig),
@@ -276,6 +334,9 @@ pub enum Processor {
     UserTransactionProcessor,
     MercatoAccountProcessor,
     MercatoIndexerProcessor,
+    MarketplaceProcessor,
+    CollectionStatsProcessor,
+    LaunchpadProcessor,
     // Parquet processors
     ParquetDefaultProcessor,
     ParquetFungibleAssetProcessor,
@@ -285,6 +346,20 @@ pub enum Processor {
     ParquetTokenV2Processor,
 }
 
+impl Processor {
+    pub fn is_parquet_processor(&self) -> bool {
+        matches!(
+            self,
+            Processor::ParquetDefaultProcessor(_)
+                | Processor::ParquetFungibleAssetProcessor(_)
+                | Processor::ParquetTransactionMetadataProcessor(_)
+                | Processor::ParquetAnsProcessor(_)
+                | Processor::ParquetEventsProcessor(_)
+                | Processor::ParquetTokenV2Processor(_)
+        )
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -0,0 +1,383 @@
+// Copyright © Aptos Foundation
+// SPDX-License-Identifier: Apache-2.0
+
+//! Deletes old rows according to per-table policies. Deletes are issued in batches of
+//! `batch_size` rows with a pause in between, so large tables are never locked for long. Runs
+//! next to a processor (`retention` in the processor config) or once from the
+//! `delete-old-recent-records` job.
+
+use crate::utils::{
+    counters::{
+        RETENTION_DELETED_ROWS_COUNT, RETENTION_ELIGIBLE_ROWS, RETENTION_RUN_DURATION_IN_SECS,
+    },
+    database::{ArcDbPool, DbPoolConnection},
+};
+use anyhow::{bail, Context, Result};
+use diesel::sql_types::BigInt;
+use diesel_async::RunQueryDsl;
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, time::Duration};
+use tracing::{error, info};
+
+lazy_static! {
+    static ref IDENTIFIER_REGEX: Regex = Regex::new("^[a-z_][a-z0-9_]*$").unwrap();
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct RetentionConfig {
+    // Wait between runs when running next to a processor
+    #[serde(default = "RetentionConfig::default_interval_secs")]
+    pub interval_secs: u64,
+    // Rows deleted per statement
+    #[serde(default = "RetentionConfig::default_batch_size")]
+    pub batch_size: u64,
+    // Pause between two delete statements on the same table
+    #[serde(default = "RetentionConfig::default_batch_delay_ms")]
+    pub batch_delay_ms: u64,
+    // Count the rows each policy would delete without deleting anything
+    #[serde(default)]
+    pub dry_run: bool,
+    pub policies: Vec<RetentionPolicy>,
+}
+
+impl RetentionConfig {
+    pub const fn default_interval_secs() -> u64 {
+        3600
+    }
+
+    pub const fn default_batch_size() -> u64 {
+        10_000
+    }
+
+    pub const fn default_batch_delay_ms() -> u64 {
+        100
+    }
+}
+
+/// Rows of `table` older than `max_age_days` are deleted unless one of the keep rules applies
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct RetentionPolicy {
+    pub table: String,
+    pub max_age_days: u32,
+    #[serde(default = "RetentionPolicy::default_age_column")]
+    pub age_column: String,
+    pub keep_latest_per_key: Option<KeepLatestPerKey>,
+    #[serde(default)]
+    pub keep_if_referenced: Vec<KeepIfReferenced>,
+}
+
+/// Keeps the newest row of each key, however old it is
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct KeepLatestPerKey {
+    pub key_columns: Vec<String>,
+    pub order_column: String,
+}
+
+/// Keeps rows that still have a matching row in another table, e.g. token datas of tokens
+/// that are still owned
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct KeepIfReferenced {
+    pub table: String,
+    // Column of the policy table -> column of the referencing table
+    pub columns: BTreeMap<String, String>,
+    // Extra SQL condition on the referencing table, e.g. `amount > 0`
+    pub condition: Option<String>,
+}
+
+impl RetentionPolicy {
+    pub fn default_age_column() -> String {
+        "inserted_at".to_string()
+    }
+
+    fn validate(&self) -> Result<()> {
+        let mut identifiers = vec![&self.table, &self.age_column];
+        if let Some(keep_latest) = &self.keep_latest_per_key {
+            if keep_latest.key_columns.is_empty() {
+                bail!("keep_latest_per_key of {} has no key columns", self.table);
+            }
+            identifiers.extend(keep_latest.key_columns.iter());
+            identifiers.push(&keep_latest.order_column);
+        }
+        for reference in &self.keep_if_referenced {
+            if reference.columns.is_empty() {
+                bail!(
+                    "keep_if_referenced {} of {} has no columns",
+                    reference.table,
+                    self.table
+                );
+            }
+            identifiers.push(&reference.table);
+            for (column, referencing_column) in &reference.columns {
+                identifiers.push(column);
+                identifiers.push(referencing_column);
+            }
+        }
+        for identifier in identifiers {
+            if !IDENTIFIER_REGEX.is_match(identifier) {
+                bail!(
+                    "Invalid identifier {:?} in retention policy for {}",
+                    identifier,
+                    self.table
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// `WITH` clause of the rows that keep_if_referenced looks for, one CTE per reference. The
+    /// condition is evaluated in the CTE, where only the referencing table is in scope, so it
+    /// can't bind to columns of the policy table.
+    fn references_cte(&self) -> String {
+        if self.keep_if_referenced.is_empty() {
+            return String::new();
+        }
+        let ctes = self
+            .keep_if_referenced
+            .iter()
+            .enumerate()
+            .map(|(index, reference)| {
+                let columns = reference
+                    .columns
+                    .values()
+                    .cloned()
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                let condition = reference
+                    .condition
+                    .as_ref()
+                    .map(|condition| format!(" WHERE ({})", condition))
+                    .unwrap_or_default();
+                format!(
+                    "r{} AS (SELECT {} FROM {}{})",
+                    index, columns, reference.table, condition
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(", ");
+        format!("WITH {} ", ctes)
+    }
+
+    /// Condition on `t`, a row of the policy table, that holds for rows to delete. Refers to the
+    /// CTEs of `references_cte`.
+    fn delete_condition(&self) -> String {
+        let mut condition = format!(
+            "t.{} < NOW() - make_interval(days => {})",
+            self.age_column, self.max_age_days
+        );
+        if let Some(keep_latest) = &self.keep_latest_per_key {
+            let key_match = keep_latest
+                .key_columns
+                .iter()
+                .map(|column| format!("n.{column} = t.{column}"))
+                .collect::<Vec<_>>()
+                .join(" AND ");
+            condition.push_str(&format!(
+                " AND EXISTS (SELECT 1 FROM {table} n WHERE {key_match} AND n.{order} > t.{order})",
+                table = self.table,
+                key_match = key_match,
+                order = keep_latest.order_column,
+            ));
+        }
+        for (index, reference) in self.keep_if_referenced.iter().enumerate() {
+            let alias = format!("r{}", index);
+            let reference_match = reference
+                .columns
+                .iter()
+                .map(|(column, referencing_column)| {
+                    format!("{alias}.{referencing_column} = t.{column}")
+                })
+                .collect::<Vec<_>>()
+                .join(" AND ");
+            condition.push_str(&format!(
+                " AND NOT EXISTS (SELECT 1 FROM {} WHERE {})",
+                alias, reference_match
+            ));
+        }
+        condition
+    }
+
+    /// Rows are picked by (tableoid, ctid) because a ctid is only unique within one partition
+    fn delete_batch_sql(&self, batch_size: u64) -> String {
+        format!(
+            "{ctes}DELETE FROM {table} d USING (SELECT t.tableoid AS row_tableoid, t.ctid AS row_ctid \
+             FROM {table} t WHERE {condition} LIMIT {batch_size}) b \
+             WHERE d.tableoid = b.row_tableoid AND d.ctid = b.row_ctid",
+            ctes = self.references_cte(),
+            table = self.table,
+            condition = self.delete_condition(),
+            batch_size = batch_size,
+        )
+    }
+
+    fn count_sql(&self) -> String {
+        format!(
+            "{}SELECT COUNT(*) AS count FROM {} t WHERE {}",
+            self.references_cte(),
+            self.table,
+            self.delete_condition()
+        )
+    }
+}
+
+#[derive(Debug, QueryableByName)]
+struct RowCount {
+    #[diesel(sql_type = BigInt)]
+    count: i64,
+}
+
+/// Outcome of one policy in one run
+#[derive(Clone, Debug)]
+pub struct RetentionReport {
+    pub table: String,
+    // Rows deleted, or rows that would be deleted in dry run mode
+    pub rows: u64,
+    pub dry_run: bool,
+    pub duration: Duration,
+}
+
+pub struct Retention {
+    connection_pool: ArcDbPool,
+    config: RetentionConfig,
+}
+
+impl Retention {
+    pub fn new(connection_pool: ArcDbPool, config: RetentionConfig) -> Result<Self> {
+        for policy in &config.policies {
+            policy.validate()?;
+        }
+        if config.batch_size == 0 {
+            bail!("Retention batch_size must be greater than 0");
+        }
+        Ok(Self {
+            connection_pool,
+            config,
+        })
+    }
+
+    /// Applies every policy every `interval_secs`. Failed runs are retried on the next interval.
+    pub async fn run(&self) {
+        loop {
+            if let Err(e) = self.run_once().await {
+                error!(error = ?e, "[Retention] Retention run failed");
+            }
+            tokio::time::sleep(Duration::from_secs(self.config.interval_secs)).await;
+        }
+    }
+
+    /// Applies every policy once, in order
+    pub async fn run_once(&self) -> Result<Vec<RetentionReport>> {
+        let mut reports = vec![];
+        for policy in &self.config.policies {
+            let report = self
+                .apply(policy)
+                .await
+                .with_context(|| format!("Retention policy for {} failed", policy.table))?;
+            info!(
+                table = report.table.as_str(),
+                rows = report.rows,
+                dry_run = report.dry_run,
+                duration_in_secs = report.duration.as_secs_f64(),
+                "[Retention] Applied retention policy"
+            );
+            reports.push(report);
+        }
+        Ok(reports)
+    }
+
+    async fn apply(&self, policy: &RetentionPolicy) -> Result<RetentionReport> {
+        let start = std::time::Instant::now();
+        let table = policy.table.as_str();
+        let rows = if self.config.dry_run {
+            let mut conn: DbPoolConnection = self.connection_pool.get().await?;
+            let row_count = diesel::sql_query(policy.count_sql())
+                .get_result::<RowCount>(&mut conn)
+                .await?;
+            RETENTION_ELIGIBLE_ROWS
+                .with_label_values(&[table])
+                .set(row_count.count);
+            row_count.count as u64
+        } else {
+            let sql = policy.delete_batch_sql(self.config.batch_size);
+            let mut total = 0;
+            loop {
+                let deleted = {
+                    let mut conn: DbPoolConnection = self.connection_pool.get().await?;
+                    diesel::sql_query(sql.as_str()).execute(&mut conn).await? as u64
+                };
+                total += deleted;
+                RETENTION_DELETED_ROWS_COUNT
+                    .with_label_values(&[table])
+                    .inc_by(deleted);
+                if deleted < self.config.batch_size {
+                    break;
+                }
+                tokio::time::sleep(Duration::from_millis(self.config.batch_delay_ms)).await;
+            }
+            total
+        };
+        let duration = start.elapsed();
+        RETENTION_RUN_DURATION_IN_SECS
+            .with_label_values(&[table])
+            .set(duration.as_secs_f64());
+        Ok(RetentionReport {
+            table: policy.table.clone(),
+            rows,
+            dry_run: self.config.dry_run,
+            duration,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_delete_condition() {
+        let policy: RetentionPolicy = serde_json::from_value(serde_json::json!({
+            "table": "current_token_datas_v2",
+            "max_age_days": 30,
+            "age_column": "last_transaction_timestamp",
+            "keep_latest_per_key": {
+                "key_columns": ["collection_id"],
+                "order_column": "last_transaction_version",
+            },
+            "keep_if_referenced": [{
+                "table": "current_token_ownerships_v2",
+                "columns": {"token_data_id": "token_data_id"},
+                "condition": "amount > 0",
+            }],
+        }))
+        .unwrap();
+        policy.validate().unwrap();
+        assert_eq!(
+            policy.delete_condition(),
+            "t.last_transaction_timestamp < NOW() - make_interval(days => 30) \
+             AND EXISTS (SELECT 1 FROM current_token_datas_v2 n WHERE n.collection_id = t.collection_id \
+             AND n.last_transaction_version > t.last_transaction_version) \
+             AND NOT EXISTS (SELECT 1 FROM r0 WHERE r0.token_data_id = t.token_data_id)"
+        );
+        assert_eq!(
+            policy.delete_batch_sql(100),
+            format!(
+                "WITH r0 AS (SELECT token_data_id FROM current_token_ownerships_v2 WHERE (amount > 0)) \
+                 DELETE FROM current_token_datas_v2 d USING (SELECT t.tableoid AS row_tableoid, \
+                 t.ctid AS row_ctid FROM current_token_datas_v2 t WHERE {} LIMIT 100) b \
+                 WHERE d.tableoid = b.row_tableoid AND d.ctid = b.row_ctid",
+                policy.delete_condition()
+            )
+        );
+
+        let invalid = RetentionPolicy {
+            table: "events; DROP TABLE events".to_string(),
+            ..policy
+        };
+        assert!(invalid.validate().is_err());
+    }
+}
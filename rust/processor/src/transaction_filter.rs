@@ -1,14 +1,24 @@
+use crate::utils::{
+    pattern::{glob_match, type_matches, type_may_match},
+    util::{
+        standardize_address, standardize_entry_function_id, standardize_type_addresses,
+        TYPE_ADDRESS_REGEX,
+    },
+};
+use anyhow::{bail, Context, Result};
 use aptos_protos::transaction::v1::{
+    multisig_transaction_payload::Payload as MultisigPayload,
     transaction::{TransactionType, TxnData},
     transaction_payload::Payload,
-    Transaction,
+    write_set_change::Change,
+    Event, Transaction,
 };
 use serde::{Deserialize, Serialize};
 
 /// Allows filtering transactions based on various criteria
 /// The criteria are combined with `AND`
 /// If a criteria is not set, it is ignored
-/// Criteria will be loaded from the config file
+/// Criteria will be loaded from the config file, then standardized with `normalized`
 #[derive(Clone, Debug, Default, Deserialize, Serialize)]
 #[serde(deny_unknown_fields)]
 #[serde(default)]
@@ -19,6 +29,44 @@ pub struct TransactionFilter {
     skip_sender_addresses: Option<ahash::HashSet<String>>,
     // Skip all transactions that aren't user transactions
     focus_user_transactions: bool,
+    // Only allow transactions matching this expression
+    rule: Option<TransactionRule>,
+}
+
+/// Boolean expression over a transaction. Patterns accept `*` wildcards, and a type pattern
+/// without type arguments matches the type with any type arguments. For example
+/// ```yaml
+/// any:
+///   - event_type: "0xcafe::launchpad::MintEvent"
+///   - all:
+///       - entry_function: "0xcafe::points::*"
+///       - success: true
+/// ```
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields, rename_all = "snake_case")]
+pub enum TransactionRule {
+    All(Vec<TransactionRule>),
+    Any(Vec<TransactionRule>),
+    Not(Box<TransactionRule>),
+    // Entry function id `address::module::function`, also when wrapped in a multisig payload
+    EntryFunction(String),
+    // `address::module` of the entry function
+    Module(String),
+    // Type of any event emitted by the transaction
+    EventType(String),
+    // Type of any resource written by the transaction
+    WriteResourceType(String),
+    Success(bool),
+    PayloadType(PayloadType),
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PayloadType {
+    EntryFunction,
+    Script,
+    Multisig,
+    WriteSet,
 }
 
 impl TransactionFilter {
@@ -26,17 +74,44 @@ impl TransactionFilter {
         focus_contract_addresses: Option<ahash::HashSet<String>>,
         skip_sender_addresses: Option<ahash::HashSet<String>>,
         focus_user_transactions: bool,
-    ) -> Self {
-        // TODO: normalize addresses
+        rule: Option<TransactionRule>,
+    ) -> Result<Self> {
         Self {
             focus_contract_addresses,
             skip_sender_addresses,
             focus_user_transactions,
+            rule,
         }
+        .normalized()
+    }
+
+    /// Standardizes every configured address so it compares equal to the standardized addresses
+    /// of incoming transactions. Errors on addresses that aren't valid.
+    pub fn normalized(self) -> Result<Self> {
+        Ok(Self {
+            focus_contract_addresses: normalize_addresses(self.focus_contract_addresses)
+                .context("Invalid focus_contract_addresses")?,
+            skip_sender_addresses: normalize_addresses(self.skip_sender_addresses)
+                .context("Invalid skip_sender_addresses")?,
+            focus_user_transactions: self.focus_user_transactions,
+            rule: self
+                .rule
+                .map(TransactionRule::normalized)
+                .transpose()
+                .context("Invalid rule")?,
+        })
     }
 
     /// Returns true if the transaction should be included
     pub fn include(&self, transaction: &Transaction) -> bool {
+        self.include_by_address(transaction)
+            && self
+                .rule
+                .as_ref()
+                .map_or(true, |rule| rule.matches(transaction))
+    }
+
+    fn include_by_address(&self, transaction: &Transaction) -> bool {
         // If we're only focusing on user transactions, skip if it's not a user transaction
 
         let is_user_txn = transaction.r#type == TransactionType::User as i32;
@@ -53,7 +128,8 @@ impl TransactionFilter {
             if let Some(utr) = user_transaction.request.as_ref() {
                 // Skip if sender is in the skip list
                 if let Some(skip_sender_addresses) = &self.skip_sender_addresses {
-                    if skip_sender_addresses.contains(&utr.sender) {
+                    let sender = standardize_address(&utr.sender.to_lowercase());
+                    if skip_sender_addresses.contains(&sender) {
                         return false;
                     }
                 }
@@ -64,7 +140,9 @@ impl TransactionFilter {
                         if let Some(Payload::EntryFunctionPayload(efp)) = payload.payload.as_ref() {
                             if let Some(function) = efp.function.as_ref() {
                                 if let Some(module) = function.module.as_ref() {
-                                    if !focus_contract_addresses.contains(&module.address) {
+                                    let address =
+                                        standardize_address(&module.address.to_lowercase());
+                                    if !focus_contract_addresses.contains(&address) {
                                         return false;
                                     }
                                 }
@@ -78,3 +156,322 @@ impl TransactionFilter {
         true
     }
 }
+
+impl TransactionRule {
+    /// Standardizes the addresses in every pattern, see `TransactionFilter::normalized`
+    pub fn normalized(self) -> Result<Self> {
+        Ok(match self {
+            TransactionRule::All(rules) => TransactionRule::All(
+                rules
+                    .into_iter()
+                    .map(TransactionRule::normalized)
+                    .collect::<Result<_>>()?,
+            ),
+            TransactionRule::Any(rules) => TransactionRule::Any(
+                rules
+                    .into_iter()
+                    .map(TransactionRule::normalized)
+                    .collect::<Result<_>>()?,
+            ),
+            TransactionRule::Not(rule) => TransactionRule::Not(Box::new(rule.normalized()?)),
+            TransactionRule::EntryFunction(pattern) => TransactionRule::EntryFunction(
+                normalize_entry_function_pattern(&pattern)
+                    .with_context(|| format!("Invalid entry_function {:?}", pattern))?,
+            ),
+            TransactionRule::Module(pattern) => TransactionRule::Module(
+                normalize_entry_function_pattern(&pattern)
+                    .with_context(|| format!("Invalid module {:?}", pattern))?,
+            ),
+            TransactionRule::EventType(pattern) => TransactionRule::EventType(
+                normalize_type_pattern(&pattern)
+                    .with_context(|| format!("Invalid event_type {:?}", pattern))?,
+            ),
+            TransactionRule::WriteResourceType(pattern) => TransactionRule::WriteResourceType(
+                normalize_type_pattern(&pattern)
+                    .with_context(|| format!("Invalid write_resource_type {:?}", pattern))?,
+            ),
+            rule @ (TransactionRule::Success(_) | TransactionRule::PayloadType(_)) => rule,
+        })
+    }
+
+    pub fn matches(&self, transaction: &Transaction) -> bool {
+        match self {
+            TransactionRule::All(rules) => rules.iter().all(|rule| rule.matches(transaction)),
+            TransactionRule::Any(rules) => rules.iter().any(|rule| rule.matches(transaction)),
+            TransactionRule::Not(rule) => !rule.matches(transaction),
+            TransactionRule::EntryFunction(pattern) => entry_function_id(transaction)
+                .map_or(false, |id| {
+                    glob_match(pattern, &standardize_entry_function_id(id))
+                }),
+            TransactionRule::Module(pattern) => entry_function_id(transaction)
+                .and_then(|id| id.rsplit_once("::"))
+                .map_or(false, |(module, _)| {
+                    glob_match(pattern, &standardize_entry_function_id(module))
+                }),
+            TransactionRule::EventType(pattern) => events(transaction).iter().any(|event| {
+                type_may_match(pattern, &event.type_str)
+                    && type_matches(pattern, &standardize_type_addresses(&event.type_str))
+            }),
+            TransactionRule::WriteResourceType(pattern) => {
+                transaction.info.as_ref().map_or(false, |info| {
+                    info.changes
+                        .iter()
+                        .any(|change| match change.change.as_ref() {
+                            Some(Change::WriteResource(resource)) => {
+                                type_may_match(pattern, &resource.type_str)
+                                    && type_matches(
+                                        pattern,
+                                        &standardize_type_addresses(&resource.type_str),
+                                    )
+                            },
+                            _ => false,
+                        })
+                })
+            },
+            TransactionRule::Success(success) => transaction
+                .info
+                .as_ref()
+                .map_or(false, |info| info.success == *success),
+            TransactionRule::PayloadType(payload_type) => {
+                let transaction_payload_type = match user_payload(transaction) {
+                    Some(Payload::EntryFunctionPayload(_)) => PayloadType::EntryFunction,
+                    Some(Payload::ScriptPayload(_)) => PayloadType::Script,
+                    Some(Payload::MultisigPayload(_)) => PayloadType::Multisig,
+                    Some(Payload::WriteSetPayload(_)) => PayloadType::WriteSet,
+                    _ => return false,
+                };
+                transaction_payload_type == *payload_type
+            },
+        }
+    }
+}
+
+fn user_payload(transaction: &Transaction) -> Option<&Payload> {
+    match transaction.txn_data.as_ref() {
+        Some(TxnData::User(user_transaction)) => user_transaction
+            .request
+            .as_ref()
+            .and_then(|request| request.payload.as_ref())
+            .and_then(|payload| payload.payload.as_ref()),
+        _ => None,
+    }
+}
+
+fn entry_function_id(transaction: &Transaction) -> Option<&str> {
+    match user_payload(transaction)? {
+        Payload::EntryFunctionPayload(payload) => Some(payload.entry_function_id_str.as_str()),
+        Payload::MultisigPayload(payload) => {
+            match payload.transaction_payload.as_ref()?.payload.as_ref()? {
+                MultisigPayload::EntryFunctionPayload(payload) => {
+                    Some(payload.entry_function_id_str.as_str())
+                },
+            }
+        },
+        _ => None,
+    }
+}
+
+fn events(transaction: &Transaction) -> &[Event] {
+    match transaction.txn_data.as_ref() {
+        Some(TxnData::BlockMetadata(tx_inner)) => &tx_inner.events,
+        Some(TxnData::Genesis(tx_inner)) => &tx_inner.events,
+        Some(TxnData::User(tx_inner)) => &tx_inner.events,
+        Some(TxnData::Validator(tx_inner)) => &tx_inner.events,
+        _ => &[],
+    }
+}
+
+fn normalize_addresses(
+    addresses: Option<ahash::HashSet<String>>,
+) -> Result<Option<ahash::HashSet<String>>> {
+    addresses
+        .map(|addresses| {
+            addresses
+                .iter()
+                .map(|address| normalize_address(address))
+                .collect::<Result<_>>()
+        })
+        .transpose()
+}
+
+fn normalize_address(address: &str) -> Result<String> {
+    let lowercase = address.trim().to_lowercase();
+    let hex = lowercase.strip_prefix("0x").unwrap_or(&lowercase);
+    if hex.is_empty() || hex.len() > 64 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        bail!(
+            "Invalid address {:?}, expected 1 to 64 hex characters with an optional 0x prefix",
+            address
+        );
+    }
+    Ok(standardize_address(hex))
+}
+
+/// Addresses containing a wildcard are matched as written
+fn normalize_address_pattern(address: &str) -> Result<String> {
+    if address.contains('*') {
+        Ok(address.trim().to_lowercase())
+    } else {
+        normalize_address(address)
+    }
+}
+
+/// Standardizes the leading address of `address::module::function` or `address::module`
+fn normalize_entry_function_pattern(pattern: &str) -> Result<String> {
+    match pattern.trim().split_once("::") {
+        Some((address, rest)) => Ok(format!("{}::{}", normalize_address_pattern(address)?, rest)),
+        None => normalize_address_pattern(pattern),
+    }
+}
+
+/// Standardizes the leading address and every address in the type arguments
+fn normalize_type_pattern(pattern: &str) -> Result<String> {
+    let (address, rest) = match pattern.trim().split_once("::") {
+        Some(split) => split,
+        None => bail!("Expected a type like address::module::Name"),
+    };
+    for type_arg_address in TYPE_ADDRESS_REGEX.find_iter(rest) {
+        let type_arg_address = type_arg_address.as_str();
+        if !type_arg_address.ends_with('*') {
+            normalize_address(type_arg_address)?;
+        }
+    }
+    Ok(format!(
+        "{}::{}",
+        normalize_address_pattern(address)?,
+        standardize_type_addresses(rest)
+    ))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use aptos_protos::transaction::v1::{
+        EntryFunctionPayload, TransactionInfo, TransactionPayload, UserTransaction,
+        UserTransactionRequest, WriteResource, WriteSetChange,
+    };
+
+    fn user_transaction(entry_function_id: &str, success: bool, event_type: &str) -> Transaction {
+        Transaction {
+            r#type: TransactionType::User as i32,
+            info: Some(TransactionInfo {
+                success,
+                changes: vec![WriteSetChange {
+                    change: Some(Change::WriteResource(WriteResource {
+                        type_str: "0x1::coin::CoinStore<0x1::aptos_coin::AptosCoin>".to_string(),
+                        ..WriteResource::default()
+                    })),
+                    ..WriteSetChange::default()
+                }],
+                ..TransactionInfo::default()
+            }),
+            txn_data: Some(TxnData::User(UserTransaction {
+                request: Some(UserTransactionRequest {
+                    payload: Some(TransactionPayload {
+                        payload: Some(Payload::EntryFunctionPayload(EntryFunctionPayload {
+                            entry_function_id_str: entry_function_id.to_string(),
+                            ..EntryFunctionPayload::default()
+                        })),
+                        ..TransactionPayload::default()
+                    }),
+                    ..UserTransactionRequest::default()
+                }),
+                events: vec![Event {
+                    type_str: event_type.to_string(),
+                    ..Event::default()
+                }],
+            })),
+            ..Transaction::default()
+        }
+    }
+
+    #[test]
+    fn test_rule() {
+        let rule: TransactionRule = serde_json::from_str(
+            r#"{"any": [
+                {"event_type": "0xcafe::launchpad::MintEvent"},
+                {"all": [
+                    {"entry_function": "0xcafe::points::*"},
+                    {"success": true},
+                    {"not": {"payload_type": "script"}}
+                ]}
+            ]}"#,
+        )
+        .unwrap();
+        let filter = TransactionFilter::new(None, None, false, Some(rule)).unwrap();
+
+        assert!(filter.include(&user_transaction(
+            "0x1::aptos_account::transfer",
+            false,
+            "0xcafe::launchpad::MintEvent<0x1::string::String>",
+        )));
+        assert!(filter.include(&user_transaction(
+            "0xcafe::points::claim",
+            true,
+            "0x1::coin::DepositEvent",
+        )));
+        assert!(!filter.include(&user_transaction(
+            "0xcafe::points::claim",
+            false,
+            "0x1::coin::DepositEvent",
+        )));
+        assert!(!filter.include(&user_transaction(
+            "0x1::aptos_account::transfer",
+            true,
+            "0x1::coin::DepositEvent",
+        )));
+
+        let transaction =
+            user_transaction("0xcafe::points::claim", true, "0x1::coin::DepositEvent");
+        let matches = |rule: TransactionRule| rule.normalized().unwrap().matches(&transaction);
+        assert!(matches(TransactionRule::Module(
+            "0xCAFE::points".to_string()
+        )));
+        assert!(matches(TransactionRule::WriteResourceType(
+            "0x1::coin::CoinStore".to_string()
+        )));
+        assert!(!matches(TransactionRule::WriteResourceType(
+            "0x1::coin::CoinStore<0x1::usdc::USDC>".to_string()
+        )));
+        assert!(matches(TransactionRule::PayloadType(
+            PayloadType::EntryFunction
+        )));
+    }
+
+    #[test]
+    fn test_normalized_addresses() {
+        let filter = TransactionFilter::new(
+            None,
+            Some(["0x07".to_string()].into_iter().collect()),
+            false,
+            Some(TransactionRule::EntryFunction(
+                "0x00CAFE::points::claim".to_string(),
+            )),
+        )
+        .unwrap();
+        let mut transaction = user_transaction(
+            "0x000000000000000000000000000000000000000000000000000000000000cafe::points::claim",
+            true,
+            "0x1::coin::DepositEvent",
+        );
+        assert!(filter.include(&transaction));
+        if let Some(TxnData::User(user_transaction)) = transaction.txn_data.as_mut() {
+            user_transaction.request.as_mut().unwrap().sender =
+                "0x0000000000000000000000000000000000000000000000000000000000000007".to_string();
+        }
+        assert!(!filter.include(&transaction));
+
+        assert!(TransactionFilter::new(
+            Some(["0xnotanaddress".to_string()].into_iter().collect()),
+            None,
+            false,
+            None,
+        )
+        .is_err());
+        assert!(TransactionRule::EventType(format!(
+            "0x1::coin::CoinStore<0x{}::usdc::USDC>",
+            "1".repeat(65)
+        ))
+        .normalized()
+        .is_err());
+    }
+}
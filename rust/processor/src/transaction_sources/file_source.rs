@@ -0,0 +1,502 @@
+// Copyright © Aptos Foundation
+// SPDX-License-Identifier: Apache-2.0
+
+//! Replays `Transaction` protos from local files. A file holds either length-delimited binary
+//! protos or JSON (one array, or one transaction after another), optionally gzip compressed.
+//! A directory is read file by file in name order, and so is a tar archive (`.tar`, `.tar.gz` or
+//! `.tgz`). An archive is held in memory while its files are read, so large recordings are better
+//! split over several archives or extracted.
+
+use crate::{
+    grpc_stream::TransactionsPBResponse,
+    transaction_filter::TransactionFilter,
+    transaction_sources::{recorder::MANIFEST_FILE_NAME, FetchRange, TransactionSource},
+    utils::counters::{
+        FETCHER_THREAD_CHANNEL_SIZE, NUM_BYTES_FILTERED_OUT_COUNT,
+        NUM_TRANSACTIONS_FILTERED_OUT_COUNT,
+    },
+};
+use anyhow::{bail, Context, Result};
+use aptos_protos::transaction::v1::Transaction;
+use async_trait::async_trait;
+use flate2::read::GzDecoder;
+use kanal::AsyncSender;
+use prost::Message;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::VecDeque,
+    fs::File,
+    io::{BufRead, BufReader, Cursor, ErrorKind, Read},
+    path::{Path, PathBuf},
+    time::Duration,
+};
+use tracing::{error, info};
+
+type TransactionIter = Box<dyn Iterator<Item = Result<Transaction>> + Send>;
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FileFormat {
+    // `.json` (or `.json.gz`) files are JSON, anything else is binary
+    #[default]
+    Auto,
+    Binary,
+    Json,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct FileTransactionSourceConfig {
+    // A file, or a directory of files
+    pub path: PathBuf,
+    // There is no service to ask, so the chain has to be given
+    pub chain_id: u64,
+    #[serde(default)]
+    pub format: FileFormat,
+    // Transactions per batch sent to the processor tasks
+    #[serde(default = "FileTransactionSourceConfig::default_batch_size")]
+    pub batch_size: usize,
+}
+
+impl FileTransactionSourceConfig {
+    pub const fn default_batch_size() -> usize {
+        1000
+    }
+}
+
+pub struct FileTransactionSource {
+    pub config: FileTransactionSourceConfig,
+    pub processor_name: String,
+    pub transaction_filter: TransactionFilter,
+}
+
+#[async_trait]
+impl TransactionSource for FileTransactionSource {
+    async fn chain_id(&mut self) -> Result<u64> {
+        Ok(self.config.chain_id)
+    }
+
+    async fn run(
+        self: Box<Self>,
+        sender: AsyncSender<TransactionsPBResponse>,
+        ranges: Vec<FetchRange>,
+    ) {
+        let processor_name = self.processor_name.clone();
+        let path = self.config.path.clone();
+        // File reads block, so the whole replay runs off the async workers
+        let handle = tokio::runtime::Handle::current();
+        let blocking_sender = sender.clone();
+        let result = tokio::task::spawn_blocking(move || {
+            let mut next_version = 0;
+            for range in ranges {
+                info!(
+                    processor_name = self.processor_name,
+                    service_type = crate::worker::PROCESSOR_SERVICE_TYPE,
+                    path = self.config.path.display().to_string(),
+                    start_version = range.starting_version,
+                    end_version = range.ending_version,
+                    "[Parser] Replaying transactions from files",
+                );
+                next_version = self.replay(
+                    &handle,
+                    &blocking_sender,
+                    range.starting_version,
+                    range.ending_version,
+                )?;
+            }
+            Ok::<_, anyhow::Error>(next_version)
+        })
+        .await
+        .expect("[Parser] File replay task panicked");
+        let next_version = match result {
+            Ok(next_version) => next_version,
+            Err(e) => {
+                error!(
+                    processor_name = processor_name,
+                    path = path.display().to_string(),
+                    error = ?e,
+                    "[Parser] Failed to replay transactions from files"
+                );
+                panic!("[Parser] Failed to replay transactions from files: {:?}", e);
+            },
+        };
+        info!(
+            processor_name = processor_name,
+            path = path.display().to_string(),
+            next_version,
+            "[Parser] Reached the end of the transaction files",
+        );
+        // Wait for the replayed transactions to finish processing before closing the channel
+        while !sender.is_empty() {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+    }
+}
+
+impl FileTransactionSource {
+    /// Returns the version after the last one sent
+    fn replay(
+        &self,
+        handle: &tokio::runtime::Handle,
+        sender: &AsyncSender<TransactionsPBResponse>,
+        starting_version: u64,
+        ending_version: Option<u64>,
+    ) -> Result<u64> {
+        let mut reader = FileTransactionReader::new(&self.config.path, self.config.format)?;
+        let mut next_version = starting_version;
+        loop {
+            let batch =
+                reader.next_batch(next_version, ending_version, self.config.batch_size.max(1))?;
+            if batch.is_empty() {
+                return Ok(next_version);
+            }
+            next_version = batch.last().unwrap().version + 1;
+            let response = self.to_response(batch);
+            handle
+                .block_on(sender.send(response))
+                .context("Transaction channel closed")?;
+            FETCHER_THREAD_CHANNEL_SIZE
+                .with_label_values(&[&self.processor_name])
+                .set(sender.len() as i64);
+        }
+    }
+
+    fn to_response(&self, mut transactions: Vec<Transaction>) -> TransactionsPBResponse {
+        let first = transactions.first().unwrap();
+        let last = transactions.last().unwrap();
+        let start_version = first.version;
+        let end_version = last.version;
+        let start_txn_timestamp = first.timestamp.clone();
+        let end_txn_timestamp = last.timestamp.clone();
+        let size_in_bytes = transactions
+            .iter()
+            .map(|txn| txn.encoded_len() as u64)
+            .sum();
+        let num_txns = transactions.len();
+        let mut num_filtered_bytes = 0;
+        transactions.retain(|txn| {
+            let include = self.transaction_filter.include(txn);
+            if !include {
+                num_filtered_bytes += txn.encoded_len() as u64;
+            }
+            include
+        });
+        NUM_TRANSACTIONS_FILTERED_OUT_COUNT
+            .with_label_values(&[&self.processor_name])
+            .inc_by((num_txns - transactions.len()) as u64);
+        NUM_BYTES_FILTERED_OUT_COUNT
+            .with_label_values(&[&self.processor_name])
+            .inc_by(num_filtered_bytes);
+        TransactionsPBResponse {
+            transactions,
+            chain_id: self.config.chain_id,
+            start_version,
+            end_version,
+            start_txn_timestamp,
+            end_txn_timestamp,
+            size_in_bytes,
+        }
+    }
+}
+
+/// Reads transactions from every file under a path, in order
+pub struct FileTransactionReader {
+    files: VecDeque<PathBuf>,
+    format: FileFormat,
+    current: Option<(PathBuf, TransactionIter)>,
+}
+
+impl FileTransactionReader {
+    pub fn new(path: &Path, format: FileFormat) -> Result<Self> {
+        let files = if path.is_dir() {
+            let mut files = std::fs::read_dir(path)
+                .with_context(|| format!("Failed to list {}", path.display()))?
+                .map(|entry| entry.map(|entry| entry.path()))
+                .collect::<std::io::Result<Vec<_>>>()
+                .with_context(|| format!("Failed to list {}", path.display()))?;
+            // Skips hidden files, such as files still being recorded, and the recording manifest
+            files.retain(|file| {
+                file.is_file()
+                    && !file
+                        .file_name()
+                        .map_or(true, |name| is_skipped(&name.to_string_lossy()))
+            });
+            files.sort();
+            files
+        } else {
+            vec![path.to_path_buf()]
+        };
+        Ok(Self {
+            files: files.into(),
+            format,
+            current: None,
+        })
+    }
+
+    /// Next transactions from `next_version` on, at most `batch_size` and none after
+    /// `ending_version`. Versions before `next_version` are skipped, so files may overlap, but a
+    /// missing version is an error as processors expect every version.
+    pub fn next_batch(
+        &mut self,
+        next_version: u64,
+        ending_version: Option<u64>,
+        batch_size: usize,
+    ) -> Result<Vec<Transaction>> {
+        let mut batch: Vec<Transaction> = vec![];
+        let mut expected_version = next_version;
+        while batch.len() < batch_size && ending_version.map_or(true, |end| expected_version <= end)
+        {
+            let transaction = match self.next() {
+                Some(transaction) => transaction?,
+                None => break,
+            };
+            if transaction.version < expected_version {
+                continue;
+            }
+            if transaction.version > expected_version {
+                bail!(
+                    "Transaction files are missing versions {} to {}",
+                    expected_version,
+                    transaction.version - 1
+                );
+            }
+            expected_version += 1;
+            batch.push(transaction);
+        }
+        Ok(batch)
+    }
+
+    fn open(&self, path: &Path) -> Result<TransactionIter> {
+        let file =
+            File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        if is_tar(&name) {
+            return read_tar(file, &name, self.format)
+                .with_context(|| format!("Failed to read tar archive {}", path.display()));
+        }
+        parse(file, &name, self.format)
+    }
+}
+
+fn is_tar(name: &str) -> bool {
+    name.ends_with(".tar") || name.ends_with(".tar.gz") || name.ends_with(".tgz")
+}
+
+fn is_skipped(name: &str) -> bool {
+    name.starts_with('.') || name == MANIFEST_FILE_NAME
+}
+
+/// Reads the files of an archive in name order. Entries come in the order they were added, so the
+/// whole archive is read before the first transaction is returned.
+fn read_tar(file: File, name: &str, format: FileFormat) -> Result<TransactionIter> {
+    let reader: Box<dyn Read + Send> = if name.ends_with(".tar") {
+        Box::new(BufReader::new(file))
+    } else {
+        Box::new(GzDecoder::new(BufReader::new(file)))
+    };
+    let mut archive = tar::Archive::new(reader);
+    let mut files = vec![];
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        if !entry.header().entry_type().is_file() {
+            continue;
+        }
+        let path = entry.path()?.into_owned();
+        let file_name = path
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .into_owned();
+        if is_skipped(&file_name) {
+            continue;
+        }
+        if is_tar(&file_name) {
+            bail!("{} is a tar archive inside a tar archive", path.display());
+        }
+        let mut contents = vec![];
+        entry
+            .read_to_end(&mut contents)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        files.push((path, contents));
+    }
+    files.sort_by(|(a, _), (b, _)| a.cmp(b));
+    Ok(Box::new(files.into_iter().flat_map(
+        move |(path, contents)| -> TransactionIter {
+            let name = path
+                .file_name()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .into_owned();
+            match parse(Cursor::new(contents), &name, format) {
+                Ok(transactions) => Box::new(transactions.map(move |transaction| {
+                    transaction.with_context(|| format!("In {}", path.display()))
+                })),
+                Err(e) => Box::new(std::iter::once(Err(e))),
+            }
+        },
+    )))
+}
+
+/// Reads a single file, named `name`
+fn parse(
+    reader: impl Read + Send + 'static,
+    name: &str,
+    format: FileFormat,
+) -> Result<TransactionIter> {
+    let is_gzip = name.ends_with(".gz");
+    let name = name.trim_end_matches(".gz");
+    let mut reader: Box<dyn BufRead + Send> = if is_gzip {
+        Box::new(BufReader::new(GzDecoder::new(reader)))
+    } else {
+        Box::new(BufReader::new(reader))
+    };
+    let is_json = match format {
+        FileFormat::Auto => name.ends_with(".json"),
+        FileFormat::Binary => false,
+        FileFormat::Json => true,
+    };
+    if !is_json {
+        return Ok(Box::new(std::iter::from_fn(move || {
+            read_length_delimited(&mut reader).transpose()
+        })));
+    }
+    // A file is either one JSON array or a sequence of JSON transactions
+    let is_array = loop {
+        let first = reader.fill_buf()?.first().copied();
+        match first {
+            Some(byte) if byte.is_ascii_whitespace() => reader.consume(1),
+            Some(byte) => break byte == b'[',
+            None => break false,
+        }
+    };
+    if is_array {
+        let transactions: Vec<Transaction> = serde_json::from_reader(reader)?;
+        Ok(Box::new(transactions.into_iter().map(Ok)))
+    } else {
+        Ok(Box::new(
+            serde_json::Deserializer::from_reader(reader)
+                .into_iter::<Transaction>()
+                .map(|transaction| transaction.map_err(anyhow::Error::from)),
+        ))
+    }
+}
+
+impl Iterator for FileTransactionReader {
+    type Item = Result<Transaction>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some((path, transactions)) = self.current.as_mut() {
+                match transactions.next() {
+                    Some(transaction) => {
+                        return Some(transaction.with_context(|| {
+                            format!("Failed to read a transaction from {}", path.display())
+                        }));
+                    },
+                    None => self.current = None,
+                }
+            }
+            let path = self.files.pop_front()?;
+            match self.open(&path) {
+                Ok(transactions) => self.current = Some((path, transactions)),
+                Err(e) => return Some(Err(e)),
+            }
+        }
+    }
+}
+
+/// Reads one varint length prefixed message, or None at the end of the file
+fn read_length_delimited(reader: &mut impl Read) -> Result<Option<Transaction>> {
+    let mut length: u64 = 0;
+    for shift in (0..64).step_by(7) {
+        let mut byte = [0u8; 1];
+        match reader.read_exact(&mut byte) {
+            Ok(()) => {},
+            Err(e) if e.kind() == ErrorKind::UnexpectedEof && shift == 0 => return Ok(None),
+            Err(e) => return Err(e).context("Truncated length prefix"),
+        }
+        length |= ((byte[0] & 0x7F) as u64) << shift;
+        if byte[0] & 0x80 == 0 {
+            let mut buf = vec![0; length as usize];
+            reader
+                .read_exact(&mut buf)
+                .context("Truncated transaction")?;
+            return Ok(Some(Transaction::decode(buf.as_slice())?));
+        }
+    }
+    bail!("Invalid length prefix")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use flate2::{write::GzEncoder, Compression};
+    use std::io::Write;
+
+    #[test]
+    fn test_read_batches() {
+        let dir = std::env::temp_dir().join(format!("file_source_test_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let txn = |version| Transaction {
+            version,
+            ..Transaction::default()
+        };
+        let mut binary = vec![];
+        for version in 0..3 {
+            txn(version).encode_length_delimited(&mut binary).unwrap();
+        }
+        File::create(dir.join("000.bin"))
+            .unwrap()
+            .write_all(&binary)
+            .unwrap();
+        // Overlaps the first file by one version
+        let json = serde_json::to_string(&vec![txn(2), txn(3), txn(4)]).unwrap();
+        File::create(dir.join("001.json"))
+            .unwrap()
+            .write_all(json.as_bytes())
+            .unwrap();
+
+        let mut reader = FileTransactionReader::new(&dir, FileFormat::Auto).unwrap();
+        let versions =
+            |batch: Vec<Transaction>| batch.iter().map(|t| t.version).collect::<Vec<_>>();
+        assert_eq!(versions(reader.next_batch(1, Some(3), 2).unwrap()), vec![
+            1, 2
+        ]);
+        assert_eq!(versions(reader.next_batch(3, Some(3), 2).unwrap()), vec![3]);
+        assert!(reader.next_batch(4, Some(3), 2).unwrap().is_empty());
+
+        let mut reader = FileTransactionReader::new(&dir, FileFormat::Auto).unwrap();
+        assert_eq!(versions(reader.next_batch(0, None, 10).unwrap()), vec![
+            0, 1, 2, 3, 4
+        ]);
+
+        // Entries are added out of name order, next to a directory and a hidden file
+        let mut archive = tar::Builder::new(GzEncoder::new(
+            File::create(dir.join("002.tar.gz")).unwrap(),
+            Compression::default(),
+        ));
+        let mut append = |path: &str, entry_type: tar::EntryType, contents: &[u8]| {
+            let mut header = tar::Header::new_gnu();
+            header.set_entry_type(entry_type);
+            header.set_size(contents.len() as u64);
+            header.set_mode(0o644);
+            archive.append_data(&mut header, path, contents).unwrap();
+        };
+        let json = serde_json::to_string(&vec![txn(7), txn(8)]).unwrap();
+        append("batches/001.json", tar::EntryType::Regular, json.as_bytes());
+        append("batches/", tar::EntryType::Directory, &[]);
+        append("batches/.002.bin", tar::EntryType::Regular, b"partial");
+        let mut binary = vec![];
+        for version in 4..7 {
+            txn(version).encode_length_delimited(&mut binary).unwrap();
+        }
+        append("batches/000.bin", tar::EntryType::Regular, &binary);
+        archive.into_inner().unwrap().finish().unwrap();
+
+        let mut reader = FileTransactionReader::new(&dir, FileFormat::Auto).unwrap();
+        assert_eq!(versions(reader.next_batch(3, None, 10).unwrap()), vec![
+            3, 4, 5, 6, 7, 8
+        ]);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
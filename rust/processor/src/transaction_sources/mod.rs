@@ -0,0 +1,164 @@
+// Copyright © Aptos Foundation
+// SPDX-License-Identifier: Apache-2.0
+
+//! Where the worker gets its transactions from. Every source fills the same
+//! `TransactionsPBResponse` channel, so processors don't know where the data came from.
+
+use crate::{
+    grpc_endpoints::GrpcEndpointSelector,
+    grpc_stream::{create_fetcher_loop, get_chain_id, TransactionsPBResponse},
+    transaction_filter::TransactionFilter,
+    transaction_sources::{file_source::FileTransactionSourceConfig, recorder::Recorder},
+};
+use anyhow::Result;
+use async_trait::async_trait;
+use kanal::AsyncSender;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+pub mod file_source;
+pub mod recorder;
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum TransactionSourceConfig {
+    // Stream from indexer_grpc_data_service_address and additional_grpc_endpoints
+    #[default]
+    Grpc,
+    // Replay transactions saved to local files
+    File(FileTransactionSourceConfig),
+}
+
+/// Versions to send, inclusive. Without an `ending_version` the range is open ended.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct FetchRange {
+    pub starting_version: u64,
+    pub ending_version: Option<u64>,
+}
+
+impl FetchRange {
+    /// Splits the versions from `starting_version` on into the gaps between `processed_ranges`,
+    /// followed by everything after the last processed range up to `ending_version`. Processed
+    /// ranges are inclusive `(start, end)` pairs, sorted and at or after `starting_version`.
+    pub fn around_processed_ranges(
+        starting_version: u64,
+        ending_version: Option<u64>,
+        processed_ranges: &[(u64, u64)],
+    ) -> Vec<Self> {
+        let mut ranges = vec![];
+        let mut next_version = starting_version;
+        for &(start_version, end_version) in processed_ranges {
+            if start_version > next_version {
+                ranges.push(Self {
+                    starting_version: next_version,
+                    ending_version: Some(start_version - 1),
+                });
+            }
+            next_version = next_version.max(end_version + 1);
+        }
+        if !matches!(ending_version, Some(ending_version) if next_version > ending_version) {
+            ranges.push(Self {
+                starting_version: next_version,
+                ending_version,
+            });
+        }
+        ranges
+    }
+}
+
+#[async_trait]
+pub trait TransactionSource: Send {
+    async fn chain_id(&mut self) -> Result<u64>;
+
+    /// Sends the transactions of each range in order into `sender`: from `starting_version` to
+    /// `ending_version`, or for as long as the source has any. Returns once everything sent has
+    /// been consumed.
+    async fn run(
+        self: Box<Self>,
+        sender: AsyncSender<TransactionsPBResponse>,
+        ranges: Vec<FetchRange>,
+    );
+}
+
+pub struct GrpcTransactionSource {
+    pub endpoints: GrpcEndpointSelector,
+    pub http2_ping_interval: Duration,
+    pub http2_ping_timeout: Duration,
+    pub reconnection_timeout: Duration,
+    pub response_item_timeout: Duration,
+    pub processor_name: String,
+    pub transaction_filter: TransactionFilter,
+    pub pb_channel_txn_chunk_size: usize,
+    // Dropped with the source, which lets the worker finish the recording
+    pub recorder: Option<Recorder>,
+}
+
+#[async_trait]
+impl TransactionSource for GrpcTransactionSource {
+    async fn chain_id(&mut self) -> Result<u64> {
+        Ok(get_chain_id(
+            &mut self.endpoints,
+            self.http2_ping_interval,
+            self.http2_ping_timeout,
+            self.reconnection_timeout,
+            self.processor_name.clone(),
+        )
+        .await)
+    }
+
+    async fn run(
+        mut self: Box<Self>,
+        sender: AsyncSender<TransactionsPBResponse>,
+        ranges: Vec<FetchRange>,
+    ) {
+        for range in ranges {
+            create_fetcher_loop(
+                sender.clone(),
+                &mut self.endpoints,
+                self.http2_ping_interval,
+                self.http2_ping_timeout,
+                self.reconnection_timeout,
+                self.response_item_timeout,
+                range.starting_version,
+                range.ending_version,
+                self.processor_name.clone(),
+                self.transaction_filter.clone(),
+                self.pb_channel_txn_chunk_size,
+                self.recorder.as_ref(),
+            )
+            .await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn range(starting_version: u64, ending_version: Option<u64>) -> FetchRange {
+        FetchRange {
+            starting_version,
+            ending_version,
+        }
+    }
+
+    #[test]
+    fn test_fetch_ranges_around_processed_ranges() {
+        assert_eq!(FetchRange::around_processed_ranges(100, None, &[]), vec![
+            range(100, None)
+        ]);
+        assert_eq!(
+            FetchRange::around_processed_ranges(100, None, &[(150, 199), (300, 349)]),
+            vec![
+                range(100, Some(149)),
+                range(200, Some(299)),
+                range(350, None)
+            ]
+        );
+        assert_eq!(
+            FetchRange::around_processed_ranges(100, Some(349), &[(100, 199), (300, 349)]),
+            vec![range(200, Some(299))]
+        );
+    }
+}
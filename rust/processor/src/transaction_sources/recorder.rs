@@ -0,0 +1,318 @@
+// Copyright © Aptos Foundation
+// SPDX-License-Identifier: Apache-2.0
+
+//! Writes the transactions received from GRPC to a local archive that the file source can replay.
+//! Files are gzip compressed length-delimited protos named after their version range, e.g.
+//! `00000000000000001000-00000000000000001999.bin.gz`. The file being written is hidden until it
+//! is complete, and `manifest.json` lists the chain id and every complete file.
+
+use anyhow::{bail, Context, Result};
+use aptos_protos::transaction::v1::Transaction;
+use flate2::{write::GzEncoder, Compression};
+use prost::Message;
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::File,
+    io::{BufWriter, Write},
+    path::PathBuf,
+};
+use tokio::sync::mpsc;
+use tracing::{error, info};
+
+pub const MANIFEST_FILE_NAME: &str = "manifest.json";
+// Batches waiting to be written before the fetcher waits on the disk
+const RECORDER_CHANNEL_SIZE: usize = 100;
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct RecordConfig {
+    // Directory of the archive, created if missing. Recording into an existing archive appends to it.
+    pub path: PathBuf,
+    #[serde(default = "RecordConfig::default_max_transactions_per_file")]
+    pub max_transactions_per_file: u64,
+}
+
+impl RecordConfig {
+    pub const fn default_max_transactions_per_file() -> u64 {
+        100_000
+    }
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct RecordManifest {
+    pub chain_id: Option<u64>,
+    pub files: Vec<RecordedFile>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct RecordedFile {
+    pub file_name: String,
+    pub start_version: u64,
+    pub end_version: u64,
+}
+
+struct RecordedBatch {
+    chain_id: u64,
+    start_version: u64,
+    end_version: u64,
+    // Length-delimited transactions
+    bytes: Vec<u8>,
+}
+
+/// Handle used by the fetcher. Encoding happens on the caller, compression and writes on a
+/// dedicated thread.
+#[derive(Clone)]
+pub struct Recorder {
+    sender: mpsc::Sender<RecordedBatch>,
+}
+
+/// The writer thread behind every `Recorder` of a recording
+pub struct Recording {
+    writer_thread: std::thread::JoinHandle<()>,
+}
+
+impl Recorder {
+    pub fn start(config: RecordConfig, processor_name: &str) -> Result<(Self, Recording)> {
+        let writer = ArchiveWriter::open(config)?;
+        let (sender, receiver) = mpsc::channel(RECORDER_CHANNEL_SIZE);
+        let processor_name = processor_name.to_string();
+        let writer_thread = std::thread::spawn(move || writer.run(receiver, processor_name));
+        Ok((Self { sender }, Recording { writer_thread }))
+    }
+
+    pub async fn record(&self, chain_id: u64, transactions: &[Transaction]) {
+        let (first, last) = match (transactions.first(), transactions.last()) {
+            (Some(first), Some(last)) => (first.version, last.version),
+            _ => return,
+        };
+        let mut bytes = vec![];
+        for transaction in transactions {
+            transaction
+                .encode_length_delimited(&mut bytes)
+                .expect("Encoding to a Vec can't fail");
+        }
+        // The writer only stops after an error it already logged
+        let _ = self
+            .sender
+            .send(RecordedBatch {
+                chain_id,
+                start_version: first,
+                end_version: last,
+                bytes,
+            })
+            .await;
+    }
+}
+
+impl Recording {
+    /// Waits for the queued batches to be written and the last file to be completed. The writer
+    /// stops once every `Recorder` is dropped, so drop them first.
+    pub async fn finish(self) {
+        let writer_thread = self.writer_thread;
+        let _ = tokio::task::spawn_blocking(move || writer_thread.join()).await;
+    }
+}
+
+struct OpenFile {
+    encoder: GzEncoder<BufWriter<File>>,
+    partial_path: PathBuf,
+    start_version: u64,
+    end_version: u64,
+    num_transactions: u64,
+}
+
+struct ArchiveWriter {
+    config: RecordConfig,
+    manifest: RecordManifest,
+    current: Option<OpenFile>,
+}
+
+impl ArchiveWriter {
+    fn open(config: RecordConfig) -> Result<Self> {
+        std::fs::create_dir_all(&config.path)
+            .with_context(|| format!("Failed to create {}", config.path.display()))?;
+        let manifest_path = config.path.join(MANIFEST_FILE_NAME);
+        let manifest = if manifest_path.exists() {
+            let manifest = std::fs::read(&manifest_path)
+                .with_context(|| format!("Failed to read {}", manifest_path.display()))?;
+            serde_json::from_slice(&manifest)
+                .with_context(|| format!("Invalid manifest {}", manifest_path.display()))?
+        } else {
+            RecordManifest::default()
+        };
+        Ok(Self {
+            config,
+            manifest,
+            current: None,
+        })
+    }
+
+    fn run(mut self, mut receiver: mpsc::Receiver<RecordedBatch>, processor_name: String) {
+        while let Some(batch) = receiver.blocking_recv() {
+            if let Err(e) = self.write(batch) {
+                error!(
+                    processor_name = processor_name,
+                    path = self.config.path.display().to_string(),
+                    error = ?e,
+                    "[Parser] Failed to record transactions, recording stopped"
+                );
+                return;
+            }
+        }
+        match self.complete_file() {
+            Ok(()) => info!(
+                processor_name = processor_name,
+                path = self.config.path.display().to_string(),
+                "[Parser] Finished recording transactions"
+            ),
+            Err(e) => error!(
+                processor_name = processor_name,
+                path = self.config.path.display().to_string(),
+                error = ?e,
+                "[Parser] Failed to complete the recorded file"
+            ),
+        }
+    }
+
+    fn write(&mut self, batch: RecordedBatch) -> Result<()> {
+        match self.manifest.chain_id {
+            Some(chain_id) if chain_id != batch.chain_id => bail!(
+                "Archive holds chain {} but the stream is chain {}",
+                chain_id,
+                batch.chain_id
+            ),
+            Some(_) => {},
+            None => self.manifest.chain_id = Some(batch.chain_id),
+        }
+        // A file covers one contiguous range, so a gap, such as a restart at another version,
+        // starts a new one
+        if let Some(current) = &self.current {
+            if batch.start_version != current.end_version + 1 {
+                self.complete_file()?;
+            }
+        }
+        if self.current.is_none() {
+            let partial_path = self
+                .config
+                .path
+                .join(format!(".{:020}.partial", batch.start_version));
+            let file = File::create(&partial_path)
+                .with_context(|| format!("Failed to create {}", partial_path.display()))?;
+            self.current = Some(OpenFile {
+                encoder: GzEncoder::new(BufWriter::new(file), Compression::default()),
+                partial_path,
+                start_version: batch.start_version,
+                end_version: batch.end_version,
+                num_transactions: 0,
+            });
+        }
+        let current = self.current.as_mut().unwrap();
+        current.encoder.write_all(&batch.bytes)?;
+        current.end_version = batch.end_version;
+        current.num_transactions += batch.end_version - batch.start_version + 1;
+        if current.num_transactions >= self.config.max_transactions_per_file {
+            self.complete_file()?;
+        }
+        Ok(())
+    }
+
+    /// Renames the file being written to its version range and adds it to the manifest
+    fn complete_file(&mut self) -> Result<()> {
+        let current = match self.current.take() {
+            Some(current) => current,
+            None => return Ok(()),
+        };
+        current.encoder.finish()?.flush()?;
+        let file_name = format!(
+            "{:020}-{:020}.bin.gz",
+            current.start_version, current.end_version
+        );
+        std::fs::rename(&current.partial_path, self.config.path.join(&file_name))?;
+        self.manifest.files.push(RecordedFile {
+            file_name,
+            start_version: current.start_version,
+            end_version: current.end_version,
+        });
+        // Replace the manifest atomically so a crash never leaves a truncated one
+        let manifest_path = self.config.path.join(MANIFEST_FILE_NAME);
+        let tmp_path = self
+            .config
+            .path
+            .join(format!(".{}.tmp", MANIFEST_FILE_NAME));
+        std::fs::write(&tmp_path, serde_json::to_vec_pretty(&self.manifest)?)?;
+        std::fs::rename(&tmp_path, &manifest_path)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::transaction_sources::file_source::{FileFormat, FileTransactionReader};
+
+    fn batch(version: u64) -> RecordedBatch {
+        let mut bytes = vec![];
+        Transaction {
+            version,
+            ..Transaction::default()
+        }
+        .encode_length_delimited(&mut bytes)
+        .unwrap();
+        RecordedBatch {
+            chain_id: 1,
+            start_version: version,
+            end_version: version,
+            bytes,
+        }
+    }
+
+    fn file_ranges(writer: &ArchiveWriter) -> Vec<(u64, u64)> {
+        writer
+            .manifest
+            .files
+            .iter()
+            .map(|file| (file.start_version, file.end_version))
+            .collect()
+    }
+
+    #[test]
+    fn test_recorded_archive_replays() {
+        let dir = std::env::temp_dir().join(format!("recorder_test_{}", std::process::id()));
+        let mut writer = ArchiveWriter::open(RecordConfig {
+            path: dir.clone(),
+            max_transactions_per_file: 2,
+        })
+        .unwrap();
+        for version in 10..15 {
+            writer.write(batch(version)).unwrap();
+        }
+        writer.complete_file().unwrap();
+        assert_eq!(file_ranges(&writer), vec![(10, 11), (12, 13), (14, 14)]);
+
+        let mut reader = FileTransactionReader::new(&dir, FileFormat::Auto).unwrap();
+        let versions = reader
+            .next_batch(10, None, 100)
+            .unwrap()
+            .iter()
+            .map(|transaction| transaction.version)
+            .collect::<Vec<_>>();
+        assert_eq!(versions, vec![10, 11, 12, 13, 14]);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_gap_starts_new_file() {
+        let dir = std::env::temp_dir().join(format!("recorder_gap_test_{}", std::process::id()));
+        let mut writer = ArchiveWriter::open(RecordConfig {
+            path: dir.clone(),
+            max_transactions_per_file: 10,
+        })
+        .unwrap();
+        for version in [10, 11, 20, 21, 5] {
+            writer.write(batch(version)).unwrap();
+        }
+        writer.complete_file().unwrap();
+        assert_eq!(file_ranges(&writer), vec![(10, 11), (20, 21), (5, 5)]);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
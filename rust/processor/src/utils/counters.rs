@@ -177,6 +177,136 @@ pub static NUM_TRANSACTIONS_FILTERED_OUT_COUNT: Lazy<IntCounterVec> = Lazy::new(
     .unwrap()
 });
 
+/// Bytes of transactions filtered out after download, what server side filtering would save
+pub static NUM_BYTES_FILTERED_OUT_COUNT: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        "indexer_processor_num_bytes_filtered_out_count",
+        "Number of bytes of transactions filtered out",
+        &["processor_name"]
+    )
+    .unwrap()
+});
+
+/// Count of transactions matched by each entry function rule
+pub static ENTRY_FUNCTION_RULE_MATCHED_COUNT: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        "indexer_processor_entry_function_rule_matched_count",
+        "Number of transactions matched by an entry function rule",
+        &["processor_name", "rule_name", "action"]
+    )
+    .unwrap()
+});
+
+/// Count of mints seen for collections that aren't mapped to the catalog
+pub static UNKNOWN_COLLECTION_MINT_COUNT: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        "indexer_processor_unknown_collection_mint_count",
+        "Number of mints seen for unknown collections",
+        &["processor_name"]
+    )
+    .unwrap()
+});
+
+/// Count of batches written to the dead letter table after exhausting insert retries
+pub static DEAD_LETTER_BATCH_COUNT: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        "indexer_processor_dead_letter_batch_count",
+        "Number of batches dead lettered after failing to insert",
+        &["processor_name"]
+    )
+    .unwrap()
+});
+
+/// Count of dead lettered batches written on a later startup
+pub static DEAD_LETTER_REPLAYED_BATCH_COUNT: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        "indexer_processor_dead_letter_replayed_batch_count",
+        "Number of dead lettered batches replayed on startup",
+        &["processor_name"]
+    )
+    .unwrap()
+});
+
+/// Count of marketplace events that matched a configured event type but failed to decode
+pub static MARKETPLACE_DECODE_ERROR_COUNT: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        "indexer_processor_marketplace_decode_error_count",
+        "Number of marketplace events that failed to decode",
+        &["processor_name", "marketplace", "event_type"]
+    )
+    .unwrap()
+});
+
+/// Count of nft_points rule evaluations that failed, e.g. because a path is missing
+pub static NFT_POINTS_RULE_ERROR_COUNT: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        "indexer_processor_nft_points_rule_error_count",
+        "Number of nft_points rule evaluations that failed",
+        &["rule_name", "rules_version"]
+    )
+    .unwrap()
+});
+
+/// Count of rows deleted by retention policies
+pub static RETENTION_DELETED_ROWS_COUNT: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        "indexer_retention_deleted_rows_count",
+        "Number of rows deleted by retention policies",
+        &["table"]
+    )
+    .unwrap()
+});
+
+/// Rows a retention policy would delete, set by dry runs
+pub static RETENTION_ELIGIBLE_ROWS: Lazy<IntGaugeVec> = Lazy::new(|| {
+    register_int_gauge_vec!(
+        "indexer_retention_eligible_rows",
+        "Number of rows a retention policy would delete",
+        &["table"]
+    )
+    .unwrap()
+});
+
+/// Duration of the last run of each retention policy
+pub static RETENTION_RUN_DURATION_IN_SECS: Lazy<GaugeVec> = Lazy::new(|| {
+    register_gauge_vec!(
+        "indexer_retention_run_duration_in_secs",
+        "Duration of the last run of a retention policy",
+        &["table"]
+    )
+    .unwrap()
+});
+
+/// 1 for the GRPC endpoint currently streaming, 0 for the others
+pub static GRPC_ACTIVE_ENDPOINT: Lazy<IntGaugeVec> = Lazy::new(|| {
+    register_int_gauge_vec!(
+        "indexer_processor_grpc_active_endpoint",
+        "Whether the GRPC endpoint is the one currently streaming",
+        &["processor_name", "endpoint"]
+    )
+    .unwrap()
+});
+
+/// Count of connection and stream errors per GRPC endpoint
+pub static GRPC_ENDPOINT_ERROR_COUNT: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        "indexer_processor_grpc_endpoint_error_count",
+        "Number of connection and stream errors per GRPC endpoint",
+        &["processor_name", "endpoint"]
+    )
+    .unwrap()
+});
+
+/// Count of switches between GRPC endpoints
+pub static GRPC_ENDPOINT_SWITCH_COUNT: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        "indexer_processor_grpc_endpoint_switch_count",
+        "Number of switches between GRPC endpoints",
+        &["processor_name", "from_endpoint", "to_endpoint"]
+    )
+    .unwrap()
+});
+
 /// Size of the channel containing transactions fetched from GRPC, waiting to be processed
 pub static FETCHER_THREAD_CHANNEL_SIZE: Lazy<IntGaugeVec> = Lazy::new(|| {
     register_int_gauge_vec!(
@@ -217,6 +347,16 @@ pub static SINGLE_BATCH_DB_INSERTION_TIME_IN_SECS: Lazy<HistogramVec> = Lazy::ne
     .unwrap()
 });
 
+/// Rows inserted or updated per table
+pub static DB_ROWS_WRITTEN_COUNT: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        "indexer_processor_db_rows_written_count",
+        "Number of rows inserted or updated per table",
+        &["table_name"]
+    )
+    .unwrap()
+});
+
 /// Transaction timestamp in unixtime
 pub static TRANSACTION_UNIX_TIMESTAMP: Lazy<GaugeVec> = Lazy::new(|| {
     register_gauge_vec!(
@@ -4,7 +4,7 @@
 //! Database-related functions
 #![allow(clippy::extra_unused_lifetimes)]
 
-use crate::utils::util::remove_null_bytes;
+use crate::utils::{counters::DB_ROWS_WRITTEN_COUNT, util::remove_null_bytes};
 use ahash::AHashMap;
 use diesel::{
     query_builder::{AstPass, Query, QueryFragment},
@@ -19,6 +19,8 @@ use diesel_async::{
 };
 use diesel_migrations::{embed_migrations, EmbeddedMigrations, MigrationHarness};
 use futures_util::{future::BoxFuture, FutureExt};
+use once_cell::sync::Lazy;
+use regex::Regex;
 use std::sync::Arc;
 
 pub type Backend = diesel::pg::Pg;
@@ -32,6 +34,15 @@ pub const MIGRATIONS: EmbeddedMigrations = embed_migrations!("src/db/postgres/mi
 
 pub const DEFAULT_MAX_POOL_SIZE: u32 = 300;
 
+// Captures the table a query writes to, e.g. `INSERT INTO "events" (...`. Deletes aren't
+// written rows, so they aren't matched.
+static WRITTEN_TABLE_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r#"^\s*(?:INSERT INTO|UPDATE)\s+"?(\w+)"?"#).unwrap());
+// Every statement of a query with a `WITH` clause. The affected row count is that of the last one,
+// so deletes are matched too, to tell when the count is of deleted rows.
+static CTE_WRITTEN_TABLE_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r#"\b(INSERT INTO|UPDATE|DELETE FROM)\s+"?(\w+)"?"#).unwrap());
+
 #[derive(QueryId)]
 /// Using this will append a where clause at the end of the string upsert function, e.g.
 /// INSERT INTO ... ON CONFLICT DO UPDATE SET ... WHERE "transaction_version" = excluded."transaction_version"
@@ -187,9 +198,41 @@ where
     if let Err(ref e) = res {
         tracing::warn!("Error running query: {:?}\n{:?}", e, debug_string);
     }
+    record_rows_written(&original_query, &res);
     res
 }
 
+fn record_rows_written(query: &str, res: &QueryResult<usize>) {
+    if let (Ok(rows), Some(table)) = (res, written_table(query)) {
+        count_rows_written(table, *rows);
+    }
+}
+
+/// Table whose rows a query's affected row count refers to
+fn written_table(query: &str) -> Option<&str> {
+    if let Some(captures) = WRITTEN_TABLE_REGEX.captures(query) {
+        return captures.get(1).map(|table| table.as_str());
+    }
+    if !query.trim_start().to_uppercase().starts_with("WITH") {
+        return None;
+    }
+    let (statement, table) = CTE_WRITTEN_TABLE_REGEX
+        .captures_iter(query)
+        .filter_map(|captures| Some((captures.get(1)?.as_str(), captures.get(2)?.as_str())))
+        // `ON CONFLICT ... DO UPDATE SET` isn't a statement of its own
+        .filter(|(_, table)| !table.eq_ignore_ascii_case("SET"))
+        .last()?;
+    (statement != "DELETE FROM").then_some(table)
+}
+
+/// Adds to the rows written to `table`, for writes that don't go through
+/// `execute_with_better_error` or `execute_with_better_error_conn`
+pub fn count_rows_written(table: &str, rows: usize) {
+    DB_ROWS_WRITTEN_COUNT
+        .with_label_values(&[table])
+        .inc_by(rows as u64);
+}
+
 /// Returns the entry for the config hashmap, or the default field count for the insert
 /// Given diesel has a limit of how many parameters can be inserted in a single operation (u16::MAX),
 /// we default to chunk an array of items based on how many columns are in the table.
@@ -227,6 +270,7 @@ where
     if let Err(ref e) = res {
         tracing::warn!("Error running query: {:?}\n{:?}", e, debug_string);
     }
+    record_rows_written(&original_query, &res);
     res
 }
 
@@ -283,3 +327,53 @@ where
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_written_table() {
+        assert_eq!(
+            written_table(r#"INSERT INTO "events" ("sequence_number") VALUES ($1)"#),
+            Some("events")
+        );
+        assert_eq!(
+            written_table("\n  UPDATE current_collection_stats s SET volume_24h = 0"),
+            Some("current_collection_stats")
+        );
+        assert_eq!(
+            written_table(
+                "WITH added AS (INSERT INTO collection_holders SELECT 1 ON CONFLICT DO NOTHING \
+                 RETURNING 1) UPDATE current_collection_stats s SET unique_holders = 1"
+            ),
+            Some("current_collection_stats")
+        );
+        assert_eq!(
+            written_table(
+                "WITH x AS (SELECT 1) INSERT INTO accounts SELECT 1 \
+                 ON CONFLICT (account_address) DO UPDATE SET has_signed = TRUE"
+            ),
+            Some("accounts")
+        );
+        assert_eq!(written_table("SELECT pg_advisory_xact_lock(1)"), None);
+        assert_eq!(
+            written_table("DELETE FROM nft_holdings WHERE amount = 0"),
+            None
+        );
+        assert_eq!(
+            written_table(
+                "WITH moved AS (INSERT INTO archived_events SELECT 1 RETURNING 1) \
+                 DELETE FROM events WHERE transaction_version < 10"
+            ),
+            None
+        );
+        assert_eq!(
+            written_table(
+                "WITH removed AS (DELETE FROM events WHERE transaction_version < 10 RETURNING *) \
+                 INSERT INTO archived_events SELECT * FROM removed"
+            ),
+            Some("archived_events")
+        );
+    }
+}
@@ -0,0 +1,56 @@
+// Copyright © Aptos Foundation
+// SPDX-License-Identifier: Apache-2.0
+
+//! `$.`-prefixed paths into JSON values, e.g. `$.data.price` or `$.args.0`. Array elements are
+//! addressed by index.
+
+use anyhow::{bail, Context, Result};
+use serde_json::Value;
+
+/// Parses `$.a.b.0` into its segments
+pub fn parse_path(path: &str) -> Result<Vec<String>> {
+    let segments = match path.strip_prefix("$.") {
+        Some(rest) => rest.split('.').map(|s| s.to_string()).collect::<Vec<_>>(),
+        None => bail!("Path {:?} must start with $.", path),
+    };
+    if segments.iter().any(|s| s.is_empty()) {
+        bail!("Path {:?} has an empty segment", path);
+    }
+    Ok(segments)
+}
+
+pub fn format_path(path: &[String]) -> String {
+    format!("$.{}", path.join("."))
+}
+
+pub fn lookup<'a>(context: &'a Value, path: &[String]) -> Result<&'a Value> {
+    let mut value = context;
+    for segment in path {
+        let next = match value {
+            Value::Object(map) => map.get(segment),
+            Value::Array(items) => segment.parse::<usize>().ok().and_then(|i| items.get(i)),
+            _ => None,
+        };
+        value = next.with_context(|| format!("{} not found", format_path(path)))?;
+    }
+    Ok(value)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_paths() {
+        assert_eq!(parse_path("$.data.price").unwrap(), vec!["data", "price"]);
+        assert!(parse_path("data.price").is_err());
+        assert!(parse_path("$.data..price").is_err());
+        assert_eq!(format_path(&parse_path("$.args.0").unwrap()), "$.args.0");
+
+        let context = serde_json::json!({"data": {"items": [{"amount": 5}]}});
+        let amount = lookup(&context, &parse_path("$.data.items.0.amount").unwrap()).unwrap();
+        assert_eq!(amount, &serde_json::json!(5));
+        assert!(lookup(&context, &parse_path("$.data.items.1").unwrap()).is_err());
+        assert!(lookup(&context, &parse_path("$.data.missing").unwrap()).is_err());
+    }
+}
@@ -3,4 +3,8 @@
 
 pub mod counters;
 pub mod database;
+pub mod json_path;
+pub mod pattern;
+pub mod run_summary;
+pub mod shutdown;
 pub mod util;
@@ -0,0 +1,132 @@
+// Copyright © Aptos Foundation
+// SPDX-License-Identifier: Apache-2.0
+
+//! `*` wildcard patterns shared by the transaction filter and the processor configs that select
+//! entry functions and Move types.
+
+/// Matches `value` against a pattern where `*` matches any sequence of characters
+pub fn glob_match(pattern: &str, value: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let mut rest = match value.strip_prefix(first) {
+        Some(rest) => rest,
+        None => return false,
+    };
+    let parts = parts.collect::<Vec<_>>();
+    let (last, middle) = match parts.split_last() {
+        Some(split) => split,
+        // No wildcard
+        None => return rest.is_empty(),
+    };
+    for part in middle {
+        match rest.find(part) {
+            Some(index) => rest = &rest[index + part.len()..],
+            None => return false,
+        }
+    }
+    rest.ends_with(last)
+}
+
+/// Matches a Move type against a pattern. A pattern without type arguments also matches the type
+/// with any type arguments. Both are expected to have standardized addresses, see
+/// `standardize_type_addresses`.
+pub fn type_matches(pattern: &str, type_str: &str) -> bool {
+    if !pattern.contains('<') {
+        if let Some((outer, _)) = type_str.split_once('<') {
+            return glob_match(pattern, outer);
+        }
+    }
+    glob_match(pattern, type_str)
+}
+
+/// Cheap check that a Move type could match a pattern, comparing only the leading address and
+/// module. Unlike `type_matches` the type needn't be standardized, so types from other modules are
+/// ruled out before `standardize_type_addresses` allocates.
+pub fn type_may_match(pattern: &str, type_str: &str) -> bool {
+    let mut pattern_parts = pattern.splitn(3, "::");
+    let mut type_parts = type_str.splitn(3, "::");
+    let (pattern_address, type_address) = match (pattern_parts.next(), type_parts.next()) {
+        (Some(pattern_address), Some(type_address)) => (pattern_address, type_address),
+        _ => return true,
+    };
+    if !pattern_address.contains('*') && !same_address(pattern_address, type_address) {
+        return false;
+    }
+    match (pattern_parts.next(), type_parts.next()) {
+        (Some(pattern_module), Some(type_module)) if !pattern_module.contains('*') => {
+            pattern_module == type_module
+        },
+        _ => true,
+    }
+}
+
+/// Compares hex addresses ignoring case and leading zeros
+fn same_address(a: &str, b: &str) -> bool {
+    let trim = |address: &str| {
+        address
+            .strip_prefix("0x")
+            .unwrap_or(address)
+            .trim_start_matches('0')
+    };
+    trim(a).eq_ignore_ascii_case(trim(b))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match("0x1::coin::transfer", "0x1::coin::transfer"));
+        assert!(!glob_match(
+            "0x1::coin::transfer",
+            "0x1::coin::transfer_all"
+        ));
+        assert!(glob_match("0x1::coin::*", "0x1::coin::transfer"));
+        assert!(glob_match("*::coin::*", "0x1::coin::transfer"));
+        assert!(!glob_match("*::coin::*", "0x1::aptos_coin::mint"));
+        assert!(glob_match("*ab*ab", "abab"));
+        assert!(!glob_match("*ab*ab", "aab"));
+    }
+
+    #[test]
+    fn test_type_matches() {
+        assert!(type_matches(
+            "0x1::coin::CoinStore",
+            "0x1::coin::CoinStore<0x1::aptos_coin::AptosCoin>"
+        ));
+        assert!(type_matches(
+            "0x1::coin::CoinStore<0x1::aptos_coin::*>",
+            "0x1::coin::CoinStore<0x1::aptos_coin::AptosCoin>"
+        ));
+        assert!(!type_matches(
+            "0x1::coin::CoinStore<0x1::aptos_coin::*>",
+            "0x1::coin::CoinStore<0xcafe::token::Token>"
+        ));
+        assert!(!type_matches(
+            "0x1::coin::CoinStore<0x1::aptos_coin::AptosCoin>",
+            "0x1::coin::CoinStore"
+        ));
+        assert!(!type_matches(
+            "*::Token",
+            "0x1::coin::CoinStore<0xcafe::token::Token>"
+        ));
+    }
+
+    #[test]
+    fn test_type_may_match() {
+        let coin_store = format!("0x{:0>64}::coin::CoinStore", "1");
+        assert!(type_may_match(
+            &coin_store,
+            "0x1::coin::CoinStore<0x1::aptos_coin::AptosCoin>"
+        ));
+        assert!(type_may_match(&coin_store, "0x01::coin::Other"));
+        assert!(!type_may_match(&coin_store, "0x1::aptos_coin::AptosCoin"));
+        assert!(!type_may_match(&coin_store, "0xcafe::coin::CoinStore"));
+        assert!(type_may_match("*::coin::*", "0xcafe::coin::CoinStore"));
+        assert!(type_may_match(
+            &format!("0x{:0>64}::*", "cafe"),
+            "0xCAFE::token::Token"
+        ));
+    }
+}
@@ -0,0 +1,116 @@
+// Copyright © Aptos Foundation
+// SPDX-License-Identifier: Apache-2.0
+
+//! Summary of a worker run, printed as JSON when the worker stops so bounded runs (e.g. backfills
+//! with an `ending_version`) can be checked by whatever launched them.
+
+use crate::utils::counters::DB_ROWS_WRITTEN_COUNT;
+use anyhow::{Context, Result};
+use prometheus::core::Collector;
+use serde::Serialize;
+use std::{collections::BTreeMap, path::Path, time::Duration};
+
+#[derive(Clone, Debug, Serialize)]
+pub struct RunSummary {
+    pub processor_name: String,
+    pub chain_id: u64,
+    pub starting_version: u64,
+    pub ending_version: Option<u64>,
+    // End of the contiguous range processed, None if nothing was
+    pub last_processed_version: Option<u64>,
+    pub versions_processed: u64,
+    // Whether every version up to ending_version was processed
+    pub complete: bool,
+    // Rows inserted or updated in postgres, by table
+    pub rows_written: BTreeMap<String, u64>,
+    pub duration_secs: f64,
+    pub versions_per_sec: f64,
+}
+
+impl RunSummary {
+    pub fn new(
+        processor_name: &str,
+        chain_id: u64,
+        starting_version: u64,
+        ending_version: Option<u64>,
+        last_processed_version: Option<u64>,
+        duration: Duration,
+    ) -> Self {
+        let versions_processed = last_processed_version
+            .map(|version| (version + 1).saturating_sub(starting_version))
+            .unwrap_or(0);
+        let complete = match (ending_version, last_processed_version) {
+            (Some(ending_version), Some(version)) => version >= ending_version,
+            _ => false,
+        };
+        let duration_secs = duration.as_secs_f64();
+        let versions_per_sec = if duration_secs > 0.0 {
+            versions_processed as f64 / duration_secs
+        } else {
+            0.0
+        };
+        Self {
+            processor_name: processor_name.to_string(),
+            chain_id,
+            starting_version,
+            ending_version,
+            last_processed_version,
+            versions_processed,
+            complete,
+            rows_written: rows_written_per_table(),
+            duration_secs,
+            versions_per_sec,
+        }
+    }
+
+    /// Prints the summary to stdout and, if a path is given, writes it there as well
+    pub fn report(&self, path: Option<&Path>) -> Result<()> {
+        let json = serde_json::to_string_pretty(self)?;
+        println!("{}", json);
+        if let Some(path) = path {
+            std::fs::write(path, &json)
+                .with_context(|| format!("Failed to write run summary to {}", path.display()))?;
+        }
+        Ok(())
+    }
+}
+
+fn rows_written_per_table() -> BTreeMap<String, u64> {
+    let mut rows_written = BTreeMap::new();
+    for family in DB_ROWS_WRITTEN_COUNT.collect() {
+        for metric in family.get_metric() {
+            let table_name = metric
+                .get_label()
+                .iter()
+                .find(|label| label.get_name() == "table_name")
+                .map(|label| label.get_value().to_string());
+            if let Some(table_name) = table_name {
+                *rows_written.entry(table_name).or_insert(0) +=
+                    metric.get_counter().get_value() as u64;
+            }
+        }
+    }
+    rows_written
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_run_summary_versions() {
+        let summary = RunSummary::new("test", 1, 100, Some(199), Some(199), Duration::from_secs(4));
+        assert_eq!(summary.versions_processed, 100);
+        assert!(summary.complete);
+        assert_eq!(summary.versions_per_sec, 25.0);
+
+        let summary = RunSummary::new("test", 1, 100, Some(199), Some(149), Duration::ZERO);
+        assert_eq!(summary.versions_processed, 50);
+        assert!(!summary.complete);
+        assert_eq!(summary.versions_per_sec, 0.0);
+
+        let summary = RunSummary::new("test", 1, 100, None, None, Duration::from_secs(1));
+        assert_eq!(summary.versions_processed, 0);
+        assert!(!summary.complete);
+    }
+}
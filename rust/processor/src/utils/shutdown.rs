@@ -0,0 +1,40 @@
+// Copyright © Aptos Foundation
+// SPDX-License-Identifier: Apache-2.0
+
+//! Process wide shutdown state. The worker triggers it once the processor tasks have stopped, so
+//! components that buffer data, such as the parquet handlers, flush what they hold and exit.
+
+use once_cell::sync::Lazy;
+use tokio::sync::watch;
+
+static SHUTDOWN: Lazy<watch::Sender<bool>> = Lazy::new(|| watch::channel(false).0);
+
+/// `changed()` on the receiver resolves once shutdown is triggered
+pub fn subscribe() -> watch::Receiver<bool> {
+    SHUTDOWN.subscribe()
+}
+
+pub fn trigger() {
+    SHUTDOWN.send_replace(true);
+}
+
+pub fn is_triggered() -> bool {
+    *SHUTDOWN.borrow()
+}
+
+/// Resolves on SIGTERM or Ctrl-C
+pub async fn wait_for_signal() {
+    #[cfg(unix)]
+    {
+        let mut sigterm = tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+            .expect("Failed to install the SIGTERM handler");
+        tokio::select! {
+            _ = sigterm.recv() => {},
+            _ = tokio::signal::ctrl_c() => {},
+        }
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = tokio::signal::ctrl_c().await;
+    }
+}
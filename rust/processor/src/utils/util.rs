@@ -17,6 +17,7 @@ use aptos_protos::{
 use bigdecimal::{BigDecimal, Signed, ToPrimitive, Zero};
 use chrono::NaiveDateTime;
 use lazy_static::lazy_static;
+use regex::Regex;
 use serde::{Deserialize, Deserializer, Serialize};
 use serde_json::Value;
 use sha2::Digest;
@@ -38,6 +39,8 @@ lazy_static! {
     };
     pub static ref APT_METADATA_ADDRESS_HEX: String =
         format!("0x{}", hex::encode(*APT_METADATA_ADDRESS_RAW));
+    // Address literals in type strings and type patterns, including a trailing wildcard
+    pub static ref TYPE_ADDRESS_REGEX: Regex = Regex::new(r"\b0x[0-9a-fA-F]+\*?").unwrap();
 }
 // Supporting structs to get clean payload without escaped strings
 #[derive(Debug, Deserialize, Serialize)]
@@ -75,6 +78,31 @@ pub fn standardize_address(handle: &str) -> String {
     }
 }
 
+/// Standardizes the address of `address::module::function` so config and chain data compare equal
+pub fn standardize_entry_function_id(entry_function_id: &str) -> String {
+    match entry_function_id.split_once("::") {
+        Some((address, rest)) => {
+            format!("{}::{}", standardize_address(&address.to_lowercase()), rest)
+        },
+        None => entry_function_id.to_string(),
+    }
+}
+
+/// Standardizes every address in a type string or pattern so short and long forms compare
+/// equal. Addresses ending in a wildcard are kept as written.
+pub fn standardize_type_addresses(type_str: &str) -> String {
+    TYPE_ADDRESS_REGEX
+        .replace_all(type_str, |caps: &regex::Captures| {
+            let address = &caps[0];
+            if address.ends_with('*') {
+                address.to_lowercase()
+            } else {
+                standardize_address(&address.to_lowercase())
+            }
+        })
+        .into_owned()
+}
+
 /// Standardizes all addresses and table handles to be length 66 (0x-64 length hash) that takes in a slice.
 pub fn standardize_address_from_bytes(bytes: &[u8]) -> String {
     let encdoed_bytes = hex::encode(bytes);
@@ -2,19 +2,32 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use crate::{
-    config::IndexerGrpcHttp2Config,
-    db::common::models::{ledger_info::LedgerInfo, processor_status::ProcessorStatusQuery},
+    bq_analytics::ParquetProcessingResult,
+    config::{GrpcTlsConfig, IndexerGrpcHttp2Config},
+    db::common::models::{
+        ledger_info::LedgerInfo, processor_ranges::ProcessorRangeQuery,
+        processor_status::ProcessorStatusQuery,
+    },
     gap_detectors::{
         create_gap_detector_status_tracker_loop, gap_detector::DefaultGapDetector,
         parquet_gap_detector::ParquetFileGapDetectorInner, GapDetector, ProcessingResult,
     },
+    grpc_endpoints::{GrpcEndpointConfig, GrpcEndpointSelector, GrpcFailoverConfig},
     grpc_stream::TransactionsPBResponse,
     processors::{
         account_transactions_processor::AccountTransactionsProcessor,
         ans_processor::AnsProcessor,
+        collection_stats_processor::CollectionStatsProcessor,
         default_processor::DefaultProcessor,
         events_processor::EventsProcessor,
         fungible_asset_processor::FungibleAssetProcessor,
+        launchpad_processor::LaunchpadProcessor,
+        marketplace_processor::MarketplaceProcessor,
+        mercato_account_processor::MercatoAccountProcessor,
+        mercato_indexer_processor::MercatoIndexerProcessor,
+        mercato_processor::MercatoProcessor,
+        mercato_token_processor::MercatoTokenProcessor,
+        mercato_token_v2_processor::MercatoTokenV2Processor,
         monitoring_processor::MonitoringProcessor,
         nft_metadata_processor::NftMetadataProcessor,
         objects_processor::ObjectsProcessor,
@@ -30,15 +43,15 @@ use crate::{
         token_v2_processor::TokenV2Processor,
         transaction_metadata_processor::TransactionMetadataProcessor,
         user_transaction_processor::UserTransactionProcessor,
-        mercato_account_processor::MercatoAccountProcessor, 
-        mercato_processor::MercatoProcessor, 
-        mercato_token_processor::MercatoTokenProcessor, 
-        mercato_token_v2_processor::MercatoTokenV2Processor, 
-        mercato_indexer_processor::MercatoIndexerProcessor,
         DefaultProcessingResult, Processor, ProcessorConfig, ProcessorTrait,
     },
     schema::ledger_infos,
     transaction_filter::TransactionFilter,
+    transaction_sources::{
+        file_source::FileTransactionSource,
+        recorder::{RecordConfig, Recorder},
+        FetchRange, GrpcTransactionSource, TransactionSource, TransactionSourceConfig,
+    },
     utils::{
         counters::{
             ProcessorStep, GRPC_LATENCY_BY_PROCESSOR_IN_SECS, LATEST_PROCESSED_VERSION,
@@ -52,7 +65,12 @@ use crate::{
         database::{
             execute_with_better_error_conn, new_db_pool, run_pending_migrations, ArcDbPool,
         },
-        util::{time_diff_since_pb_timestamp_in_secs, timestamp_to_iso, timestamp_to_unixtime},
+        run_summary::RunSummary,
+        shutdown,
+        util::{
+            naive_datetime_to_timestamp, time_diff_since_pb_timestamp_in_secs, timestamp_to_iso,
+            timestamp_to_unixtime,
+        },
     },
 };
 use ahash::AHashMap;
@@ -62,9 +80,12 @@ use bitflags::bitflags;
 use kanal::AsyncSender;
 use std::{
     collections::HashSet,
+    path::PathBuf,
     sync::{Arc, Mutex},
+    time::Duration,
 };
 use tokio::task::JoinHandle;
+use tonic::transport::ClientTlsConfig;
 use tracing::{debug, error, info};
 use url::Url;
 // this is how large the fetch queue should be. Each bucket should have a max of 80MB or so, so a batch
@@ -123,6 +144,14 @@ pub struct Worker {
     pub indexer_grpc_data_service_address: Url,
     pub grpc_http2_config: IndexerGrpcHttp2Config,
     pub auth_token: String,
+    pub additional_grpc_endpoints: Vec<GrpcEndpointConfig>,
+    pub grpc_failover_config: GrpcFailoverConfig,
+    pub grpc_tls_config: Option<ClientTlsConfig>,
+    pub transaction_source: TransactionSourceConfig,
+    pub record: Option<RecordConfig>,
+    pub shutdown_drain_timeout: Duration,
+    pub shutdown_flush_timeout: Duration,
+    pub run_summary_path: Option<PathBuf>,
     pub starting_version: Option<u64>,
     pub ending_version: Option<u64>,
     pub number_concurrent_processing_tasks: usize,
@@ -145,6 +174,14 @@ impl Worker {
         indexer_grpc_data_service_address: Url,
         grpc_http2_config: IndexerGrpcHttp2Config,
         auth_token: String,
+        additional_grpc_endpoints: Vec<GrpcEndpointConfig>,
+        grpc_failover_config: GrpcFailoverConfig,
+        grpc_tls_config: Option<GrpcTlsConfig>,
+        transaction_source: TransactionSourceConfig,
+        record: Option<RecordConfig>,
+        shutdown_drain_timeout: Duration,
+        shutdown_flush_timeout: Duration,
+        run_summary_path: Option<PathBuf>,
         starting_version: Option<u64>,
         ending_version: Option<u64>,
         number_concurrent_processing_tasks: Option<usize>,
@@ -177,6 +214,39 @@ impl Worker {
         );
         let number_concurrent_processing_tasks = number_concurrent_processing_tasks.unwrap_or(10);
 
+        let grpc_tls_config = match grpc_tls_config {
+            Some(grpc_tls_config) => {
+                // tonic only uses TLS for https addresses, so the certificates would be silently ignored
+                for address in std::iter::once(&indexer_grpc_data_service_address)
+                    .chain(additional_grpc_endpoints.iter().map(|e| &e.address))
+                {
+                    if address.scheme() != "https" {
+                        anyhow::bail!(
+                            "grpc_tls_config is set but GRPC address {} is not https",
+                            address
+                        );
+                    }
+                }
+                Some(
+                    grpc_tls_config
+                        .build()
+                        .context("Failed to load grpc_tls_config")?,
+                )
+            },
+            None => None,
+        };
+        if record.is_some() && !matches!(transaction_source, TransactionSourceConfig::Grpc) {
+            anyhow::bail!("record is only supported with the grpc transaction source");
+        }
+        let transaction_filter = transaction_filter
+            .normalized()
+            .context("Invalid transaction_filter")?;
+        info!(
+            processor_name = processor_name,
+            transaction_filter = serde_json::to_string(&transaction_filter)?,
+            "[Parser] Using transaction filter"
+        );
+
         let mut deprecated_tables_flags = TableFlags::empty();
         for table in deprecated_tables.iter() {
             if let Some(flags) = TableFlags::from_name(table) {
@@ -193,6 +263,14 @@ impl Worker {
             starting_version,
             ending_version,
             auth_token,
+            additional_grpc_endpoints,
+            grpc_failover_config,
+            grpc_tls_config,
+            transaction_source,
+            record,
+            shutdown_drain_timeout,
+            shutdown_flush_timeout,
+            run_summary_path,
             number_concurrent_processing_tasks,
             gap_detection_batch_size,
             parquet_gap_detection_batch_size,
@@ -212,7 +290,10 @@ impl Worker {
     /// 3. Start a loop to consume from the buffer. We will have Y threads to process the transactions in parallel. (Y should be less than X for obvious reasons)
     ///   * Note that the batches will be sequential so we won't have problems with gaps
     /// 4. We will keep track of the last processed version and monitoring things like TPS
-    pub async fn run(&mut self) {
+    /// 5. Once the fetcher reaches the ending version or a shutdown signal arrives, drain, write the
+    ///    final processor status and report a run summary. Errors if the requested range wasn't
+    ///    fully processed.
+    pub async fn run(&mut self) -> Result<()> {
         let processor_name = self.processor_config.name();
         info!(
             processor_name = processor_name,
@@ -253,18 +334,78 @@ impl Worker {
             "[Parser] Building processor",
         );
 
+        // Ranges a previous run processed ahead of a gap are skipped: only the gaps are fetched
+        // before resuming from the last processed range. They are relative to processor_status,
+        // so a starting_version from the config reprocesses everything after it; the gap
+        // detector's first write then replaces the stored ranges.
+        let processed_ranges =
+            if self.processor_config.is_parquet_processor() || self.starting_version.is_some() {
+                vec![]
+            } else {
+                self.get_processed_ranges(starting_version)
+                    .await
+                    .expect("[Parser] Database error when getting processed ranges")
+            };
+        let fetch_ranges = FetchRange::around_processed_ranges(
+            starting_version,
+            self.ending_version,
+            &processed_ranges
+                .iter()
+                .map(|range| (range.start_version, range.end_version))
+                .collect::<Vec<_>>(),
+        );
+        if !processed_ranges.is_empty() {
+            info!(
+                processor_name = processor_name,
+                service_type = PROCESSOR_SERVICE_TYPE,
+                num_processed_ranges = processed_ranges.len(),
+                fetch_ranges = ?fetch_ranges,
+                "[Parser] Backfilling gaps before resuming",
+            );
+        }
+
         let concurrent_tasks = self.number_concurrent_processing_tasks;
 
+        // The fetcher owns the recorder; the recording is finished here once the fetcher is gone
+        let (recorder, recording) = match (&self.transaction_source, &self.record) {
+            (TransactionSourceConfig::Grpc, Some(config)) => {
+                let (recorder, recording) = Recorder::start(config.clone(), processor_name)
+                    .expect("[Parser] Failed to start recording");
+                (Some(recorder), Some(recording))
+            },
+            _ => (None, None),
+        };
+        let mut transaction_source: Box<dyn TransactionSource> = match &self.transaction_source {
+            TransactionSourceConfig::Grpc => Box::new(GrpcTransactionSource {
+                endpoints: GrpcEndpointSelector::new(
+                    self.indexer_grpc_data_service_address.clone(),
+                    self.auth_token.clone(),
+                    &self.additional_grpc_endpoints,
+                    self.grpc_failover_config.clone(),
+                    self.grpc_tls_config.clone(),
+                    processor_name,
+                ),
+                http2_ping_interval: self.grpc_http2_config.grpc_http2_ping_interval_in_secs(),
+                http2_ping_timeout: self.grpc_http2_config.grpc_http2_ping_timeout_in_secs(),
+                reconnection_timeout: self.grpc_http2_config.grpc_connection_timeout_secs(),
+                response_item_timeout: Duration::from_secs(self.grpc_response_item_timeout_in_secs),
+                processor_name: processor_name.to_string(),
+                transaction_filter: self.transaction_filter.clone(),
+                pb_channel_txn_chunk_size: self.pb_channel_txn_chunk_size,
+                recorder,
+            }),
+            TransactionSourceConfig::File(config) => Box::new(FileTransactionSource {
+                config: config.clone(),
+                processor_name: processor_name.to_string(),
+                transaction_filter: self.transaction_filter.clone(),
+            }),
+        };
+
         // get the chain id
-        let chain_id = crate::grpc_stream::get_chain_id(
-            self.indexer_grpc_data_service_address.clone(),
-            self.grpc_http2_config.grpc_http2_ping_interval_in_secs(),
-            self.grpc_http2_config.grpc_http2_ping_timeout_in_secs(),
-            self.grpc_http2_config.grpc_connection_timeout_secs(),
-            self.auth_token.clone(),
-            processor_name.to_string(),
-        )
-        .await;
+        let chain_id = transaction_source
+            .chain_id()
+            .await
+            .expect("[Parser] Failed to get chain id");
         self.check_or_update_chain_id(chain_id as i64)
             .await
             .unwrap();
@@ -272,25 +413,13 @@ impl Worker {
         self.grpc_chain_id = Some(chain_id);
 
         let ending_version = self.ending_version;
-        let indexer_grpc_data_service_address = self.indexer_grpc_data_service_address.clone();
-        let indexer_grpc_http2_ping_interval =
-            self.grpc_http2_config.grpc_http2_ping_interval_in_secs();
-        let indexer_grpc_http2_ping_timeout =
-            self.grpc_http2_config.grpc_http2_ping_timeout_in_secs();
-        let indexer_grpc_reconnection_timeout_secs =
-            self.grpc_http2_config.grpc_connection_timeout_secs();
-        let pb_channel_txn_chunk_size = self.pb_channel_txn_chunk_size;
-
-        // Create a transaction fetcher thread that will continuously fetch transactions from the GRPC stream
+        let processing_start_time = std::time::Instant::now();
+
+        // Create a transaction fetcher thread that will continuously fetch transactions from the source
         // and write into a channel
         // TODO: change channel size based on number_concurrent_processing_tasks
         let (tx, receiver) = kanal::bounded_async::<TransactionsPBResponse>(BUFFER_SIZE);
-        let request_ending_version = self.ending_version;
-        let auth_token = self.auth_token.clone();
-        let transaction_filter = self.transaction_filter.clone();
-        let grpc_response_item_timeout =
-            std::time::Duration::from_secs(self.grpc_response_item_timeout_in_secs);
-        let fetcher_task = tokio::spawn(async move {
+        let mut fetcher_task = tokio::spawn(async move {
             info!(
                 processor_name = processor_name,
                 service_type = PROCESSOR_SERVICE_TYPE,
@@ -299,21 +428,7 @@ impl Worker {
                 "[Parser] Starting fetcher thread"
             );
 
-            crate::grpc_stream::create_fetcher_loop(
-                tx.clone(),
-                indexer_grpc_data_service_address.clone(),
-                indexer_grpc_http2_ping_interval,
-                indexer_grpc_http2_ping_timeout,
-                indexer_grpc_reconnection_timeout_secs,
-                grpc_response_item_timeout,
-                starting_version,
-                request_ending_version,
-                auth_token.clone(),
-                processor_name.to_string(),
-                transaction_filter,
-                pb_channel_txn_chunk_size,
-            )
-            .await
+            transaction_source.run(tx, fetch_ranges).await
         });
 
         // Create a gap detector task that will panic if there is a gap in the processing
@@ -342,18 +457,21 @@ impl Worker {
                 ParquetFileGapDetectorInner::new(starting_version),
             )))
         } else {
-            GapDetector::DefaultGapDetector(DefaultGapDetector::new(starting_version))
+            GapDetector::DefaultGapDetector(DefaultGapDetector::with_processed_ranges(
+                starting_version,
+                processed_ranges,
+            ))
         };
         let gap_detector_clone = gap_detector.clone();
 
-        tokio::spawn(async move {
+        let gap_detector_task = tokio::spawn(async move {
             create_gap_detector_status_tracker_loop(
                 gap_detector_clone,
                 gap_detector_receiver,
                 processor,
                 gap_detection_batch_size,
             )
-            .await;
+            .await
         });
 
         // This is the consumer side of the channel. These are the major states:
@@ -371,7 +489,7 @@ impl Worker {
             "[Parser] Spawning concurrent parallel processor tasks",
         );
 
-        let mut processor_tasks = vec![fetcher_task];
+        let mut processor_tasks = vec![];
         for task_index in 0..concurrent_tasks {
             let join_handle: JoinHandle<()> = self
                 .launch_processor_task(
@@ -392,10 +510,105 @@ impl Worker {
             "[Parser] Processor tasks spawned",
         );
 
-        // Await the processor tasks: this is forever
-        futures::future::try_join_all(processor_tasks)
-            .await
-            .expect("[Processor] Processor tasks have died");
+        // Await the processor tasks: this is forever unless there is an ending version or a
+        // shutdown signal
+        let processor_tasks = futures::future::try_join_all(processor_tasks);
+        tokio::pin!(processor_tasks);
+        let stopped_by_signal = tokio::select! {
+            res = futures::future::try_join(&mut fetcher_task, &mut processor_tasks) => {
+                res.expect("[Processor] Processor tasks have died");
+                false
+            },
+            _ = shutdown::wait_for_signal() => {
+                info!(
+                    processor_name = processor_name,
+                    service_type = PROCESSOR_SERVICE_TYPE,
+                    channel_size = receiver.len(),
+                    "[Parser] Received shutdown signal, draining the transaction channel",
+                );
+                // Without a fetcher the processor tasks exit once the channel is empty
+                fetcher_task.abort();
+                // Waiting for the aborted fetcher drops the transaction source and its recorder
+                let _ = (&mut fetcher_task).await;
+                match tokio::time::timeout(self.shutdown_drain_timeout, &mut processor_tasks).await {
+                    Ok(Ok(_)) => {},
+                    Ok(Err(e)) => error!(
+                        processor_name = processor_name,
+                        service_type = PROCESSOR_SERVICE_TYPE,
+                        error = ?e,
+                        "[Parser] A processor task failed while draining",
+                    ),
+                    Err(_) => {
+                        error!(
+                            processor_name = processor_name,
+                            service_type = PROCESSOR_SERVICE_TYPE,
+                            channel_size = receiver.len(),
+                            "[Parser] Timed out draining the transaction channel, dropping the remaining batches",
+                        );
+                        receiver.close();
+                    },
+                }
+                true
+            },
+        };
+
+        if let Some(recording) = recording {
+            recording.finish().await;
+        }
+
+        // Parquet handlers upload their buffers and exit. Once every sender is gone the gap
+        // detector writes the last contiguous version to processor_status.
+        shutdown::trigger();
+        drop(gap_detector_sender);
+        let last_processed_version =
+            match tokio::time::timeout(self.shutdown_flush_timeout, gap_detector_task).await {
+                Ok(Ok(last_processed_version)) => {
+                    info!(
+                        processor_name = processor_name,
+                        service_type = PROCESSOR_SERVICE_TYPE,
+                        last_processed_version = last_processed_version,
+                        "[Parser] Processor stopped",
+                    );
+                    last_processed_version
+                },
+                Ok(Err(e)) => {
+                    error!(
+                        processor_name = processor_name,
+                        service_type = PROCESSOR_SERVICE_TYPE,
+                        error = ?e,
+                        "[Parser] Gap detector task failed",
+                    );
+                    None
+                },
+                Err(_) => {
+                    error!(
+                        processor_name = processor_name,
+                        service_type = PROCESSOR_SERVICE_TYPE,
+                        "[Parser] Timed out waiting for the final processor status update",
+                    );
+                    None
+                },
+            };
+
+        let summary = RunSummary::new(
+            processor_name,
+            chain_id,
+            starting_version,
+            ending_version,
+            last_processed_version,
+            processing_start_time.elapsed(),
+        );
+        summary.report(self.run_summary_path.as_deref())?;
+        if let Some(ending_version) = ending_version {
+            if !stopped_by_signal && !summary.complete {
+                anyhow::bail!(
+                    "[Parser] Stopped at version {:?} before reaching ending_version {}",
+                    last_processed_version,
+                    ending_version
+                );
+            }
+        }
+        Ok(())
     }
 
     async fn launch_processor_task(
@@ -561,11 +774,12 @@ impl Worker {
                                 let processing_time = processing_time.elapsed().as_secs_f64();
 
                                 // We've processed things: do some data and metrics
-                                ma.tick_now((last_txn_version - first_txn_version) + 1);
+                                // Count the whole stream range, filtered out transactions included
+                                let num_processed =
+                                    (batch_last_txn_version - batch_first_txn_version) + 1;
+                                ma.tick_now(num_processed);
                                 let tps = ma.avg().ceil() as u64;
 
-                                let num_processed = (last_txn_version - first_txn_version) + 1;
-
                                 debug!(
                                     processor_name = processor_name,
                                     service_type = PROCESSOR_SERVICE_TYPE,
@@ -604,7 +818,7 @@ impl Worker {
                                         label,
                                         &task_index_str,
                                     ])
-                                    .set(last_txn_version as i64);
+                                    .set(batch_last_txn_version as i64);
                                 TRANSACTION_UNIX_TIMESTAMP
                                     .with_label_values(&[
                                         processor_name,
@@ -658,7 +872,8 @@ impl Worker {
                                     _ => panic!("Invalid gap detector type"),
                                 };
 
-                                let num_processed = (last_txn_version - first_txn_version) + 1;
+                                let num_processed =
+                                    (batch_last_txn_version - batch_first_txn_version) + 1;
 
                                 NUM_TRANSACTIONS_PROCESSED_COUNT
                                     .with_label_values(&[
@@ -751,6 +966,40 @@ impl Worker {
         }
     }
 
+    /// Ranges processed ahead of a gap by a previous run, clipped to the versions this run
+    /// covers
+    pub async fn get_processed_ranges(
+        &self,
+        starting_version: u64,
+    ) -> Result<Vec<DefaultProcessingResult>> {
+        let mut conn = self.db_pool.get().await?;
+
+        let ranges =
+            ProcessorRangeQuery::get_by_processor(self.processor_config.name(), &mut conn).await?;
+        Ok(ranges
+            .into_iter()
+            .filter_map(|range| {
+                let start_version = (range.start_version as u64).max(starting_version);
+                let end_version = match self.ending_version {
+                    Some(ending_version) => (range.end_version as u64).min(ending_version),
+                    None => range.end_version as u64,
+                };
+                // The timestamp belongs to the range's last version, so drop it when clipped
+                let last_transaction_timestamp = range
+                    .last_transaction_timestamp
+                    .filter(|_| end_version == range.end_version as u64)
+                    .map(naive_datetime_to_timestamp);
+                (start_version <= end_version).then_some(DefaultProcessingResult {
+                    start_version,
+                    end_version,
+                    last_transaction_timestamp,
+                    processing_duration_in_secs: 0.0,
+                    db_insertion_duration_in_secs: 0.0,
+                })
+            })
+            .collect())
+    }
+
     /// Verify the chain id from GRPC against the database.
     pub async fn check_or_update_chain_id(&self, grpc_chain_id: i64) -> Result<u64> {
         let processor_name = self.processor_config.name();
@@ -840,6 +1089,19 @@ pub async fn do_processor(
 
     // Fake this as it's possible we have filtered out all of the txns in this batch
     if transactions_pb.transactions.is_empty() {
+        if processor.is_parquet_processor() {
+            // Without structs to wait for, the gap detector marks the whole range as processed
+            return Ok(ProcessingResult::ParquetProcessingResult(
+                ParquetProcessingResult {
+                    start_version: start_version as i64,
+                    end_version: end_version as i64,
+                    last_transaction_timestamp: transactions_pb.end_txn_timestamp,
+                    txn_version_to_struct_count: Some(AHashMap::new()),
+                    parquet_processed_structs: None,
+                    table_name: "".to_string(),
+                },
+            ));
+        }
         return Ok(ProcessingResult::DefaultProcessingResult(
             DefaultProcessingResult {
                 start_version,
@@ -852,6 +1114,7 @@ pub async fn do_processor(
     }
 
     let txn_time = transactions_pb.start_txn_timestamp;
+    let end_txn_timestamp = transactions_pb.end_txn_timestamp;
 
     if let Some(ref t) = txn_time {
         PROCESSOR_DATA_RECEIVED_LATENCY_IN_SECS
@@ -873,7 +1136,7 @@ pub async fn do_processor(
         );
     }
 
-    let processed_result = processor
+    let mut processed_result = processor
         .process_transactions(
             transactions_pb.transactions,
             start_version,
@@ -881,6 +1144,12 @@ pub async fn do_processor(
             Some(db_chain_id),
         )
         .await;
+    // Processors only see the transactions left after filtering, the batch ends at end_version
+    if let Ok(ProcessingResult::DefaultProcessingResult(result)) = processed_result.as_mut() {
+        if end_txn_timestamp.is_some() {
+            result.last_transaction_timestamp = end_txn_timestamp;
+        }
+    }
 
     if let Some(ref t) = txn_time {
         PROCESSOR_DATA_PROCESSED_LATENCY_IN_SECS
@@ -918,9 +1187,12 @@ pub fn build_processor(
             per_table_chunk_sizes,
             deprecated_tables,
         )),
-        ProcessorConfig::MercatoProcessor => {
-            Processor::from(MercatoProcessor::new(db_pool, per_table_chunk_sizes, deprecated_tables))
-        },
+        ProcessorConfig::MercatoProcessor(config) => Processor::from(MercatoProcessor::new(
+            db_pool,
+            config.clone(),
+            per_table_chunk_sizes,
+            deprecated_tables,
+        )),
         ProcessorConfig::EventsProcessor => {
             Processor::from(EventsProcessor::new(db_pool, per_table_chunk_sizes))
         },
@@ -950,29 +1222,35 @@ pub fn build_processor(
             per_table_chunk_sizes,
             deprecated_tables,
         )),
-        ProcessorConfig::MercatoTokenProcessor(config) => Processor::from(MercatoTokenProcessor::new(
-            db_pool,
-            config.clone(),
-            per_table_chunk_sizes,
-        )),
-        ProcessorConfig::MercatoTokenV2Processor(config) => Processor::from(MercatoTokenV2Processor::new(
-            db_pool,
-            config.clone(),
-            per_table_chunk_sizes,
-        )),
-        ProcessorConfig::MercatoIndexerProcessor => Processor::from(MercatoIndexerProcessor::new(
-            db_pool,
-            per_table_chunk_sizes,
-        )),
+        ProcessorConfig::MercatoTokenProcessor(config) => Processor::from(
+            MercatoTokenProcessor::new(db_pool, config.clone(), per_table_chunk_sizes),
+        ),
+        ProcessorConfig::MercatoTokenV2Processor(config) => Processor::from(
+            MercatoTokenV2Processor::new(db_pool, config.clone(), per_table_chunk_sizes),
+        ),
+        ProcessorConfig::MercatoIndexerProcessor(config) => Processor::from(
+            MercatoIndexerProcessor::new(db_pool, config.clone(), per_table_chunk_sizes),
+        ),
         ProcessorConfig::TransactionMetadataProcessor => Processor::from(
             TransactionMetadataProcessor::new(db_pool, per_table_chunk_sizes),
         ),
         ProcessorConfig::UserTransactionProcessor => Processor::from(
             UserTransactionProcessor::new(db_pool, per_table_chunk_sizes, deprecated_tables),
         ),
-        ProcessorConfig::MercatoAccountProcessor => Processor::from(
-            MercatoAccountProcessor::new(db_pool, per_table_chunk_sizes),
+        ProcessorConfig::MercatoAccountProcessor => {
+            Processor::from(MercatoAccountProcessor::new(db_pool, per_table_chunk_sizes))
+        },
+        ProcessorConfig::MarketplaceProcessor(config) => Processor::from(
+            MarketplaceProcessor::new(db_pool, config.clone(), per_table_chunk_sizes),
+        ),
+        ProcessorConfig::CollectionStatsProcessor(config) => Processor::from(
+            CollectionStatsProcessor::new(db_pool, config.clone(), per_table_chunk_sizes),
         ),
+        ProcessorConfig::LaunchpadProcessor(config) => Processor::from(LaunchpadProcessor::new(
+            db_pool,
+            config.clone(),
+            per_table_chunk_sizes,
+        )),
         ProcessorConfig::ParquetDefaultProcessor(config) => {
             Processor::from(ParquetDefaultProcessor::new(
                 db_pool,